@@ -1,5 +1,4 @@
 // cargo bench --bench=as_ref
-#![feature(maybe_uninit_uninit_array)]
 #![feature(maybe_uninit_array_assume_init)]
 extern crate wynn_build_tools;
 
@@ -24,7 +23,7 @@ fn array_as_ref<TR, T, const LEN: usize>(array: &[TR; LEN]) -> [&T; LEN]
 where
     TR: AsRef<T>,
 {
-    let mut result: [MaybeUninit<&T>; LEN] = MaybeUninit::uninit_array();
+    let mut result: [MaybeUninit<&T>; LEN] = [const { MaybeUninit::uninit() }; LEN];
     for i in 0..LEN {
         result[i].write(array[i].as_ref());
     }
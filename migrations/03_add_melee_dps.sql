@@ -0,0 +1 @@
+ALTER TABLE build ADD COLUMN melee_dps INTEGER NOT NULL DEFAULT 0;
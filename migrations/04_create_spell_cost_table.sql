@@ -0,0 +1,9 @@
+ALTER TABLE build ADD COLUMN mana_sustain REAL NOT NULL DEFAULT 0;
+
+CREATE TABLE
+	IF NOT EXISTS spell_cost (
+        name TEXT NOT NULL,
+        cost REAL NOT NULL,
+        build_id INTEGER NOT NULL,
+        FOREIGN KEY (build_id) REFERENCES build(row_id)
+    );
@@ -0,0 +1 @@
+ALTER TABLE build ADD COLUMN weapon TEXT NOT NULL DEFAULT '';
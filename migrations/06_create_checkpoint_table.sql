@@ -0,0 +1,6 @@
+CREATE TABLE
+	IF NOT EXISTS checkpoint (
+        run TEXT NOT NULL,
+        segment INTEGER NOT NULL,
+        PRIMARY KEY (run, segment)
+    );
@@ -0,0 +1 @@
+ALTER TABLE build RENAME COLUMN ari_original TO air_original;
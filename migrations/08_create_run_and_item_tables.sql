@@ -0,0 +1,29 @@
+CREATE TABLE
+	IF NOT EXISTS run (
+        row_id INTEGER PRIMARY KEY AUTOINCREMENT,
+        key TEXT NOT NULL,
+        config TEXT NOT NULL,
+        items_version TEXT NOT NULL,
+        started_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+    );
+
+CREATE TABLE
+	IF NOT EXISTS item (
+        row_id INTEGER PRIMARY KEY AUTOINCREMENT,
+        name TEXT NOT NULL,
+        type TEXT NOT NULL,
+        items_version TEXT NOT NULL,
+        UNIQUE (name, items_version)
+    );
+
+CREATE TABLE
+	IF NOT EXISTS build_item (
+        build_id INTEGER NOT NULL,
+        slot TEXT NOT NULL,
+        item_id INTEGER NOT NULL,
+        PRIMARY KEY (build_id, slot),
+        FOREIGN KEY (build_id) REFERENCES build(row_id),
+        FOREIGN KEY (item_id) REFERENCES item(row_id)
+    );
+
+ALTER TABLE build ADD COLUMN run_id INTEGER REFERENCES run(row_id);
@@ -0,0 +1,13 @@
+CREATE INDEX IF NOT EXISTS build_run_id ON build(run_id);
+CREATE INDEX IF NOT EXISTS build_max_ehp ON build(max_ehp);
+CREATE INDEX IF NOT EXISTS build_max_hp ON build(max_hp);
+CREATE INDEX IF NOT EXISTS build_max_mr ON build(max_mr);
+CREATE INDEX IF NOT EXISTS build_max_ms ON build(max_ms);
+CREATE INDEX IF NOT EXISTS build_max_spd ON build(max_spd);
+CREATE INDEX IF NOT EXISTS build_max_ls ON build(max_ls);
+CREATE INDEX IF NOT EXISTS build_melee_dps ON build(melee_dps);
+CREATE INDEX IF NOT EXISTS build_mana_sustain ON build(mana_sustain);
+CREATE INDEX IF NOT EXISTS damage_build_id ON damage(build_id);
+CREATE INDEX IF NOT EXISTS damage_name_avg ON damage(name, avg);
+CREATE INDEX IF NOT EXISTS spell_cost_build_id ON spell_cost(build_id);
+CREATE INDEX IF NOT EXISTS build_item_item_id ON build_item(item_id);
@@ -0,0 +1,11 @@
+CREATE TABLE
+	IF NOT EXISTS build_run (
+        build_id INTEGER NOT NULL,
+        run_id INTEGER NOT NULL,
+        PRIMARY KEY (build_id, run_id),
+        FOREIGN KEY (build_id) REFERENCES build(row_id),
+        FOREIGN KEY (run_id) REFERENCES run(row_id)
+    );
+CREATE INDEX IF NOT EXISTS build_run_run_id ON build_run(run_id);
+
+INSERT OR IGNORE INTO build_run(build_id, run_id) SELECT row_id, run_id FROM build WHERE run_id IS NOT NULL;
@@ -1,4 +1,8 @@
+mod builder_args;
 mod db;
+mod estimate;
+mod output;
+mod search;
 
 use std::fmt::Write;
 use std::{
@@ -6,15 +10,18 @@ use std::{
     fmt,
     fs::File,
     io::BufReader,
+    path::Path,
     sync::{
-        atomic::{AtomicUsize, Ordering},
-        Arc,
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex,
     },
-    time::Duration,
+    time::{Duration, Instant},
 };
 
+use builder_args::{BuilderArgs, Slot};
+use clap::Parser;
 use itertools::Itertools;
-use tokio::{runtime::Runtime, spawn, time::sleep};
+use tokio::{spawn, time::sleep};
 
 use wynn_build_tools::calculate::*;
 use wynn_build_tools::config::*;
@@ -22,15 +29,41 @@ use wynn_build_tools::items::*;
 use wynn_build_tools::network::*;
 use wynn_build_tools::util::*;
 
+use output::{Build, Sink, Writer};
+use search::{ObjectiveBound, SearchBound};
+
 const SPLIT_STR: &str = ".";
+/// combinations of the slots other than the rings of a segment of the random order
+const SEGMENT_SIZE: usize = 1000;
+/// where the hppeng ids of the tomes come from, see `load_hppeng_tome_id_map`
+const TOME_ID_HINT: &str =
+    "put hppeng's tomes.json at assets/tomes.json, or add the tomes to assets/tome_id_map.json";
 
 #[tokio::main]
 async fn main() {
-    let config = load_config("config/config.toml").await.unwrap();
-    let hppeng_codes: HppengCodes = HppengCodes::split_hppeng_url(&config.hppeng.template_url);
+    let args = BuilderArgs::parse();
+    let (mut config, config_table) = load_config_with_overrides(&args.config, &args.overrides())
+        .await
+        .unwrap_or_else(|err| {
+            eprintln!("can not load the config {}: {}", args.config, err);
+            std::process::exit(1);
+        });
+    args.apply(&mut config);
+    if let Some(threads) = args.threads {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads as usize)
+            .build_global()
+            .unwrap();
+    }
+    let mut hppeng_codes: HppengCodes =
+        HppengCodes::split_hppeng_url(&config.hppeng.template_url);
     let hppeng_id_map = load_hppeng_id_map();
+    let hppeng_tome_id_map = load_hppeng_tome_id_map();
 
-    let (apparels, weapons) = match load_from_wapi(&config.hppeng.items_file) {
+    let powders = config.powders(decode_powders(&hppeng_codes.powder).unwrap());
+    hppeng_codes.powder = encode_powders(&powders);
+
+    let (mut apparels, weapons, tomes) = match load_from_wapi(&config.hppeng.items_file) {
         Ok(v) => v,
         Err(_) => {
             let api_fetch_attempt =
@@ -50,119 +83,820 @@ async fn main() {
         }
     };
 
-    let weapon = weapons
-        .iter()
-        .find(|v| v.name == config.items.weapon)
-        .unwrap();
-
     let abilities = load_abilities();
+    // the items of --from-url are the candidates, the names of its rings are kept to evaluate it
+    let url_rings = args.from_url.as_ref().map(|url| {
+        decode_build(url, &apparels, &weapons, &abilities, &hppeng_id_map)
+            .and_then(|build| {
+                url_candidates(&mut config.items, &build, &args.around)?;
+                Ok([build.apparels[4], build.apparels[5]].map(|ring| ring.map(|v| v.name.clone())))
+            })
+            .unwrap_or_else(|err| {
+                eprintln!("can not start from {}: {}", url, err);
+                std::process::exit(1);
+            })
+    });
+
+    // powders are applied to every candidate of the slot, up to the powder slots of the candidate
+    let mut capped = Vec::new();
+    for (apparels, powders) in apparels.iter_mut().zip(&powders[..4]) {
+        apparels.iter_mut().for_each(|apparel| {
+            if (apparel.slots.max(0) as usize) < powders.len() {
+                capped.push(format!("{} ({} slots)", apparel.name, apparel.slots));
+            }
+            apparel.apply_powders(powders)
+        });
+    }
+    let mut weapons = find_weapons(&weapons, config.items.weapons()).unwrap();
+    weapons.iter_mut().for_each(|weapon| {
+        if (weapon.slots.max(0) as usize) < powders[4].len() {
+            capped.push(format!("{} ({} slots)", weapon.name, weapon.slots));
+        }
+        weapon.apply_powders(&powders[4])
+    });
+    if !capped.is_empty() {
+        println!(
+            "the powders beyond the powder slots are dropped for: {}",
+            capped.join(", ")
+        );
+    }
+    // the weapons share the class, so the atree and the melee ability are the same for all of them
+    let weapon = &weapons[0];
+
+    // a tome without a hppeng id can not be put into the urls, nor read from them
+    let tomes = match &config.items.tomes {
+        Some(names) => {
+            let tomes = find_tomes(&tomes, names).unwrap_or_else(|err| {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            });
+            let missing: Vec<&String> = tomes
+                .iter()
+                .filter(|tome| !hppeng_tome_id_map.contains_key(&tome.name))
+                .map(|tome| &tome.name)
+                .collect();
+            if missing.is_empty() {
+                hppeng_codes.tomes =
+                    encode_tomes(&tomes, &hppeng_tome_id_map).unwrap_or_else(|err| {
+                        eprintln!("{}", err);
+                        std::process::exit(1);
+                    });
+            } else {
+                println!(
+                    "the tomes {} have no hppeng id, the urls keep the tomes of template_url\n{}",
+                    missing.iter().join(", "),
+                    TOME_ID_HINT
+                );
+            }
+            tomes
+        }
+        None => {
+            let (tomes, missing) =
+                find_decoded_tomes(&hppeng_codes.tomes, &tomes, &hppeng_tome_id_map)
+                    .unwrap_or_else(|err| {
+                        eprintln!("{} in template_url", err);
+                        std::process::exit(1);
+                    });
+            if !missing.is_empty() {
+                println!(
+                    "the tome ids {} of template_url are unknown, their stats are not counted\n{}",
+                    missing.iter().join(", "),
+                    TOME_ID_HINT
+                );
+            }
+            tomes
+        }
+    };
+    let tome_stat = TomeStat::sum(&tomes);
+
     let active_abilities = decode_atree(
         &abilities.get(&Class::from(weapon)).unwrap(),
         &hppeng_codes.ability,
     );
-    let (common_stat, dam_raw, dam_pct, dam_add, mut spells) = atree_merge(&active_abilities);
-    validate_config_damages(&spells, &config).unwrap_or_else(|err| {
+    let mut candidates: [Vec<&Apparel>; 7] = [
+        find(&apparels[0], &config.items.helmets).unwrap(),
+        find(&apparels[1], &config.items.chest_plates).unwrap(),
+        find(&apparels[2], &config.items.leggings).unwrap(),
+        find(&apparels[3], &config.items.boots).unwrap(),
+        find(&apparels[5], &config.items.bracelets).unwrap(),
+        find(&apparels[6], &config.items.necklaces).unwrap(),
+        find(&apparels[4], &config.items.rings).unwrap(),
+    ];
+
+    // sets.json is kept next to the items file, the items file has no set bonuses
+    let sets_file = Path::new(&config.hppeng.items_file).with_file_name("sets.json");
+    let set_pieces: Vec<&str> = candidates
+        .iter()
+        .flatten()
+        .filter(|apparel| apparel.tier == Rarity::Set.to_string())
+        .map(|apparel| apparel.name.as_str())
+        .collect();
+    let sets = Sets::new(&load_sets(&sets_file).unwrap_or_else(|err| {
+        if !set_pieces.is_empty() {
+            eprintln!("{}", err);
+        }
+        Default::default()
+    }));
+    let missing_sets: Vec<&str> = set_pieces
+        .into_iter()
+        .filter(|name| !sets.contains(name))
+        .collect();
+    if !missing_sets.is_empty() {
+        eprintln!(
+            "{} has no set of these candidates, their set bonuses are not calculated: {}",
+            sets_file.display(),
+            missing_sets.join(", ")
+        );
+    }
+
+    let major_id_index = MajorIdIndex::new(
+        candidates
+            .iter()
+            .flatten()
+            .map(|apparel| &apparel.major_ids)
+            .chain(weapons.iter().map(|weapon| &weapon.major_ids)),
+    )
+    .unwrap_or_else(|err| {
         eprintln!("{}", err);
         std::process::exit(1);
     });
-    retain_spells(&mut spells, &config);
-
-    let no_ring_apparels: [&[&Apparel]; 6] = [
-        &find(&apparels[0], &config.items.helmets).unwrap(),
-        &find(&apparels[1], &config.items.chest_plates).unwrap(),
-        &find(&apparels[2], &config.items.leggings).unwrap(),
-        &find(&apparels[3], &config.items.boots).unwrap(),
-        &find(&apparels[5], &config.items.bracelets).unwrap(),
-        &find(&apparels[6], &config.items.necklaces).unwrap(),
-    ];
+    // the effects of the major ids are not in the items file, without the file they are ignored
+    let major_ids = if major_id_index.names.is_empty() {
+        Default::default()
+    } else {
+        match load_major_ids("assets/major_ids.json") {
+            Ok(major_ids) => {
+                for name in &major_id_index.names {
+                    if MajorId::find(&major_ids, name).is_none() {
+                        println!(
+                            "major id {} is not in assets/major_ids.json, it is ignored",
+                            name
+                        );
+                    }
+                }
+                major_ids
+            }
+            Err(err) => {
+                println!(
+                    "{}\nthe major ids of the candidates are ignored: {}\nput hppeng's majid.json at assets/major_ids.json",
+                    err,
+                    major_id_index.names.join(", ")
+                );
+                Default::default()
+            }
+        }
+    };
+    let class = Class::from(weapon);
+    let melee_ability = ATreeNodeData::melee(&weapon.r#type);
+    let toggles = config.toggles();
+    // the atree is merged again for every set of major ids found in the combinations
+    let merge_atree = |mask: u64| -> (ATreeStat, Vec<Spell>) {
+        let major_ids: Vec<&MajorId> = major_id_index
+            .names(mask)
+            .filter_map(|name| MajorId::find(&major_ids, name))
+            .collect();
+        let major_id_abilities = merge_major_id(&active_abilities, &major_ids, &class);
+        atree_merge(
+            &std::iter::once(&melee_ability)
+                .chain(active_abilities.iter().copied())
+                .chain(&major_id_abilities)
+                .collect(),
+            &toggles,
+        )
+    };
 
-    let rings: [&[&Apparel]; 2] = [
-        &find(&apparels[4], &config.items.rings).unwrap(),
-        &find(&apparels[4], &config.items.rings).unwrap(),
-    ];
+    let constraints = config.constraints().unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    });
+    let objective = config.objective().unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    });
+    // the spell parts used by the constraints and the objective are calculated
+    let exprs: Vec<Expr> = constraints.iter().chain(&objective).cloned().collect();
+    let merged_atree = |(atree_stat, mut spells): (ATreeStat, Vec<Spell>)| -> MergedATree {
+        retain_spells(&mut spells, &config, &exprs);
+        let scaling_bound = atree_stat.scaling_bound();
+        (atree_stat, scaling_bound, spells)
+    };
+
+    for weapon in &weapons {
+        let (_, spells) = merge_atree(major_id_index.mask(&weapon.major_ids));
+        validate_config_damages(&spells, &config).unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        });
+        validate_config_spam_spell(&spells, &config).unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        });
+        validate_exprs(&spells, &exprs).unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        });
+    }
+
+    if config.dominance() {
+        let dominance = Dominance::from_config(&config, &constraints, objective.as_ref());
+        println!("dominance compares: {}", dominance.names().join(", "));
+        // set pieces and the items of illegal combinations are not only worth their own stats
+        let keep = |apparel: &Apparel| {
+            sets.contains(&apparel.name)
+                || config
+                    .items
+                    .illegal_combinations
+                    .iter()
+                    .flatten()
+                    .flatten()
+                    .any(|name| name == &apparel.name)
+        };
+        for apparels in &mut candidates {
+            let Some(first) = apparels.first() else {
+                continue;
+            };
+            let r#type = first.r#type.clone();
+            for (removed, dominated_by) in dominance.filter(apparels, keep) {
+                println!(
+                    "{}: dropped {}, dominated by {}",
+                    r#type, removed.name, dominated_by.name
+                );
+            }
+        }
+    }
+    let [helmets, chest_plates, leggings, boots, bracelets, necklaces, rings] = &candidates;
+    let no_ring_apparels: [&[&Apparel]; 6] =
+        [helmets, chest_plates, leggings, boots, bracelets, necklaces];
+
+    let rings: [&[&Apparel]; 2] = [rings, rings];
+    // the atree of every set of major ids a combination can have is merged before the search
+    let apparel_masks = major_id_index.reachable(no_ring_apparels.iter().chain(&rings).copied());
+    let mut atrees: HashMap<u64, MergedATree> = HashMap::new();
+    for weapon in &weapons {
+        let weapon_mask = major_id_index.mask(&weapon.major_ids);
+        for mask in &apparel_masks {
+            atrees
+                .entry(weapon_mask | mask)
+                .or_insert_with(|| merged_atree(merge_atree(weapon_mask | mask)));
+        }
+    }
+    for (atree_stat, _, _) in atrees.values() {
+        validate_scaling_inputs(atree_stat).unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        });
+    }
     let ring_combinations = generate_no_order_combinations(rings[0].len());
-    let total_combinations =
-        no_ring_apparels.map(|f| f.len()).iter().product::<usize>() * ring_combinations.len();
+    // weapons and rings are enumerated for every combination of the other slots
+    let inner_combinations = weapons.len() * ring_combinations.len();
+    let no_ring_combinations = no_ring_apparels.map(|f| f.len()).iter().product::<usize>();
+    let total_combinations = no_ring_combinations * inner_combinations;
 
     no_ring_apparels
         .iter()
         .for_each(|v| println!("{}:{}", v.first().unwrap().r#type, v.len()));
     println!("rings:{}", rings.first().unwrap().len());
+    println!("weapons:{}", weapons.len());
     println!("total combinations: {}", total_combinations);
+    if args.dry_run {
+        return;
+    }
+
+    let stats = |combination: &[&Apparel; 8], weapon: &Weapon| {
+        let mask = combination
+            .iter()
+            .fold(major_id_index.mask(&weapon.major_ids), |mask, item| {
+                mask | major_id_index.mask(&item.major_ids)
+            });
+        let (atree_stat, scaling_bound, spells) = &atrees[&mask];
+
+        calculate_stats(
+            &config,
+            atree_stat,
+            scaling_bound.as_ref(),
+            spells,
+            &tome_stat,
+            &sets,
+            &constraints,
+            combination,
+            weapon,
+        )
+    };
+    // --from-url without --around evaluates the build of the url, its slots have one candidate
+    if let Some(url_rings) = url_rings.filter(|_| args.around.is_empty()) {
+        // the rings of the url are found by `url_candidates`
+        let ring = |name: &Option<String>| {
+            *rings[0]
+                .iter()
+                .find(|ring| Some(&ring.name) == name.as_ref())
+                .unwrap()
+        };
+        let combination = [
+            ring(&url_rings[0]),
+            ring(&url_rings[1]),
+            helmets[0],
+            chest_plates[0],
+            leggings[0],
+            boots[0],
+            bracelets[0],
+            necklaces[0],
+        ];
+        match stats(&combination, weapon) {
+            Ok(stat) => {
+                log_build(&config.hppeng.template_url, weapon, &stat);
+                if let Some(objective) = &objective {
+                    println!("score:{:.2}", objective.eval(&|var| stat.value(var)));
+                }
+            }
+            Err(stage) => println!("the build is not valid: {}", stage.name()),
+        }
+        return;
+    }
+
+    let items_version = items_version(&config.hppeng.items_file);
+    let run = run_key(
+        &config_table,
+        &items_version,
+        &candidates,
+        &weapons,
+        args.shard,
+    );
+    // only the sqlite output needs the database, the runs and checkpoints are kept there, a sample
+    // reads the checkpoint of an existing database without creating one
+    let db_pool = match config.output() {
+        Output::Sqlite if args.sample.is_none() || Path::new(&config.hppeng.db_path).exists() => {
+            Some(db::init(&config).await)
+        }
+        _ => None,
+    };
+    // the random order is checkpointed by segment, the branch and bound search is not
+    let checkpoint_pool = db_pool
+        .as_ref()
+        .zip(config.checkpoint_interval())
+        .filter(|_| !config.branch_and_bound());
+    let mut finished = HashSet::new();
+    if let Some((pool, _)) = checkpoint_pool {
+        finished = db::load_checkpoint(pool, &run).await;
+        if !finished.is_empty() {
+            println!("resuming run {}, {} segments finished", run, finished.len());
+            if config.objective.is_some() && args.sample.is_none() {
+                println!("the best builds of the finished segments are already saved");
+            }
+        }
+    }
+    // the segments of the random order that are finished or in another shard
+    let skip = |segment: usize| {
+        finished.contains(&segment) || args.shard.is_some_and(|shard| !shard.contains(segment))
+    };
+    let combinations = if config.branch_and_bound() {
+        match args.shard {
+            // the shards split the slot with the fewest candidates, see `search::slots`
+            Some(shard) => {
+                let fewest = no_ring_apparels
+                    .iter()
+                    .map(|apparels| apparels.len())
+                    .chain([ring_combinations.len()])
+                    .min()
+                    .unwrap();
+                total_combinations.checked_div(fewest).unwrap_or(0) * shard.len_of(fewest)
+            }
+            None => total_combinations,
+        }
+    } else {
+        (0..no_ring_combinations.div_ceil(SEGMENT_SIZE))
+            .filter(|segment| !skip(*segment))
+            .map(|segment| {
+                no_ring_combinations
+                    .saturating_sub(segment * SEGMENT_SIZE)
+                    .min(SEGMENT_SIZE)
+            })
+            .sum::<usize>()
+            * inner_combinations
+    };
+    if let Some(shard) = args.shard {
+        println!("shard {}: {} combinations", shard, combinations);
+    }
+    if let Some(samples) = args.sample {
+        // slots, rings, weapons
+        let dims: [usize; 8] = [
+            no_ring_apparels[0].len(),
+            no_ring_apparels[1].len(),
+            no_ring_apparels[2].len(),
+            no_ring_apparels[3].len(),
+            no_ring_apparels[4].len(),
+            no_ring_apparels[5].len(),
+            ring_combinations.len(),
+            weapons.len(),
+        ];
+        let seed = args.seed.unwrap_or_else(|| fastrand::u64(..));
+        let estimate = estimate::sample(samples, &dims, fastrand::Rng::with_seed(seed), |index| {
+            let [a, b] = ring_combinations[index[6]];
+            let combination = [
+                rings[0][a],
+                rings[1][b],
+                no_ring_apparels[0][index[0]],
+                no_ring_apparels[1][index[1]],
+                no_ring_apparels[2][index[2]],
+                no_ring_apparels[3][index[3]],
+                no_ring_apparels[4][index[4]],
+                no_ring_apparels[5][index[5]],
+            ];
+            stats(&combination, &weapons[index[7]]).map(|_| ())
+        });
+        estimate.print(combinations, config.objective.as_ref().map(|v| v.top_k));
+        return;
+    }
+
+    let sink = match db_pool.clone() {
+        Some(pool) => {
+            let items: Vec<(String, String)> = candidates
+                .iter()
+                .flatten()
+                .map(|apparel| (apparel.name.clone(), apparel.r#type.clone()))
+                .chain(weapons.iter().map(|weapon| {
+                    let r#type = format!("{:?}", weapon.r#type).to_lowercase();
+                    (weapon.name.clone(), r#type)
+                }))
+                .collect();
+            let db_run = db::save_run(
+                &pool,
+                &run,
+                &config_table.to_string(),
+                &items_version,
+                &items,
+            )
+            .await;
+            Sink::Sqlite {
+                pool,
+                config: Box::new(config.clone()),
+                run: db_run,
+            }
+        }
+        None => {
+            let path = config.output_path();
+            println!("writing the builds to {}", path.display());
+            // the atrees are sorted by major id mask, so the columns are the same for every run
+            let spells = spell_columns(
+                atrees
+                    .iter()
+                    .sorted_by_key(|(mask, _)| **mask)
+                    .map(|(_, (_, _, spells))| spells.as_slice()),
+            );
+            Sink::file(config.output(), &path, spells).unwrap_or_else(|err| {
+                eprintln!("can not create {}: {}", path.display(), err);
+                std::process::exit(1);
+            })
+        }
+    };
+    let writer = Writer::spawn(sink);
+    if db_pool.is_none() && config.checkpoint_interval().is_some() {
+        println!(
+            "the checkpoints are only saved by the sqlite output, this run can not be resumed"
+        );
+    }
+    let checkpoint = checkpoint_pool.map(|(pool, interval)| {
+        Mutex::new(Checkpoint {
+            pool: pool.clone(),
+            run: run.clone(),
+            pending: Vec::new(),
+            interval: Duration::from_secs(interval),
+            last_save: Instant::now(),
+        })
+    });
 
     let counter = Arc::new(AtomicUsize::new(0));
-    spawn_speed_watcher(counter.clone(), ring_combinations.len(), total_combinations);
-
-    let db_pool = db::init(&config).await;
-    generate_full_combinations_with_random(
-        1000,
-        counter,
-        &no_ring_apparels,
-        |no_rings_combination| {
-            let default = Default::default();
-            let mut combination: [&Apparel; 8] = [&default; 8];
-            combination[2..].copy_from_slice(&no_rings_combination);
+    // the branch and bound search counts every combination, the random order counts them without
+    // the weapons and rings
+    let coefficient = if config.branch_and_bound() {
+        1
+    } else {
+        inner_combinations
+    };
+    if !args.quiet {
+        spawn_speed_watcher(counter.clone(), coefficient, combinations);
+    }
 
-            for indexes in &ring_combinations {
-                let ring_combination = unsafe { select_from_arrays(indexes, &rings) };
-                combination[..2].copy_from_slice(&ring_combination);
-
-                if let Ok(stat) = calculate_stats(
-                    &config,
-                    &common_stat,
-                    &dam_pct,
-                    dam_raw,
-                    &dam_add,
-                    &spells,
-                    &combination,
-                    weapon,
-                ) {
-                    let url = hppeng_codes.generate_url(
-                        Some("9"),
-                        Some([
-                            *hppeng_id_map.get(&combination[2].name).unwrap(),
-                            *hppeng_id_map.get(&combination[3].name).unwrap(),
-                            *hppeng_id_map.get(&combination[4].name).unwrap(),
-                            *hppeng_id_map.get(&combination[5].name).unwrap(),
-                            *hppeng_id_map.get(&combination[0].name).unwrap(),
-                            *hppeng_id_map.get(&combination[1].name).unwrap(),
-                            *hppeng_id_map.get(&combination[6].name).unwrap(),
-                            *hppeng_id_map.get(&combination[7].name).unwrap(),
-                            *hppeng_id_map.get(&weapon.name).unwrap(),
-                        ]),
-                        Some([
-                            stat.skill_point.original.e() as i32,
-                            stat.skill_point.original.t() as i32,
-                            stat.skill_point.original.w() as i32,
-                            stat.skill_point.original.f() as i32,
-                            stat.skill_point.original.a() as i32,
-                        ]),
-                        Some(config.player.lvl),
-                    );
-                    if config.hppeng.log_builds {
-                        println!("{}", url);
-                        println!("{}", stat);
-                        for spell_damage in &stat.spell_damages {
-                            println!(
-                                "{}: normal {:.2} crit {:.2} avg {:.2}",
-                                spell_damage.name,
-                                spell_damage.normal,
-                                spell_damage.crit,
-                                spell_damage.avg
-                            )
+    let generate_url = |combination: &[&Apparel; 8], weapon: &Weapon, stat: &Status| {
+        hppeng_codes.generate_url(
+            Some("9"),
+            Some([
+                *hppeng_id_map.get(&combination[2].name).unwrap(),
+                *hppeng_id_map.get(&combination[3].name).unwrap(),
+                *hppeng_id_map.get(&combination[4].name).unwrap(),
+                *hppeng_id_map.get(&combination[5].name).unwrap(),
+                *hppeng_id_map.get(&combination[0].name).unwrap(),
+                *hppeng_id_map.get(&combination[1].name).unwrap(),
+                *hppeng_id_map.get(&combination[6].name).unwrap(),
+                *hppeng_id_map.get(&combination[7].name).unwrap(),
+                *hppeng_id_map.get(&weapon.name).unwrap(),
+            ]),
+            Some([
+                stat.skill_point.original.e() as i32,
+                stat.skill_point.original.t() as i32,
+                stat.skill_point.original.w() as i32,
+                stat.skill_point.original.f() as i32,
+                stat.skill_point.original.a() as i32,
+            ]),
+            Some(config.player.lvl),
+        )
+    };
+    // with an objective only the best builds are kept and saved at the end
+    let best = config.objective.as_ref().map(|objective| {
+        Mutex::new(Best {
+            top: TopK::new(objective.top_k),
+            report_interval: Duration::from_secs(objective.report_interval.unwrap_or(60)),
+            last_report: Instant::now(),
+        })
+    });
+    // the score a build must beat to be kept by the objective, see `TopK::min_score`
+    let score_to_beat = AtomicU64::new(f64::NEG_INFINITY.to_bits());
+    // the weapon is passed by index, the best builds keep references to it
+    let evaluate = |combination: [&Apparel; 8], weapon: usize| {
+        let weapon = &weapons[weapon];
+        let Ok(stat) = stats(&combination, weapon) else {
+            return;
+        };
+        if let (Some(best), Some(objective)) = (&best, &objective) {
+            let score = objective.eval(&|var| stat.value(var));
+            let mut best = best.lock().unwrap();
+            if best.top.accepts(score) {
+                let url = generate_url(&combination, weapon, &stat);
+                // the items only live for the call, kept builds own them
+                let items = combination.map(|item| item.clone());
+                best.top.push(score, (url, stat, items, weapon));
+                if let Some(min_score) = best.top.min_score() {
+                    score_to_beat.store(min_score.to_bits(), Ordering::Relaxed);
+                }
+                if best.last_report.elapsed() >= best.report_interval {
+                    best.report();
+                }
+            }
+            return;
+        }
+
+        let url = generate_url(&combination, weapon, &stat);
+        if config.hppeng.log_builds {
+            log_build(&url, weapon, &stat);
+        }
+
+        writer.save_build(Build::new(url, stat, &combination, weapon));
+    };
+
+    if config.branch_and_bound() {
+        let objective_bound = objective.as_ref().and_then(ObjectiveBound::new);
+        if objective.is_some() && objective_bound.is_none() {
+            println!("the objective is not a weighted sum of summed stats, only the thresholds are bounded");
+        }
+        let slots = search::slots(
+            &no_ring_apparels,
+            rings[0],
+            &ring_combinations,
+            objective_bound.as_ref(),
+            args.shard,
+        );
+        let candidates: Vec<&Apparel> = slots
+            .iter()
+            .flatten()
+            .flat_map(|candidate| candidate.items.iter().map(|(_, item)| *item))
+            .collect();
+        for (index, weapon) in weapons.iter().enumerate() {
+            let weapon_mask = major_id_index.mask(&weapon.major_ids);
+            let weapon_atrees: Vec<&MergedATree> = apparel_masks
+                .iter()
+                .map(|mask| &atrees[&(weapon_mask | mask)])
+                .collect();
+            let (lower, upper) = base_bound(
+                &config,
+                weapon,
+                &tome_stat,
+                &sets,
+                &candidates,
+                &weapon_atrees,
+            );
+            let scaling_bounded = weapon_atrees.iter().all(|(_, bound, _)| bound.is_some());
+            let bound = SearchBound::new(
+                &config,
+                &slots,
+                lower,
+                upper,
+                scaling_bounded,
+                objective_bound.as_ref(),
+            );
+            branch_and_bound(
+                counter.clone(),
+                &slots,
+                |fixed| bound.prune(fixed, f64::from_bits(score_to_beat.load(Ordering::Relaxed))),
+                |fixed| {
+                    let default = Default::default();
+                    let mut combination: [&Apparel; 8] = [&default; 8];
+                    for candidate in fixed {
+                        for (index, item) in &candidate.items {
+                            combination[*index] = item;
                         }
                     }
+                    evaluate(combination, index);
+                },
+            );
+        }
+    } else {
+        let apply = |no_rings_combination: [&Apparel; 6]| {
+            let default = Default::default();
+            let mut combination: [&Apparel; 8] = [&default; 8];
+            combination[2..].copy_from_slice(&no_rings_combination);
 
-                    let rt = Runtime::new().unwrap();
-                    rt.block_on(db::save_build(db_pool.clone(), url, stat, combination));
-                };
+            for weapon in 0..weapons.len() {
+                for indexes in &ring_combinations {
+                    let ring_combination = unsafe { select_from_arrays(indexes, &rings) };
+                    combination[..2].copy_from_slice(&ring_combination);
+                    evaluate(combination, weapon);
+                }
             }
-        },
-    );
+        };
+        let seed = args.seed.unwrap_or_else(|| fastrand::u64(..));
+        println!("seed: {}", seed);
+        generate_full_combinations_by_segment(
+            SEGMENT_SIZE,
+            counter,
+            &no_ring_apparels,
+            fastrand::Rng::with_seed(seed),
+            skip,
+            apply,
+            |segment| {
+                let Some(checkpoint) = &checkpoint else {
+                    return;
+                };
+                let mut checkpoint = checkpoint.lock().unwrap();
+                checkpoint.pending.push(segment);
+                if checkpoint.last_save.elapsed() >= checkpoint.interval {
+                    checkpoint.save(&writer, best.as_ref());
+                }
+            },
+        );
+    }
+
+    if let Some(best) = best {
+        let best = best.into_inner().unwrap().top.into_sorted_vec();
+        println!("best builds:");
+        for (rank, (score, (url, stat, combination, weapon))) in best.into_iter().enumerate() {
+            println!("#{} score:{:.2}", rank + 1, score);
+            log_build(&url, weapon, &stat);
+            writer
+                .save_build_async(Build::new(url, stat, &combination.each_ref(), weapon))
+                .await;
+        }
+    }
+    // the checkpoint is cleared after its last segments are written
+    writer.close().await;
+    if let Some(checkpoint) = checkpoint {
+        let checkpoint = checkpoint.into_inner().unwrap();
+        db::clear_checkpoint(&checkpoint.pool, &checkpoint.run).await;
+    }
 
     println!("done");
 }
 
+/// the segments of the random order finished by a run, see `run_key`
+struct Checkpoint {
+    pool: sqlx::SqlitePool,
+    run: String,
+    /// finished since the last save
+    pending: Vec<usize>,
+    interval: Duration,
+    last_save: Instant,
+}
+impl Checkpoint {
+    /// the builds of the pending segments are saved before the segments, so a resumed run does not
+    /// lose any
+    fn save(&mut self, writer: &Writer, best: Option<&Mutex<Best>>) {
+        if let Some(best) = best {
+            best.lock().unwrap().save(writer);
+        }
+        let segments = std::mem::take(&mut self.pending);
+        writer.save_checkpoint(self.run.clone(), segments);
+        self.last_save = Instant::now();
+    }
+}
+
+/// identifies a run by its config, items file, candidate items and shard, a changed config or item
+/// list starts a new run instead of resuming a checkpoint
+///
+/// the key is saved in the database, so it is a fixed hash of the fields written one by one
+fn run_key(
+    config: &toml::Table,
+    items_version: &str,
+    candidates: &[Vec<&Apparel>],
+    weapons: &[Weapon],
+    shard: Option<Shard>,
+) -> String {
+    let mut hasher = Fnv::default();
+    // the parsed config ignores the comments and the formatting, its tables are sorted
+    hasher.write_field(&config.to_string());
+    hasher.write_field(items_version);
+    hasher.write_field(&SEGMENT_SIZE.to_string());
+    hasher.write_field(&shard.map(|v| v.to_string()).unwrap_or_default());
+    // the items are parsed from the items file, their names and order identify them
+    for apparels in candidates {
+        apparels
+            .iter()
+            .for_each(|apparel| hasher.write_field(&apparel.name));
+        hasher.write_field("");
+    }
+    for weapon in weapons {
+        hasher.write_field(&weapon.name);
+    }
+    format!("{:016x}", hasher.finish())
+}
+
+/// identifies the content of the items file, the items are saved once per version
+fn items_version(path: &str) -> String {
+    let mut hasher = Fnv::default();
+    hasher.write(&std::fs::read(path).unwrap_or_default());
+    format!("{:016x}", hasher.finish())
+}
+
+/// the best builds of an objective, (url, status, combination, weapon) by score
+struct Best<'a> {
+    top: TopK<(String, Status, [Apparel; 8], &'a Weapon)>,
+    report_interval: Duration,
+    last_report: Instant,
+}
+impl Best<'_> {
+    /// save the best builds so far, the rest of the builds are saved when they are found
+    fn save(&self, writer: &Writer) {
+        for (_, (url, stat, combination, weapon)) in self.top.sorted() {
+            writer.save_build(Build::new(
+                url.clone(),
+                stat.clone(),
+                &combination.each_ref(),
+                weapon,
+            ));
+        }
+    }
+    fn report(&mut self) {
+        println!("best builds so far:");
+        for (score, (url, _, _, weapon)) in self.top.sorted() {
+            println!("score:{:.2} weapon:{} {}", score, weapon.name, url);
+        }
+        self.last_report = Instant::now();
+    }
+}
+
+fn log_build(url: &str, weapon: &Weapon, stat: &Status) {
+    println!("{}", url);
+    println!("weapon:{}", weapon.name);
+    println!("{}", stat);
+    for spell_damage in &stat.spell_damages {
+        println!(
+            "{}: normal {:.2} crit {:.2} avg {:.2}",
+            spell_damage.name, spell_damage.normal, spell_damage.crit, spell_damage.avg
+        )
+    }
+}
+
+/// (lower, upper) of the stats that do not depend on the slots (weapon, tomes, base hp, atree), the
+/// stats the major ids and set bonuses of the candidates can add are included in the range,
+/// `atrees` are the merged atrees of every set of major ids the builds of the weapon can have, the
+/// atree scaling effects are assumed not to lower stats
+fn base_bound(
+    config: &Config,
+    weapon: &Weapon,
+    tome_stat: &TomeStat,
+    sets: &Sets,
+    candidates: &[&Apparel],
+    atrees: &[&MergedATree],
+) -> (StatSum, StatSum) {
+    let ranges: Vec<(StatSum, StatSum)> = atrees
+        .iter()
+        .map(|(atree_stat, scaling_bound, _)| {
+            let lower = StatSum::from(atree_stat);
+            // the stats of unbounded scalings are not pruned, see `SearchBound::new`
+            let upper = match scaling_bound {
+                Some(bound) => &lower + &StatSum::from(bound),
+                None => lower.clone(),
+            };
+            (lower, upper)
+        })
+        .collect();
+    let (mut lower, _) = StatSum::range(ranges.iter().map(|(lower, _)| lower)).unwrap();
+    let (_, mut upper) = StatSum::range(ranges.iter().map(|(_, upper)| upper)).unwrap();
+
+    for bonuses in sets.possible_bonuses(candidates) {
+        // fewer pieces may give no bonus
+        let sums: Vec<StatSum> = bonuses
+            .iter()
+            .map(StatSum::from)
+            .chain(std::iter::once(StatSum::default()))
+            .collect();
+        let (min, max) = StatSum::range(&sums).unwrap();
+        lower += &min;
+        upper += &max;
+    }
+
+    let mut base = &StatSum::from(weapon) + &StatSum::from(tome_stat);
+    base.hp += config.player.base_hp;
+    (&lower + &base, &upper + &base)
+}
+
 fn spawn_speed_watcher(counter: Arc<AtomicUsize>, coefficient: usize, combinations: usize) {
     spawn(async move {
         let mut total = 0;
@@ -201,6 +935,48 @@ fn spawn_speed_watcher(counter: Arc<AtomicUsize>, coefficient: usize, combinatio
     });
 }
 
+/// the candidates of `--from-url`, the item of the url in each slot, added to the candidates of the
+/// config in the slots of `around`
+fn url_candidates(items: &mut Items, build: &HppengBuild, around: &[Slot]) -> Result<(), String> {
+    let set = |slot: Slot, candidates: &mut Vec<String>, names: &[Option<&String>]| {
+        let kept = around.contains(&slot);
+        if !kept && names.iter().any(|name| name.is_none()) {
+            return Err(format!(
+                "the url has no {}, search its candidates with --around {}",
+                slot, slot
+            ));
+        }
+        let names = names.iter().flatten().map(|name| name.to_string()).unique();
+        if kept {
+            for name in names {
+                if !candidates.contains(&name) {
+                    candidates.push(name);
+                }
+            }
+        } else {
+            *candidates = names.collect();
+        }
+        Ok(())
+    };
+    let [helmet, chest_plate, leggings, boots, ring_1, ring_2, bracelet, necklace] =
+        build.apparels.map(|apparel| apparel.map(|v| &v.name));
+    set(Slot::Helmet, &mut items.helmets, &[helmet])?;
+    set(Slot::ChestPlate, &mut items.chest_plates, &[chest_plate])?;
+    set(Slot::Leggings, &mut items.leggings, &[leggings])?;
+    set(Slot::Boots, &mut items.boots, &[boots])?;
+    set(Slot::Rings, &mut items.rings, &[ring_1, ring_2])?;
+    set(Slot::Bracelet, &mut items.bracelets, &[bracelet])?;
+    set(Slot::Necklace, &mut items.necklaces, &[necklace])?;
+    let mut weapons = items.weapons().to_vec();
+    set(
+        Slot::Weapon,
+        &mut weapons,
+        &[build.weapon.map(|weapon| &weapon.name)],
+    )?;
+    items.weapon = WeaponNames::Many(weapons);
+    Ok(())
+}
+
 fn load_abilities() -> AbilitiesMap {
     let file = File::open("assets/atree.json")
         .expect("The file `atree.json` should exist in the folder assets.");
@@ -213,9 +989,8 @@ fn validate_config_damages(spells: &[Spell], config: &Config) -> Result<(), Stri
         .iter()
         .flat_map(|spell| {
             spell
-                .parts
-                .iter()
-                .map(|part| format!("{}{SPLIT_STR}{}", spell.name, part.name))
+                .part_names()
+                .map(|part| format!("{}{SPLIT_STR}{}", spell.name, part))
         })
         .collect();
 
@@ -248,27 +1023,121 @@ fn validate_config_damages(spells: &[Spell], config: &Config) -> Result<(), Stri
     Ok(())
 }
 
-fn retain_spells(spells: &mut Vec<Spell>, config: &Config) {
+/// the spell damages and costs the builds of the merged atrees can have, in the order of the first
+/// atree they are in
+fn spell_columns<'a>(atrees: impl Iterator<Item = &'a [Spell]>) -> output::SpellColumns {
+    let mut damages: Vec<String> = Vec::new();
+    let mut costs: Vec<String> = Vec::new();
+    for spell in atrees.flatten() {
+        for part in spell.part_names() {
+            let name = format!("{}{SPLIT_STR}{}", spell.name, part);
+            if !damages.contains(&name) {
+                damages.push(name);
+            }
+        }
+        if spell.is_base_spell() && !costs.contains(&spell.name) {
+            costs.push(spell.name.clone());
+        }
+    }
+    (damages, costs)
+}
+
+/// keep the spell parts used by `threshold_damages` and the expressions, the melee spell is always
+/// kept for the melee dps and the base spells are always kept for the spell costs
+fn retain_spells(spells: &mut Vec<Spell>, config: &Config, exprs: &[Expr]) {
     let threshold_map: HashMap<&str, HashSet<&str>> = config
         .threshold_damages
         .iter()
-        .filter_map(|damage| damage.name.split_once(SPLIT_STR))
+        .map(|damage| damage.name.as_str())
+        .chain(expr_spell_parts(exprs))
+        .filter_map(|name| name.split_once(SPLIT_STR))
         .fold(HashMap::new(), |mut map, (spell, part)| {
             map.entry(spell).or_insert_with(HashSet::new).insert(part);
             map
         });
     spells.retain_mut(|spell| {
-        if let Some(parts) = threshold_map.get(spell.name.as_str()) {
-            spell
-                .parts
-                .retain(|part| parts.contains(part.name.as_str()));
-            !spell.parts.is_empty()
+        if spell.id == MELEE_SPELL_ID {
+            true
         } else {
-            false
+            let parts = threshold_map
+                .get(spell.name.as_str())
+                .cloned()
+                .unwrap_or_default();
+            spell.retain_parts(&parts) || spell.is_base_spell()
         }
     });
 }
 
+fn validate_config_spam_spell(spells: &[Spell], config: &Config) -> Result<(), String> {
+    let Some((name, _)) = config.spam_spell() else {
+        return Ok(());
+    };
+    if spells
+        .iter()
+        .any(|spell| spell.is_base_spell() && spell.name == name)
+    {
+        return Ok(());
+    }
+    let mut error = format!("The spam spell is not found: {}\n\nActive spells:\n", name);
+    spells
+        .iter()
+        .filter(|spell| spell.is_base_spell())
+        .for_each(|spell| {
+            writeln!(&mut error, "{}", spell.name).unwrap();
+        });
+    Err(error)
+}
+
+fn expr_spell_parts(exprs: &[Expr]) -> impl Iterator<Item = &str> {
+    exprs
+        .iter()
+        .flat_map(|expr| expr.vars())
+        .filter_map(|var| match var {
+            Var::SpellDamage(name, _) => Some(name.as_str()),
+            _ => None,
+        })
+}
+
+/// the spells of the constraints and the objective must exist
+fn validate_exprs(spells: &[Spell], exprs: &[Expr]) -> Result<(), String> {
+    let mut error = String::new();
+    for var in exprs.iter().flat_map(|expr| expr.vars()) {
+        let found = match var {
+            Var::SpellDamage(name, _) => spells.iter().any(|spell| {
+                spell
+                    .part_names()
+                    .any(|part| format!("{}{SPLIT_STR}{}", spell.name, part) == *name)
+            }),
+            Var::SpellCost(name) => spells
+                .iter()
+                .any(|spell| spell.is_base_spell() && spell.name == *name),
+            Var::Stat(_) => true,
+        };
+        if !found {
+            match var {
+                Var::SpellDamage(name, _) => writeln!(&mut error, "spell part: {}", name),
+                Var::SpellCost(name) => writeln!(&mut error, "spell: {}", name),
+                Var::Stat(_) => Ok(()),
+            }
+            .unwrap();
+        }
+    }
+    if error.is_empty() {
+        return Ok(());
+    }
+    let mut error = format!(
+        "The following spells of the constraints and the objective are not found:\n{}",
+        error
+    );
+    write!(&mut error, "\nActive abilities:\n").unwrap();
+    spells.iter().for_each(|spell| {
+        spell.part_names().for_each(|part| {
+            writeln!(&mut error, "{}{SPLIT_STR}{}", spell.name, part).unwrap();
+        })
+    });
+    Err(error)
+}
+
 fn find<'a>(apparels: &'a [Apparel], names: &'a [String]) -> Result<Vec<&'a Apparel>, String> {
     let mut results = Vec::with_capacity(names.len());
     let mut errors = Vec::new();
@@ -286,12 +1155,111 @@ fn find<'a>(apparels: &'a [Apparel], names: &'a [String]) -> Result<Vec<&'a Appa
         Err("can not find apparel: ".to_owned() + &errors.iter().join(", "))
     }
 }
+/// the major ids of the candidates, the major ids of an item are the bits of their indexes in a mask,
+/// so the atree of a combination is found without allocating
+struct MajorIdIndex {
+    /// sorted
+    names: Vec<String>,
+}
+impl MajorIdIndex {
+    fn new<'a>(major_ids: impl Iterator<Item = &'a Vec<String>>) -> Result<Self, String> {
+        let names: Vec<String> = major_ids.flatten().cloned().sorted().dedup().collect();
+        if names.len() > u64::BITS as usize {
+            return Err(format!(
+                "the candidates have more than {} major ids: {}",
+                u64::BITS,
+                names.join(", ")
+            ));
+        }
+        Ok(Self { names })
+    }
+    fn mask(&self, major_ids: &[String]) -> u64 {
+        major_ids
+            .iter()
+            .filter_map(|name| self.names.iter().position(|v| v == name))
+            .fold(0, |mask, index| mask | 1 << index)
+    }
+    fn names(&self, mask: u64) -> impl Iterator<Item = &String> {
+        self.names
+            .iter()
+            .enumerate()
+            .filter(move |(index, _)| mask & 1 << index != 0)
+            .map(|(_, name)| name)
+    }
+    /// the masks of every combination of one item of each slot
+    fn reachable<'a>(&self, slots: impl Iterator<Item = &'a [&'a Apparel]>) -> HashSet<u64> {
+        slots.fold(HashSet::from([0]), |reachable, apparels| {
+            let masks: HashSet<u64> = apparels
+                .iter()
+                .map(|apparel| self.mask(&apparel.major_ids))
+                .collect();
+            reachable
+                .iter()
+                .flat_map(|a| masks.iter().map(move |b| a | b))
+                .collect()
+        })
+    }
+}
+/// the weapons must be of the same class, the atree of the template url is used for all of them
+fn find_weapons(weapons: &[Weapon], names: &[String]) -> Result<Vec<Weapon>, String> {
+    let mut results: Vec<Weapon> = Vec::with_capacity(names.len());
+    let mut errors = Vec::new();
+
+    for name in names {
+        match weapons.iter().find(|weapon| &weapon.name == name) {
+            Some(weapon) => results.push(weapon.clone()),
+            None => errors.push(name),
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err("can not find weapon: ".to_owned() + &errors.iter().join(", "));
+    }
+    let Some(first) = results.first() else {
+        return Err("no weapon is set".to_string());
+    };
+    let class = Class::from(first);
+    let other_classes: Vec<&String> = results
+        .iter()
+        .filter(|weapon| Class::from(*weapon) != class)
+        .map(|weapon| &weapon.name)
+        .collect();
+    if !other_classes.is_empty() {
+        return Err(format!(
+            "weapons must be of the same class as {}: {}",
+            first.name,
+            other_classes.iter().join(", ")
+        ));
+    }
+    Ok(results)
+}
+fn find_tomes<'a>(tomes: &'a [Tome], names: &'a [String]) -> Result<Vec<&'a Tome>, String> {
+    let mut results = Vec::with_capacity(names.len());
+    let mut errors = Vec::new();
+
+    for name in names {
+        match tomes.iter().find(|tome| &tome.name == name) {
+            Some(tome) => results.push(tome),
+            None => errors.push(name),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(results)
+    } else {
+        Err("can not find tome: ".to_owned() + &errors.iter().join(", "))
+    }
+}
+/// (atree_stat, scaling_bound, spells), see `atree_merge` and `ATreeStat::scaling_bound`
+type MergedATree = (ATreeStat, Option<ATreeStat>, Vec<Spell>);
+#[derive(Clone)]
 pub struct SpellDamage {
     pub name: String,
     pub normal: f64,
     pub crit: f64,
     pub avg: f64,
 }
+#[derive(Clone, Default)]
 pub struct Status {
     pub max_common_stat: CommonStat,
     pub max_sec_stat: SecStat,
@@ -301,13 +1269,60 @@ pub struct Status {
     pub max_def: Point,
     pub skill_point: SkillPoints,
     pub max_dam_pct: Dam,
+    pub melee_dps: f64,
+    /// mana gained per second
+    pub mana_sustain: f64,
+    /// (spell name, mana cost) of the base spells
+    pub spell_costs: Vec<(String, f64)>,
     pub spell_damages: Vec<SpellDamage>,
 }
+impl Status {
+    /// the value of `var` used by the constraints and the objective, missing spells are 0
+    fn value(&self, var: &Var) -> f64 {
+        match var {
+            Var::Stat(stat) => match stat {
+                BuildStat::MaxHp => self.max_hp as f64,
+                BuildStat::MaxEhp => self.max_ehp as f64,
+                BuildStat::MaxHpr => self.max_hpr as f64,
+                BuildStat::MaxHprRaw => self.max_common_stat.hpr_raw() as f64,
+                BuildStat::MaxHprPct => self.max_common_stat.hpr_pct() as f64,
+                BuildStat::MaxMr => self.max_common_stat.mr() as f64,
+                BuildStat::MaxLs => self.max_common_stat.ls() as f64,
+                BuildStat::MaxMs => self.max_common_stat.ms() as f64,
+                BuildStat::MaxSpd => self.max_common_stat.spd() as f64,
+                BuildStat::MaxSdRaw => self.max_common_stat.sd_raw() as f64,
+                BuildStat::MaxSdPct => self.max_common_stat.sd_pct() as f64,
+                BuildStat::MaxExpBonus => self.max_sec_stat.exp_bonus() as f64,
+                BuildStat::MaxLootBonus => self.max_sec_stat.loot_bonus() as f64,
+                BuildStat::MaxHealPct => self.max_sec_stat.heal_pct() as f64,
+                BuildStat::MeleeDps => self.melee_dps,
+                BuildStat::ManaSustain => self.mana_sustain,
+                BuildStat::MaxDefense(index) => self.max_def.inner[*index] as f64,
+                BuildStat::MaxDamPct(index) => self.max_dam_pct.inner[*index] as f64,
+                BuildStat::Point(index) => self.skill_point.original.inner[*index] as f64,
+            },
+            Var::SpellDamage(name, kind) => self
+                .spell_damages
+                .iter()
+                .find(|v| v.name == *name)
+                .map_or(0.0, |v| match kind {
+                    DamageKind::Normal => v.normal,
+                    DamageKind::Crit => v.crit,
+                    DamageKind::Avg => v.avg,
+                }),
+            Var::SpellCost(name) => self
+                .spell_costs
+                .iter()
+                .find(|(spell, _)| spell == name)
+                .map_or(0.0, |(_, cost)| *cost),
+        }
+    }
+}
 impl std::fmt::Display for Status {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
-            "max_common_stat:{}\nmax_sec_stat:{}\nmax_hpr:{}\nmax_hp:{}\nmax_ehp:{}\nskill_point:\n{}\nmax_def:\t{}\nmax_dam_pct:\t{}",
+            "max_common_stat:{}\nmax_sec_stat:{}\nmax_hpr:{}\nmax_hp:{}\nmax_ehp:{}\nskill_point:\n{}\nmax_def:\t{}\nmax_dam_pct:\t{}\nmelee_dps:{:.2}\nmana_sustain:{:.2}",
             self.max_common_stat,
             self.max_sec_stat,
             self.max_hpr,
@@ -316,140 +1331,484 @@ impl std::fmt::Display for Status {
             self.skill_point,
             self.max_def,
             self.max_dam_pct,
-        )
+            self.melee_dps,
+            self.mana_sustain,
+        )?;
+        for (name, cost) in &self.spell_costs {
+            write!(f, "\n{} cost:{:.2}", name, cost)?;
+        }
+        Ok(())
     }
 }
 
+/// spells with melee scaling use the main attack stats in place of the spell damage stats,
+/// heals are scaled by the max hp and healing efficiency, return (name, normal, crit) of each part
+#[allow(clippy::too_many_arguments)]
 fn calculate_spell_damage(
+    max_hp: i32,
+    heal_pct: i16,
     common_stat: &CommonStat,
     skill_point: &SkillPoints,
     dam_pct: &Dam,
+    damage_stat: &DamageStat,
+    melee_stat: &MeleeStat,
     weapon: &Weapon,
-    dam_raw: i32,
-    dam_add: &Damages,
+    atree_stat: &ATreeStat,
     spells: &[Spell],
 ) -> Vec<(String, f64, f64)> {
+    let mut dam_raw_s = atree_stat.dam_raw_s;
+    for (total, item) in dam_raw_s
+        .iter_mut()
+        .zip(<[i32; 6]>::from(&damage_stat.dam_raw_s))
+    {
+        *total += item;
+    }
     let mut spell_damage = Vec::new();
     for spell in spells {
+        let mut stats = Statistics {
+            ability_dam_convert: Default::default(),
+            dam_convert: Default::default(),
+            skill_point: skill_point.original.clone(),
+            sd_pct: common_stat.sd_pct() as f64 / 100.0,
+            sd_pct_s: DamagesConvert::from(&damage_stat.sd_pct_s),
+            dam_pct: damage_stat.dam_pct as f64 / 100.0,
+            dam_pct_s: DamagesConvert::from(dam_pct),
+            r_sd_pct: damage_stat.r_sd_pct as f64 / 100.0,
+            r_dam_pct: damage_stat.r_dam_pct as f64 / 100.0,
+            r_sd_raw: damage_stat.r_sd_raw as i32,
+            r_dam_raw: damage_stat.r_dam_raw as i32,
+            sd_raw: common_stat.sd_raw() as i32,
+            sd_raw_s: <[i32; 6]>::from(&damage_stat.sd_raw_s),
+            dam_raw: atree_stat.dam_raw + damage_stat.dam_raw as i32,
+            dam_raw_s,
+            crit_dam_pct: damage_stat.crit_dam_pct as f64 / 100.0,
+            dam_add: atree_stat.dam_add.clone(),
+        };
+        if spell.melee {
+            stats.sd_pct = melee_stat.md_pct as f64 / 100.0;
+            stats.sd_pct_s = DamagesConvert::from(&melee_stat.md_pct_s);
+            stats.r_sd_pct = melee_stat.r_md_pct as f64 / 100.0;
+            stats.sd_raw = melee_stat.md_raw as i32;
+            stats.sd_raw_s = <[i32; 6]>::from(&melee_stat.md_raw_s);
+            stats.r_sd_raw = melee_stat.r_md_raw as i32;
+        }
+        // totals use the parts before them
+        let mut parts: Vec<(&str, f64, f64)> = Vec::new();
         for part in &spell.parts {
-            let (normal_damage, crit_damage) = damage_calculate(
-                &Statistics {
-                    ability_dam_convert: Default::default(),
-                    dam_convert: Default::default(),
-                    skill_point: skill_point.original.clone(),
-                    sd_pct: common_stat.sd_pct() as f64 / 100.0,
-                    sd_pct_s: Default::default(),
-                    dam_pct: Default::default(),
-                    dam_pct_s: DamagesConvert::from(dam_pct),
-                    r_sd_pct: Default::default(),
-                    r_dam_pct: Default::default(),
-                    r_sd_raw: Default::default(),
-                    r_dam_raw: Default::default(),
-                    sd_raw: common_stat.sd_raw() as i32,
-                    sd_raw_s: Default::default(),
-                    dam_raw,
-                    dam_raw_s: Default::default(),
-                    crit_dam_pct: Default::default(),
-                    dam_add: dam_add.clone(),
-                },
-                weapon,
-                &part.dam_convert,
-            );
-            spell_damage.push((
-                format!("{}{SPLIT_STR}{}", spell.name, part.name),
-                normal_damage.total().avg(),
-                crit_damage.total().avg(),
+            let (normal_damage, crit_damage) =
+                damage_calculate(&stats, weapon, &part.dam_convert, !spell.use_atk_spd);
+            let dam_mult = atree_stat.dam_mult(spell.id, &part.name);
+            parts.push((
+                &part.name,
+                normal_damage.total().avg() * dam_mult,
+                crit_damage.total().avg() * dam_mult,
             ));
         }
+        // https://github.com/hppeng-wynn/hppeng-wynn.github.io/blob/HEAD/js/builder/builder_graph.js
+        for heal in &spell.heals {
+            let amount = heal.power
+                * max_hp as f64
+                * (1.0 + heal_pct as f64 / 100.0)
+                * atree_stat.heal_mult(spell.id, &heal.name);
+            parts.push((&heal.name, amount, amount));
+        }
+        for total in &spell.totals {
+            let (normal, crit) =
+                total
+                    .hits
+                    .iter()
+                    .fold((0.0, 0.0), |(normal, crit), (name, hits)| {
+                        match parts.iter().find(|(part, _, _)| part == name) {
+                            Some((_, part_normal, part_crit)) => (
+                                normal + part_normal * hits.number(),
+                                crit + part_crit * hits.number(),
+                            ),
+                            None => (normal, crit),
+                        }
+                    });
+            parts.push((&total.name, normal, crit));
+        }
+        spell_damage.extend(parts.into_iter().map(|(name, normal, crit)| {
+            (format!("{}{SPLIT_STR}{}", spell.name, name), normal, crit)
+        }));
     }
     spell_damage
 }
-fn calculate_stats(
-    config: &Config,
+/// average damage per second of the melee spell, its strongest part is used as one attack
+/// (the total of all hits if the spell has one), the attack speed includes the raw attack speed
+fn melee_dps(
+    spells: &[Spell],
+    spell_damages: &[SpellDamage],
+    weapon: &Weapon,
+    melee_stat: &MeleeStat,
+) -> f64 {
+    let Some(melee) = spells.iter().find(|spell| spell.id == MELEE_SPELL_ID) else {
+        return 0.0;
+    };
+    let prefix = format!("{}{SPLIT_STR}", melee.name);
+    let hit = spell_damages
+        .iter()
+        .filter(|damage| damage.name.starts_with(&prefix))
+        .map(|damage| damage.avg)
+        .fold(0.0, f64::max);
+    hit * weapon.atk_spd.with_tier(melee_stat.atk_tier).speed_mult()
+}
+/// stats of the build used as the input of atree scaling effects, `None` if the stat is not
+/// calculated yet, see `validate_scaling_inputs`
+fn scaling_input(
+    name: &StatName,
     common_stat: &CommonStat,
     dam_pct: &Dam,
-    dam_raw: i32,
-    dam_add: &Damages,
+    melee_stat: &MeleeStat,
+    skill_point: &SkillPoints,
+) -> Option<f64> {
+    let value = match name {
+        StatName::HprRaw => common_stat.hpr_raw(),
+        StatName::HprPct => common_stat.hpr_pct(),
+        StatName::MR => common_stat.mr(),
+        StatName::LS => common_stat.ls(),
+        StatName::MS => common_stat.ms(),
+        StatName::SPD => common_stat.spd(),
+        StatName::SdRaw => common_stat.sd_raw(),
+        StatName::SdPct => common_stat.sd_pct(),
+        StatName::EDamPct => dam_pct.e(),
+        StatName::TDamPct => dam_pct.t(),
+        StatName::WDamPct => dam_pct.w(),
+        StatName::FDamPct => dam_pct.f(),
+        StatName::ADamPct => dam_pct.a(),
+        StatName::MdPct => melee_stat.md_pct,
+        StatName::MdRaw => melee_stat.md_raw,
+        StatName::Str => skill_point.original.e(),
+        StatName::Dex => skill_point.original.t(),
+        StatName::Int => skill_point.original.w(),
+        StatName::Def => skill_point.original.f(),
+        StatName::Agi => skill_point.original.a(),
+        _ => return None,
+    };
+    Some(value as f64)
+}
+/// the scaling effects of the atree must only scale with the stats of `scaling_input`
+fn validate_scaling_inputs(atree_stat: &ATreeStat) -> Result<(), String> {
+    let unsupported: Vec<String> = atree_stat
+        .scalings
+        .iter()
+        .filter(|stat_scaling| !stat_scaling.is_slider())
+        .flat_map(|stat_scaling| &stat_scaling.inputs)
+        .filter_map(|input| match input {
+            ScalingTarget::Stat { name } => Some(name),
+            ScalingTarget::Prop { .. } => None,
+        })
+        .filter(|name| {
+            scaling_input(
+                name,
+                &Default::default(),
+                &Default::default(),
+                &Default::default(),
+                &Default::default(),
+            )
+            .is_none()
+        })
+        .map(|name| format!("{:?}", name))
+        .unique()
+        .collect();
+    if unsupported.is_empty() {
+        Ok(())
+    } else {
+        Err(format!(
+            "the abilities of template_url scale with stats that are not calculated yet: {}",
+            unsupported.join(", ")
+        ))
+    }
+}
+/// thresholds of stats that atree scaling effects can change
+fn below_scaled_threshold(
+    config: &Config,
+    max_hp: i32,
+    max_common_stat: &CommonStat,
+    max_dam_pct: &Dam,
+) -> bool {
+    config.hp_threshold().is_some_and(|v| max_hp < v)
+        || config
+            .common_stat_threshold()
+            .is_some_and(|v| max_common_stat.any_lt(&v))
+        || config
+            .hpr_threshold()
+            .is_some_and(|v| max_common_stat.hpr() < v)
+        || config
+            .dam_threshold()
+            .is_some_and(|v| max_dam_pct.any_lt(&v))
+}
+/// the max thresholds are checked with the scaled stats, the scaling effects are not bounded below
+fn above_max_threshold(
+    config: &Config,
+    max_hp: i32,
+    max_common_stat: &CommonStat,
+    max_dam_pct: &Dam,
+) -> bool {
+    config.hp_max_threshold().is_some_and(|v| max_hp > v)
+        || config
+            .common_stat_max_threshold()
+            .is_some_and(|v| max_common_stat.any_gt(&v))
+        || config
+            .hpr_max_threshold()
+            .is_some_and(|v| max_common_stat.hpr() > v)
+        || config
+            .dam_max_threshold()
+            .is_some_and(|v| max_dam_pct.any_gt(&v))
+}
+/// the check of `calculate_stats` that rejects a build, in the order of the checks
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Stage {
+    Hp,
+    CommonStat,
+    Hpr,
+    Def,
+    Dam,
+    IllegalCombination,
+    SkillPoints,
+    /// the min thresholds after the atree scaling
+    Scaled,
+    MaxStat,
+    Ehp,
+    SecStat,
+    Mana,
+    Damages,
+    MeleeDps,
+    Constraints,
+}
+impl Stage {
+    const ALL: [Stage; 15] = [
+        Stage::Hp,
+        Stage::CommonStat,
+        Stage::Hpr,
+        Stage::Def,
+        Stage::Dam,
+        Stage::IllegalCombination,
+        Stage::SkillPoints,
+        Stage::Scaled,
+        Stage::MaxStat,
+        Stage::Ehp,
+        Stage::SecStat,
+        Stage::Mana,
+        Stage::Damages,
+        Stage::MeleeDps,
+        Stage::Constraints,
+    ];
+    fn name(&self) -> &'static str {
+        match self {
+            Stage::Hp => "hp",
+            Stage::CommonStat => "common stat",
+            Stage::Hpr => "hpr",
+            Stage::Def => "def",
+            Stage::Dam => "dam pct",
+            Stage::IllegalCombination => "illegal combination",
+            Stage::SkillPoints => "skill points",
+            Stage::Scaled => "scaled stats",
+            Stage::MaxStat => "max stats",
+            Stage::Ehp => "ehp",
+            Stage::SecStat => "exp/loot",
+            Stage::Mana => "mana",
+            Stage::Damages => "spell damages",
+            Stage::MeleeDps => "melee dps",
+            Stage::Constraints => "constraints",
+        }
+    }
+}
+/// `scaling_bound` is the max stats the atree scaling effects can give, the scaling effects
+/// are evaluated after skill points, the thresholds before are checked with the bound, or only
+/// after the scaling without a bound
+#[allow(clippy::too_many_arguments)]
+fn calculate_stats(
+    config: &Config,
+    atree_stat: &ATreeStat,
+    scaling_bound: Option<&ATreeStat>,
     spells: &[Spell],
+    tome_stat: &TomeStat,
+    sets: &Sets,
+    constraints: &[Expr],
     combination: &[&Apparel; 8],
     weapon: &Weapon,
-) -> Result<Status, String> {
-    let max_hp = sum_hp_max(combination, weapon) + config.player.base_hp;
-    if let Some(threshold) = &config.hp_threshold() {
-        if max_hp < *threshold {
-            return Err(String::new());
+) -> Result<Status, Stage> {
+    // set bonuses are summed like items, their skill points change with every piece put on, see
+    // `SkillPoints::scc_put_calculate_with_base`
+    let set_bonuses = sets.bonuses(combination);
+    let mut base_add = tome_stat.add.clone();
+    set_bonuses.iter().for_each(|bonus| base_add += &bonus.add);
+    let with_sets: Vec<&Apparel>;
+    let items: &[&Apparel] = if set_bonuses.is_empty() {
+        combination
+    } else {
+        with_sets = combination.iter().chain(&set_bonuses).copied().collect();
+        &with_sets
+    };
+
+    let mut max_hp =
+        sum_hp_max(items, weapon) + config.player.base_hp + tome_stat.hp + atree_stat.hp_bonus;
+    if let (Some(threshold), Some(bound)) = (&config.hp_threshold(), scaling_bound) {
+        if max_hp + bound.hp_bonus < *threshold {
+            return Err(Stage::Hp);
         }
     }
-    let max_common_stat = &CommonStat::sum_max_stats(combination, weapon) + &common_stat;
-    if let Some(threshold) = &config.common_stat_threshold() {
-        if max_common_stat.any_lt(threshold) {
-            return Err(String::new());
+    let mut max_common_stat = &(&CommonStat::sum_max_stats(items, weapon)
+        + &atree_stat.common_stat)
+        + &tome_stat.common_stat;
+    if let (Some(threshold), Some(bound)) = (&config.common_stat_threshold(), scaling_bound) {
+        if (&max_common_stat + &bound.common_stat).any_lt(threshold) {
+            return Err(Stage::CommonStat);
         }
     }
-    let max_hpr = max_common_stat.hpr();
-    if let Some(threshold) = &config.hpr_threshold() {
-        if max_hpr < *threshold {
-            return Err(String::new());
+    if let (Some(threshold), Some(bound)) = (&config.hpr_threshold(), scaling_bound) {
+        if (&max_common_stat + &bound.common_stat).hpr() < *threshold {
+            return Err(Stage::Hpr);
         }
     }
-    let max_def = sum_def_max(combination, weapon);
+    let max_def = sum_def_max_with_base(items, weapon, &tome_stat.def, &tome_stat.def_pct);
     if let Some(threshold) = &config.def_threshold() {
         if max_def.any_lt(&threshold) {
-            return Err(String::new());
+            return Err(Stage::Def);
+        }
+    }
+    if let Some(threshold) = &config.def_max_threshold() {
+        if max_def.any_gt(threshold) {
+            return Err(Stage::Def);
         }
     }
-    let max_dam_pct = &sum_dam_pct_max(combination, weapon) + dam_pct;
-    if let Some(threshold) = &config.dam_threshold() {
-        if max_dam_pct.any_lt(threshold) {
-            return Err(String::new());
+    let mut max_dam_pct =
+        &(&sum_dam_pct_max(items, weapon) + &atree_stat.dam_pct) + &tome_stat.dam_pct;
+    if let (Some(threshold), Some(bound)) = (&config.dam_threshold(), scaling_bound) {
+        if (&max_dam_pct + &bound.dam_pct).any_lt(threshold) {
+            return Err(Stage::Dam);
         }
     }
 
     if let Some(illegal_combinations) = &config.items.illegal_combinations {
         if is_illegal_combination(combination, illegal_combinations.as_slice()) {
-            return Err(String::new());
+            return Err(Stage::IllegalCombination);
         }
     }
 
-    if SkillPoints::fast_gap(combination)
+    if (SkillPoints::fast_gap(combination) + base_add.clone())
         .only_negative()
         .sum()
         .abs()
         > config.player.available_point
     {
-        return Err(String::new());
+        return Err(Stage::SkillPoints);
     }
-    let (mut skill_point, _) = SkillPoints::scc_put_calculate(combination, weapon);
+    let (mut skill_point, _) =
+        SkillPoints::scc_put_calculate_with_base(combination, weapon, &tome_stat.add, sets);
 
     if let Some(threshold) = &config.point_threshold() {
         skill_point.assign(threshold);
     }
     if !skill_point.check(config.player.available_point) {
-        return Err(String::new());
+        return Err(Stage::SkillPoints);
     }
+    if let Some(threshold) = &config.point_max_threshold() {
+        if skill_point.original.any_gt(threshold) {
+            return Err(Stage::SkillPoints);
+        }
+    }
+
+    let mut max_melee_stat = MeleeStat::sum_max_stats(items, weapon);
+    max_melee_stat += &atree_stat.melee_stat;
+    max_melee_stat += &tome_stat.melee_stat;
 
-    let max_ehp = ehp(&skill_point, max_hp, &Class::from(weapon));
+    let mut max_damage_stat = DamageStat::sum_max_stats(items, weapon);
+    max_damage_stat += &tome_stat.damage_stat;
+
+    let scaled_atree_stat;
+    let atree_stat = if atree_stat.scalings.is_empty() {
+        atree_stat
+    } else {
+        let scaled = atree_stat.scale(|name| {
+            scaling_input(
+                name,
+                &max_common_stat,
+                &max_dam_pct,
+                &max_melee_stat,
+                &skill_point,
+            )
+            .expect("the scaling inputs are checked by validate_scaling_inputs")
+        });
+        max_hp += scaled.hp_bonus;
+        max_common_stat += &scaled.common_stat;
+        max_dam_pct += &scaled.dam_pct;
+        max_melee_stat += &scaled.melee_stat;
+        if below_scaled_threshold(config, max_hp, &max_common_stat, &max_dam_pct) {
+            return Err(Stage::Scaled);
+        }
+        let mut atree_stat = atree_stat.clone();
+        atree_stat += &scaled;
+        scaled_atree_stat = atree_stat;
+        &scaled_atree_stat
+    };
+    if above_max_threshold(config, max_hp, &max_common_stat, &max_dam_pct) {
+        return Err(Stage::MaxStat);
+    }
+    let max_hpr = max_common_stat.hpr();
+
+    let max_ehp =
+        (ehp(&skill_point, max_hp, &Class::from(weapon)) as f64 / atree_stat.def_mult()) as i32;
     if let Some(threshold) = &config.ehp_threshold() {
         if max_ehp < *threshold {
-            return Err(String::new());
+            return Err(Stage::Ehp);
+        }
+    }
+    if let Some(threshold) = &config.ehp_max_threshold() {
+        if max_ehp > *threshold {
+            return Err(Stage::Ehp);
         }
     }
 
-    let max_sec_stat = SecStat::sum_max_stats(combination, weapon);
+    let mut max_sec_stat = SecStat::sum_max_stats(items, weapon);
+    max_sec_stat.inner += tome_stat.sec_stat.inner;
     if let Some(threshold) = &config.sec_stat_threshold() {
         if max_sec_stat.any_lt(threshold) {
-            return Err(String::new());
+            return Err(Stage::SecStat);
+        }
+    }
+    if let Some(threshold) = &config.sec_stat_max_threshold() {
+        if max_sec_stat.any_gt(threshold) {
+            return Err(Stage::SecStat);
+        }
+    }
+
+    let mut max_spell_cost = SpellCost::sum_max_stats(items, weapon);
+    max_spell_cost.inner += tome_stat.spell_cost.inner;
+    let spell_costs: Vec<(String, f64)> = spells
+        .iter()
+        .filter(|spell| spell.is_base_spell())
+        .map(|spell| {
+            let cost = spell_cost(spell, skill_point.original.w(), &max_spell_cost, atree_stat);
+            (spell.name.clone(), cost)
+        })
+        .collect();
+    let mana_sustain = mana_sustain(&max_common_stat);
+    if let Some(threshold) = &config.mana_sustain_threshold() {
+        if mana_sustain < *threshold {
+            return Err(Stage::Mana);
+        }
+    }
+    if let Some((name, interval)) = config.spam_spell() {
+        if let Some((_, cost)) = spell_costs.iter().find(|(spell, _)| spell == name) {
+            if mana_sustain < cost / interval {
+                return Err(Stage::Mana);
+            }
         }
     }
 
     let crit_pct = skill_points_to_percentage(skill_point.original.t());
     let spell_damages: Vec<SpellDamage> = calculate_spell_damage(
+        max_hp,
+        max_sec_stat.heal_pct(),
         &max_common_stat,
         &skill_point,
         &max_dam_pct,
+        &max_damage_stat,
+        &max_melee_stat,
         weapon,
-        dam_raw,
-        dam_add,
+        atree_stat,
         spells,
     )
     .into_iter()
@@ -466,17 +1825,28 @@ fn calculate_stats(
 
     let damage_threshold = config.damage_threshold();
     for v in &spell_damages {
-        let (normal_threshold, crit_threshold, avg_threshold) =
-            damage_threshold.get(v.name.as_str()).unwrap();
+        // the melee spell is kept without thresholds
+        let Some((normal_threshold, crit_threshold, avg_threshold)) =
+            damage_threshold.get(v.name.as_str())
+        else {
+            continue;
+        };
         if (*normal_threshold as f64) > v.normal
             || (*crit_threshold as f64) > v.crit
             || (*avg_threshold as f64) > v.avg
         {
-            return Err(String::new());
+            return Err(Stage::Damages);
         }
     }
 
-    Ok(Status {
+    let melee_dps = melee_dps(spells, &spell_damages, weapon, &max_melee_stat);
+    if let Some(threshold) = &config.melee_dps_threshold() {
+        if melee_dps < *threshold as f64 {
+            return Err(Stage::MeleeDps);
+        }
+    }
+
+    let status = Status {
         max_common_stat,
         max_sec_stat,
         max_hpr,
@@ -485,8 +1855,19 @@ fn calculate_stats(
         skill_point,
         max_ehp,
         max_dam_pct,
+        melee_dps,
+        mana_sustain,
+        spell_costs,
         spell_damages,
-    })
+    };
+    if !constraints
+        .iter()
+        .all(|constraint| constraint.test(&|var| status.value(var)))
+    {
+        return Err(Stage::Constraints);
+    }
+
+    Ok(status)
 }
 
 fn is_illegal_combination(
@@ -507,3 +1888,185 @@ fn is_illegal_combination(
     }
     false
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn url_candidates_works() {
+        let mut items: Items = toml::from_str(
+            r#"
+            helmets = ["Cumulonimbus"]
+            chest_plates = []
+            leggings = []
+            boots = []
+            rings = ["Prism"]
+            bracelets = []
+            necklaces = []
+            weapon = "Idol"
+            "#,
+        )
+        .unwrap();
+        let apparel = |name: &str| Apparel {
+            name: name.to_string(),
+            ..Default::default()
+        };
+        let apparels = [
+            "Morph-Stardust",
+            "Far Cosmos",
+            "Anxiolytic",
+            "Violet-Shift",
+            "Facile",
+            "Facile",
+            "Pandemonium",
+            "Diamond Fusion Necklace",
+        ]
+        .map(apparel);
+        let weapon = Weapon {
+            name: "Fatal".to_string(),
+            ..Default::default()
+        };
+        let mut build = HppengBuild {
+            apparels: apparels.each_ref().map(Some),
+            weapon: Some(&weapon),
+            skill_point: Default::default(),
+            level: 106,
+            powders: Default::default(),
+            tome_ids: Vec::new(),
+            abilities: Vec::new(),
+        };
+
+        // the url's items are the only candidates, but in the slots searched around it
+        url_candidates(&mut items, &build, &[Slot::Helmet, Slot::Rings]).unwrap();
+        assert_eq!(items.helmets, ["Cumulonimbus", "Morph-Stardust"]);
+        assert_eq!(items.chest_plates, ["Far Cosmos"]);
+        assert_eq!(items.rings, ["Prism", "Facile"]);
+        assert_eq!(items.necklaces, ["Diamond Fusion Necklace"]);
+        assert_eq!(items.weapons(), ["Fatal"]);
+
+        build.apparels[1] = None;
+        assert_eq!(
+            url_candidates(&mut items, &build, &[]),
+            Err(
+                "the url has no chest-plate, search its candidates with --around chest-plate"
+                    .to_string()
+            )
+        );
+        assert!(url_candidates(&mut items, &build, &[Slot::ChestPlate]).is_ok());
+    }
+
+    #[test]
+    fn validate_scaling_inputs_works() {
+        let scaling = |input: &str| -> StatScaling {
+            serde_json::from_str(&format!(
+                r#"{{
+                    "inputs": [{{ "name": "{}", "type": "stat" }}],
+                    "output": {{ "name": "sdRaw", "type": "stat" }},
+                    "scaling": [1],
+                    "slider": false
+                }}"#,
+                input
+            ))
+            .unwrap()
+        };
+        let atree_stat = |inputs: &[&str]| ATreeStat {
+            scalings: inputs.iter().map(|input| scaling(input)).collect(),
+            ..Default::default()
+        };
+        assert!(validate_scaling_inputs(&atree_stat(&["spd", "mdPct"])).is_ok());
+        // the stats stolen or reflected are not calculated
+        assert_eq!(
+            Err("the abilities of template_url scale with stats that are not calculated yet: ESteal, Ref".to_string()),
+            validate_scaling_inputs(&atree_stat(&["eSteal", "spd", "ref", "eSteal"]))
+        );
+    }
+    #[test]
+    fn spell_columns_works() {
+        let spell = |name: &str, id: i32, parts: &[&str]| {
+            let parts = parts
+                .iter()
+                .map(|part| DamagePart::new(part.to_string(), Default::default()))
+                .collect();
+            Spell::new(name.to_string(), id, 0, parts)
+        };
+        let first = [
+            spell("Bash", 1, &["Single Hit", "Total Damage"]),
+            spell("Melee", 0, &["Single Hit"]),
+        ];
+        // a major id changing a spell and adding one
+        let second = [
+            spell("Bash", 1, &["Single Hit", "Sidekick"]),
+            spell("Uppercut", 3, &["Total Damage"]),
+        ];
+        let (damages, costs) = spell_columns([first.as_slice(), second.as_slice()].into_iter());
+        assert_eq!(
+            damages,
+            [
+                "Bash.Single Hit",
+                "Bash.Total Damage",
+                "Melee.Single Hit",
+                "Bash.Sidekick",
+                "Uppercut.Total Damage"
+            ]
+        );
+        assert_eq!(costs, ["Bash", "Uppercut"]);
+    }
+
+    #[test]
+    fn find_weapons_works() {
+        let weapon = |name: &str, r#type: WeaponTypes| Weapon {
+            name: name.to_string(),
+            r#type,
+            ..Default::default()
+        };
+        let weapons = [
+            weapon("Fatal", WeaponTypes::Wand),
+            weapon("Nirvana", WeaponTypes::Wand),
+            weapon("Guardian", WeaponTypes::Spear),
+        ];
+        let names =
+            |names: &[&str]| -> Vec<String> { names.iter().map(|v| v.to_string()).collect() };
+
+        let found = find_weapons(&weapons, &names(&["Nirvana", "Fatal"])).unwrap();
+        assert_eq!(
+            vec!["Nirvana", "Fatal"],
+            found.iter().map(|v| &v.name).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            Err("weapons must be of the same class as Fatal: Guardian".to_string()),
+            find_weapons(&weapons, &names(&["Fatal", "Guardian"])).map(|_| ())
+        );
+        assert_eq!(
+            Err("can not find weapon: Idol, Oak".to_string()),
+            find_weapons(&weapons, &names(&["Fatal", "Idol", "Oak"])).map(|_| ())
+        );
+        assert!(find_weapons(&weapons, &[]).is_err());
+    }
+
+    #[test]
+    fn major_id_index_works() {
+        let apparel = |major_ids: &[&str]| Apparel {
+            major_ids: major_ids.iter().map(|v| v.to_string()).collect(),
+            ..Default::default()
+        };
+        let (a, b, none) = (apparel(&["Plague"]), apparel(&["Hawkeye"]), apparel(&[]));
+        let index =
+            MajorIdIndex::new([&a.major_ids, &b.major_ids, &none.major_ids].into_iter()).unwrap();
+        assert_eq!(vec!["Hawkeye", "Plague"], index.names);
+        assert_eq!(0b10, index.mask(&a.major_ids));
+        assert_eq!(0, index.mask(&none.major_ids));
+        assert_eq!(vec!["Plague"], index.names(0b10).collect::<Vec<_>>());
+
+        let first: [&Apparel; 2] = [&a, &none];
+        let second: [&Apparel; 2] = [&b, &none];
+        assert_eq!(
+            HashSet::from([0, 0b01, 0b10, 0b11]),
+            index.reachable([&first[..], &second[..]].into_iter())
+        );
+        assert_eq!(
+            HashSet::from([0, 0b10]),
+            index.reachable([&first[..]].into_iter())
+        );
+    }
+}
@@ -0,0 +1,156 @@
+use std::fmt;
+
+use clap::{Parser, ValueEnum};
+
+use wynn_build_tools::config::{Config, Output};
+use wynn_build_tools::util::Shard;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about)]
+pub struct BuilderArgs {
+    /// Path of the config file
+    #[arg(short, long, default_value = "config/config.toml")]
+    pub config: String,
+
+    /// Path of the items file, instead of `items_file` of the config
+    #[arg(long)]
+    pub items_file: Option<String>,
+
+    /// Path of the database, instead of `db_path` of the config
+    #[arg(long)]
+    pub db_path: Option<String>,
+
+    /// Where the builds are written, instead of `output` of the config
+    #[arg(long)]
+    pub output: Option<Output>,
+
+    /// File of the csv and jsonl outputs, instead of `output_path` of the config
+    #[arg(long)]
+    pub output_path: Option<String>,
+
+    /// Override a value of the config (format: table.key=value)
+    ///
+    /// The value is read as a toml value, e.g. `--set threshold_first.min_hp=12000` or
+    /// `--set threshold_second.min_mr=5`, and as a string if it is not one. An unknown key of a
+    /// threshold table is an error.
+    #[arg(long = "set", value_name = "KEY=VALUE", value_parser = parse_key_val, num_args = 1)]
+    pub overrides: Vec<(String, String)>,
+
+    /// Print the number of candidates of each slot and of combinations, then exit
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Evaluate this many random combinations, print the pass rate of each threshold and the
+    /// projected number of builds and runtime of the combinations left to search (of the shard and
+    /// without the finished segments of a checkpoint), then exit
+    #[arg(long, value_name = "SAMPLES")]
+    pub sample: Option<usize>,
+
+    /// Log every valid build and the database errors, whatever the config says
+    #[arg(short, long, conflicts_with = "quiet")]
+    pub verbose: bool,
+
+    /// Only print the summary, no builds, database errors or progress
+    #[arg(short, long)]
+    pub quiet: bool,
+
+    /// Number of threads searching the combinations, all the cores by default
+    #[arg(short = 'j', long, value_parser = clap::value_parser!(u16).range(1..))]
+    pub threads: Option<u16>,
+
+    /// Seed of the random order of the combinations, the same seed gives the same order
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    /// Only search the part i of n disjoint parts of the combinations (format: i/n, from 1/n to n/n)
+    ///
+    /// Each shard can run on another machine with its own database, the databases can be merged
+    /// afterwards.
+    #[arg(long)]
+    pub shard: Option<Shard>,
+
+    /// Start from the build of a hppeng url instead of the items of the config
+    ///
+    /// The url replaces `template_url`, its abilities, tomes and powders are used, and its items
+    /// are the only candidates of their slots, so the build of the url is evaluated and printed
+    /// without the thresholds and constraints. With `--around`, the builds around it are searched.
+    #[arg(long, value_name = "URL")]
+    pub from_url: Option<String>,
+
+    /// With `--from-url`, the slots that keep the candidates of the config, the item of the url is
+    /// added to them, e.g. `--around rings necklace` searches the rings and necklaces of the build
+    #[arg(long, value_name = "SLOT", num_args = 1.., requires = "from_url")]
+    pub around: Vec<Slot>,
+}
+
+/// the slots of the candidates of `Items`
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Debug)]
+pub enum Slot {
+    Helmet,
+    ChestPlate,
+    Leggings,
+    Boots,
+    /// Both rings
+    Rings,
+    Bracelet,
+    Necklace,
+    Weapon,
+}
+impl fmt::Display for Slot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_possible_value().unwrap().get_name())
+    }
+}
+impl BuilderArgs {
+    /// apply the paths, the output, the verbosity and the url to the config
+    pub fn apply(&self, config: &mut Config) {
+        if let Some(items_file) = &self.items_file {
+            config.hppeng.items_file = items_file.clone();
+        }
+        if let Some(db_path) = &self.db_path {
+            config.hppeng.db_path = db_path.clone();
+        }
+        if let Some(output) = self.output {
+            config.hppeng.output = Some(output);
+        }
+        if let Some(output_path) = &self.output_path {
+            config.hppeng.output_path = Some(output_path.clone());
+        }
+        if self.verbose {
+            config.hppeng.log_builds = true;
+            config.hppeng.log_db_errors = true;
+        }
+        if self.quiet {
+            config.hppeng.log_builds = false;
+            config.hppeng.log_db_errors = false;
+        }
+        if let Some(url) = &self.from_url {
+            config.hppeng.template_url = url.clone();
+            config.powders = None;
+            config.items.tomes = None;
+            // the build of the url is evaluated whatever its stats
+            if self.around.is_empty() {
+                config.clear_thresholds();
+            }
+        }
+    }
+    /// the overrides of `--set`, with the template of `--from-url`, so the run of a url is not the
+    /// run of another
+    pub fn overrides(&self) -> Vec<(String, String)> {
+        let template = self
+            .from_url
+            .as_ref()
+            .map(|url| ("hppeng.template_url".to_owned(), url.clone()));
+        self.overrides.iter().cloned().chain(template).collect()
+    }
+}
+
+fn parse_key_val(s: &str) -> Result<(String, String), String> {
+    let (key, value) = s
+        .split_once('=')
+        .ok_or_else(|| format!("invalid KEY=value: no `=` found in `{}`", s))?;
+    if key.is_empty() {
+        return Err(format!("invalid KEY=value: no key in `{}`", s));
+    }
+    Ok((key.trim().to_owned(), value.trim().to_owned()))
+}
@@ -2,28 +2,25 @@ use std::collections::{BTreeMap, HashMap};
 
 use crate::items::*;
 
-/// return (common_stat, dam_raw, dam_pct, dam_add, spells)
+/// return (atree_stat, spells), `toggles` are the toggle and slider values of the atree
 /// https://github.com/hppeng-wynn/hppeng-wynn.github.io/blob/50ed4620bd0a4e3af7dd5646971c6dcd78e8b783/js/builder/atree.js#L441
 pub fn atree_merge<'a>(
     active_abilities: &Vec<&ATreeNodeData>,
-) -> (CommonStat, i32, Dam, Damages, Vec<Spell>) {
+    toggles: &Toggles,
+) -> (ATreeStat, Vec<Spell>) {
     let abilities_merged = merge_ability(active_abilities);
 
-    let (common_stat, dam_raw, dam_pct, dam_add): (CommonStat, i32, Dam, Damages) =
-        abilities_merged
-            .values()
-            .map(|v| v.join_stat())
-            .fold(Default::default(), |mut acc, x| {
-                acc.0 += &x.0;
-                acc.1 += &x.1;
-                acc.2 += &x.2;
-                acc.3 += &x.3;
-                acc
-            });
+    let mut atree_stat: ATreeStat = abilities_merged
+        .values()
+        .map(|v| v.join_stat(toggles))
+        .fold(Default::default(), |mut acc, x| {
+            acc += &x;
+            acc
+        });
 
     let (mut base_spells, merged_spell_properties): (
         HashMap<i32, Spell>,
-        HashMap<i32, (i32, Vec<DamagePart>)>,
+        HashMap<i32, (i32, Vec<(Behavior, Part)>)>,
         // reverses abilities, only uses the last spell(replace_spell) as the base spell
     ) = abilities_merged.iter().rev().fold(
         (HashMap::new(), HashMap::new()),
@@ -47,26 +44,58 @@ pub fn atree_merge<'a>(
         },
     );
 
-    for (base_id, (cost, add_parts)) in merged_spell_properties {
+    for (base_id, (cost, mut add_parts)) in merged_spell_properties {
         if let Some(value) = base_spells.get_mut(&base_id) {
             value.cost += cost;
-            for add_part in add_parts {
-                if let Some(v) = value.parts.iter_mut().find(|v| v.name == add_part.name) {
-                    v.dam_convert += &add_part.dam_convert;
-                } else {
-                    value.parts.push(add_part);
-                }
+            // overwrite is applied after all merges
+            add_parts.sort_by_key(|(behavior, _)| *behavior == Behavior::Overwrite);
+            for (behavior, add_part) in add_parts {
+                value.merge_part(behavior, &add_part);
             }
         } else {
             println!("not found base ability: {}", base_id);
         }
     }
 
+    // conversions are applied in ability order, after all parts are merged
+    for convert in abilities_merged.values().flat_map(|v| v.join_spell_conv()) {
+        if let Some(spell) = base_spells.get_mut(&convert.base_spell) {
+            convert.apply(spell);
+        }
+    }
+
+    // https://github.com/hppeng-wynn/hppeng-wynn.github.io/blob/50ed4620bd0a4e3af7dd5646971c6dcd78e8b783/js/builder/atree.js#L1067
+    for (index, target, value) in std::mem::take(&mut atree_stat.conv_base) {
+        let part = target.split_once('.').and_then(|(spell_id, part_name)| {
+            let spell = base_spells.get_mut(&spell_id.parse().ok()?)?;
+            spell.parts.iter_mut().find(|part| part.name == part_name)
+        });
+        if let Some(part) = part {
+            let mut dam_convert = [0.0; 6];
+            dam_convert[index] = value / 100.0;
+            part.dam_convert += &DamagesConvert::from_slice(dam_convert);
+        }
+    }
+
+    // properties referenced by the hits of totals, e.g. "0.hits" of "Bash"
+    let mut props: HashMap<String, f64> = abilities_merged
+        .iter()
+        .flat_map(|(id, ability)| {
+            ability
+                .properties
+                .iter()
+                .map(move |(name, value)| (format!("{}.{}", id, name), value.number()))
+        })
+        .collect();
+    for (key, value) in std::mem::take(&mut atree_stat.props) {
+        *props.entry(key).or_default() += value;
+    }
+    for spell in base_spells.values_mut() {
+        spell.resolve_hits(&props);
+    }
+
     (
-        common_stat,
-        dam_raw,
-        dam_pct,
-        dam_add,
+        atree_stat,
         base_spells.into_iter().map(|(_key, value)| value).collect(),
     )
 }
@@ -90,10 +119,20 @@ pub fn merge_ability(active_abilities: &Vec<&ATreeNodeData>) -> BTreeMap<i32, AT
         .for_each(|(base_id, &v)| {
             if let Some(base_ability) = abilities_merged.get_mut(&base_id) {
                 base_ability.effects.extend(v.effects.clone());
+                for (name, value) in &v.properties {
+                    match (base_ability.properties.get_mut(name), value) {
+                        (Some(NumberOrString::Number(base)), NumberOrString::Number(value)) => {
+                            *base += value
+                        }
+                        _ => {
+                            base_ability.properties.insert(name.clone(), value.clone());
+                        }
+                    }
+                }
             } else {
                 // https://github.com/hppeng-wynn/hppeng-wynn.github.io/blob/f29e47836e7469bae4eed1264ccc113c516fc73c/js/builder/atree.js#L131
-                // 999 is "Melee", this type of calculation is not currently supported
-                if base_id == 999 {
+                // 999 is "Melee", it is only active if `ATreeNodeData::melee` is in the active abilities
+                if base_id == MELEE_ABILITY_ID {
                     return;
                 }
                 // https://github.com/hppeng-wynn/hppeng-wynn.github.io/blob/f29e47836e7469bae4eed1264ccc113c516fc73c/js/builder/atree.js#L128
@@ -115,8 +154,47 @@ pub fn merge_ability(active_abilities: &Vec<&ATreeNodeData>) -> BTreeMap<i32, AT
     abilities_merged
 }
 
-// TODO merge_major_id
-// https://github.com/hppeng-wynn/hppeng-wynn.github.io/blob/50ed4620bd0a4e3af7dd5646971c6dcd78e8b783/js/builder/atree.js#L502
+/// return the abilities of the major ids as atree nodes, they are merged after the active abilities:
+/// `atree_merge(&active_abilities.iter().chain(&major_id_abilities).collect())`
+/// https://github.com/hppeng-wynn/hppeng-wynn.github.io/blob/50ed4620bd0a4e3af7dd5646971c6dcd78e8b783/js/builder/atree.js#L502
+pub fn merge_major_id(
+    active_abilities: &[&ATreeNodeData],
+    major_ids: &[&MajorId],
+    class: &Class,
+) -> Vec<ATreeNodeData> {
+    let is_active = |id: i32| active_abilities.iter().any(|v| v.id == id);
+
+    major_ids
+        .iter()
+        .flat_map(|major_id| {
+            major_id
+                .abilities
+                .iter()
+                .filter(|ability| ability.is_class(class))
+                .filter(|ability| ability.dependencies.iter().all(|&id| is_active(id)))
+                // the base spell of the ability is not unlocked
+                .filter(|ability| match ability.base_abil {
+                    Some(id) => id == 998 || id == 999 || is_active(id),
+                    None => true,
+                })
+                .map(|ability| ATreeNodeData {
+                    display_name: major_id.display_name.clone(),
+                    id: 0,
+                    parents: Vec::new(),
+                    base_abil: ability.base_abil,
+                    cost: 0,
+                    properties: Default::default(),
+                    effects: ability.effects.clone(),
+                })
+        })
+        .enumerate()
+        .map(|(i, mut ability)| {
+            // ids above every atree ability, so the spells of major ids replace the atree spells
+            ability.id = i32::MAX - i as i32;
+            ability
+        })
+        .collect()
+}
 
 #[cfg(test)]
 mod tests {
@@ -135,12 +213,15 @@ mod tests {
 
         // https://hppeng-wynn.github.io/builder/?v=10#9_2SG2SH2SI2SJ2SK2SL2SM2SN0Qf00002I00001g000000z0z0+0+0+0+0-1T--hOsK5v3
         let active_abilities = decode_atree(&abilities.get(&Class::Warrior).unwrap(), "--hOsK5v3");
-        let (common_stat, dam_raw, dam_pct, dam_add, spells) = atree_merge(&active_abilities);
-        assert_eq!(common_stat, CommonStat::new(0, 0, 0, 0, 0, 20, 0, 0));
-        assert_eq!(5, dam_raw);
+        let (atree_stat, spells) = atree_merge(&active_abilities, &Toggles::new());
+        assert_eq!(
+            atree_stat.common_stat,
+            CommonStat::new(0, 0, 0, 0, 0, 20, 0, 0)
+        );
+        assert_eq!(5, atree_stat.dam_raw);
         assert_eq!(
             DamagesConvert::from_slice([0.0, 0.0, 0.1, 0.15, 0.15, 0.15]),
-            DamagesConvert::from(&dam_pct)
+            DamagesConvert::from(&atree_stat.dam_pct)
         );
         assert_eq!(
             Damages::from_slice([
@@ -151,31 +232,327 @@ mod tests {
                 Range { min: 3.0, max: 5.0 },
                 Range { min: 3.0, max: 4.0 },
             ]),
-            dam_add
+            atree_stat.dam_add
         );
 
         for v in spells {
             if v.id != 3 {
                 continue;
             }
-            assert_eq!(
-                Spell::new(
-                    "Uppercut".to_string(),
-                    3,
-                    45,
-                    vec![
-                        DamagePart::new(
-                            "Uppercut".to_string(),
-                            DamagesConvert::from_slice([3.2, 0.4, 0.4, 0.3, 0.0, 0.3])
-                        ),
-                        DamagePart::new(
-                            "Fireworks".to_string(),
-                            DamagesConvert::from_slice([0.8, 0.0, 0.2, 0.0, 0.0, 0.0])
-                        )
-                    ]
-                ),
-                v
+            let mut uppercut = Spell::new(
+                "Uppercut".to_string(),
+                3,
+                45,
+                vec![
+                    DamagePart::new(
+                        "Uppercut".to_string(),
+                        DamagesConvert::from_slice([3.2, 0.4, 0.4, 0.3, 0.0, 0.3]),
+                    ),
+                    DamagePart::new(
+                        "Fireworks".to_string(),
+                        DamagesConvert::from_slice([0.8, 0.0, 0.2, 0.0, 0.0, 0.0]),
+                    ),
+                ],
             );
+            uppercut.totals.push(TotalPart::new(
+                "Total Damage".to_string(),
+                HashMap::from([
+                    ("Uppercut".to_string(), NumberOrString::Number(1.0)),
+                    ("Fireworks".to_string(), NumberOrString::Number(1.0)),
+                ]),
+            ));
+            assert_eq!(uppercut, v);
         }
     }
+    #[test]
+    fn merge_major_id_works() {
+        let file = File::open("assets/atree_test_case.json")
+            .expect("The file `atree.json` should exist in the folder assets.");
+        let reader = BufReader::new(file);
+        let abilities: AbilitiesMap = serde_json::from_reader(reader).unwrap();
+        let active_abilities = decode_atree(abilities.get(&Class::Warrior).unwrap(), "--hOsK5v3");
+
+        let major_ids: MajorIdsMap = serde_json::from_str(
+            r#"{
+                "TEST_WARRIOR": {
+                    "displayName": "Test Warrior",
+                    "id": "TEST_WARRIOR",
+                    "abilities": [
+                        {
+                            "class": "Warrior",
+                            "base_abil": 8,
+                            "effects": [
+                                {
+                                    "type": "add_spell_prop",
+                                    "base_spell": 3,
+                                    "target_part": "Uppercut",
+                                    "behavior": "overwrite",
+                                    "multipliers": [100, 0, 0, 0, 0, 0]
+                                }
+                            ]
+                        },
+                        {
+                            "effects": [
+                                {
+                                    "type": "raw_stat",
+                                    "bonuses": [{ "type": "stat", "name": "spd", "value": 10 }]
+                                }
+                            ]
+                        }
+                    ]
+                },
+                "TEST_MAGE": {
+                    "displayName": "Test Mage",
+                    "id": "TEST_MAGE",
+                    "abilities": [
+                        {
+                            "class": "Mage",
+                            "effects": [
+                                {
+                                    "type": "raw_stat",
+                                    "bonuses": [{ "type": "stat", "name": "spd", "value": 10 }]
+                                }
+                            ]
+                        }
+                    ]
+                }
+            }"#,
+        )
+        .unwrap();
+        let major_ids: Vec<&MajorId> = major_ids.values().collect();
+
+        let major_id_abilities = merge_major_id(&active_abilities, &major_ids, &Class::Warrior);
+        assert_eq!(2, major_id_abilities.len());
+
+        let (atree_stat, spells) = atree_merge(
+            &active_abilities
+                .iter()
+                .copied()
+                .chain(&major_id_abilities)
+                .collect(),
+            &Toggles::new(),
+        );
+        assert_eq!(
+            atree_stat.common_stat,
+            CommonStat::new(0, 0, 0, 0, 0, 30, 0, 0)
+        );
+        let uppercut = spells.iter().find(|v| v.id == 3).unwrap();
+        assert_eq!(
+            DamagesConvert::from_slice([1.0, 0.0, 0.0, 0.0, 0.0, 0.0]),
+            uppercut.parts[0].dam_convert
+        );
+    }
+    #[test]
+    fn atree_merge_conv_base_works() {
+        let nodes: Vec<ATreeNodeData> = serde_json::from_str(
+            r#"[
+                {
+                    "display_name": "Spell",
+                    "id": 0,
+                    "parents": [],
+                    "cost": 1,
+                    "effects": [
+                        {
+                            "type": "replace_spell",
+                            "name": "Spell",
+                            "base_spell": 3,
+                            "parts": [{ "name": "Winded Damage", "multipliers": [100, 0, 0, 0, 0, 0] }]
+                        }
+                    ]
+                },
+                {
+                    "display_name": "Winded",
+                    "id": 1,
+                    "parents": [0],
+                    "base_abil": 0,
+                    "cost": 1,
+                    "effects": [
+                        {
+                            "type": "stat_scaling",
+                            "slider": true,
+                            "slider_name": "Winded",
+                            "output": { "name": "nConvBase:3.Winded Damage", "type": "stat" },
+                            "scaling": [-10]
+                        },
+                        {
+                            "type": "stat_scaling",
+                            "slider": true,
+                            "slider_name": "Winded",
+                            "output": { "name": "aConvBase:3.Winded Damage", "type": "stat" },
+                            "scaling": [5]
+                        }
+                    ]
+                }
+            ]"#,
+        )
+        .unwrap();
+        let active_abilities: Vec<&ATreeNodeData> = nodes.iter().collect();
+
+        let (_, spells) = atree_merge(&active_abilities, &Toggles::new());
+        assert_eq!(
+            DamagesConvert::from_slice([1.0, 0.0, 0.0, 0.0, 0.0, 0.0]),
+            spells[0].parts[0].dam_convert
+        );
+
+        let toggles = Toggles::from([("Winded".to_string(), 2.0)]);
+        let (_, spells) = atree_merge(&active_abilities, &toggles);
+        assert_eq!(
+            DamagesConvert::from_slice([0.8, 0.0, 0.0, 0.0, 0.0, 0.1]),
+            spells[0].parts[0].dam_convert
+        );
+    }
+    #[test]
+    fn atree_merge_convert_spell_conv_works() {
+        // no ability of assets/atree.json has a convert_spell_conv effect, so
+        // assets/atree_test_case.json has no build using one and the tree is written here
+        let nodes: Vec<ATreeNodeData> = serde_json::from_str(
+            r#"[
+                {
+                    "display_name": "Spell",
+                    "id": 0,
+                    "parents": [],
+                    "cost": 1,
+                    "effects": [
+                        {
+                            "type": "replace_spell",
+                            "name": "Spell",
+                            "base_spell": 1,
+                            "parts": [
+                                { "name": "Hit", "multipliers": [60, 20, 0, 0, 20, 0] },
+                                { "name": "Explosion", "multipliers": [100, 0, 0, 0, 0, 0] }
+                            ]
+                        }
+                    ]
+                },
+                {
+                    "display_name": "Element",
+                    "id": 1,
+                    "parents": [0],
+                    "base_abil": 0,
+                    "cost": 1,
+                    "effects": [
+                        {
+                            "type": "add_spell_prop",
+                            "base_spell": 1,
+                            "target_part": "Explosion",
+                            "multipliers": [0, 0, 0, 30, 0, 0]
+                        }
+                    ]
+                },
+                {
+                    "display_name": "Thunder Conversion",
+                    "id": 2,
+                    "parents": [1],
+                    "base_abil": 0,
+                    "cost": 1,
+                    "effects": [
+                        {
+                            "type": "convert_spell_conv",
+                            "base_spell": 1,
+                            "target_part": "Hit",
+                            "conversion": "Thunder"
+                        }
+                    ]
+                },
+                {
+                    "display_name": "Air Conversion",
+                    "id": 3,
+                    "parents": [1],
+                    "base_abil": 0,
+                    "cost": 1,
+                    "effects": [
+                        {
+                            "type": "convert_spell_conv",
+                            "base_spell": 1,
+                            "target_part": "all",
+                            "conversion": "Air"
+                        }
+                    ]
+                }
+            ]"#,
+        )
+        .unwrap();
+
+        let (_, spells) = atree_merge(&nodes[0..3].iter().collect(), &Toggles::new());
+        let parts = &spells[0].parts;
+        assert_eq!(
+            DamagesConvert::from_slice([0.6, 0.0, 0.4, 0.0, 0.0, 0.0]),
+            parts.iter().find(|v| v.name == "Hit").unwrap().dam_convert
+        );
+        assert_eq!(
+            DamagesConvert::from_slice([1.0, 0.0, 0.0, 0.3, 0.0, 0.0]),
+            parts
+                .iter()
+                .find(|v| v.name == "Explosion")
+                .unwrap()
+                .dam_convert
+        );
+
+        let (_, spells) = atree_merge(&nodes.iter().collect(), &Toggles::new());
+        let parts = &spells[0].parts;
+        assert_eq!(
+            DamagesConvert::from_slice([0.6, 0.0, 0.0, 0.0, 0.0, 0.4]),
+            parts.iter().find(|v| v.name == "Hit").unwrap().dam_convert
+        );
+        assert_eq!(
+            DamagesConvert::from_slice([1.0, 0.0, 0.0, 0.0, 0.0, 0.3]),
+            parts
+                .iter()
+                .find(|v| v.name == "Explosion")
+                .unwrap()
+                .dam_convert
+        );
+
+        assert!(serde_json::from_str::<ConvertSpellConv>(
+            r#"{ "base_spell": 1, "target_part": "all", "conversion": "Rainbow" }"#
+        )
+        .is_err());
+    }
+    #[test]
+    fn atree_merge_melee_works() {
+        let file = File::open("assets/atree.json")
+            .expect("The file `atree.json` should exist in the folder assets.");
+        let abilities: AbilitiesMap = serde_json::from_reader(BufReader::new(file)).unwrap();
+        let find = |class: &Class, name: &str| {
+            abilities[class]
+                .iter()
+                .find(|v| v.display_name == name)
+                .unwrap()
+        };
+
+        let melee = ATreeNodeData::melee(&WeaponTypes::Spear);
+        let (_, spells) = atree_merge(
+            &vec![&melee, find(&Class::Warrior, "Spear Proficiency 1")],
+            &Toggles::new(),
+        );
+        let spell = spells.iter().find(|v| v.id == MELEE_SPELL_ID).unwrap();
+        assert!(spell.melee);
+        assert!(!spell.use_atk_spd);
+        assert_eq!(
+            vec![DamagePart::new(
+                "Melee".to_string(),
+                DamagesConvert::from_slice([1.05, 0.0, 0.0, 0.0, 0.0, 0.0])
+            )],
+            spell.parts
+        );
+
+        // abilities of the melee ability replace its spell
+        let melee = ATreeNodeData::melee(&WeaponTypes::Relik);
+        let (_, spells) = atree_merge(
+            &vec![
+                &melee,
+                find(&Class::Shaman, "Relik Proficiency 1"),
+                find(&Class::Shaman, "Hand of the Shaman"),
+            ],
+            &Toggles::new(),
+        );
+        let spell = spells.iter().find(|v| v.id == MELEE_SPELL_ID).unwrap();
+        assert_eq!(
+            vec![DamagePart::new(
+                "Single Beam".to_string(),
+                DamagesConvert::from_slice([0.34, 0.0, 0.0, 0.0, 0.0, 0.0])
+            )],
+            spell.parts
+        );
+    }
 }
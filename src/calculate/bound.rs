@@ -0,0 +1,206 @@
+use std::{
+    ops::{Add, AddAssign, Sub},
+    simd::{cmp::SimdOrd, i16x8},
+};
+
+use crate::items::*;
+
+/// the stats that are summed over the items of a build, a partial combination is bounded by the
+/// sum of its items and the per-slot min/max of the slots left
+#[derive(Clone, Debug, Default)]
+pub struct StatSum {
+    pub hp: i32,
+    pub def: Point,
+    pub def_pct: Point,
+    pub dam_pct: Dam,
+    pub common_stat: CommonStat,
+    pub sec_stat: SecStat,
+}
+impl StatSum {
+    fn zip(&self, other: &Self, hp: fn(i32, i32) -> i32, f: fn(i16x8, i16x8) -> i16x8) -> Self {
+        Self {
+            hp: hp(self.hp, other.hp),
+            def: Point {
+                inner: f(self.def.inner, other.def.inner),
+            },
+            def_pct: Point {
+                inner: f(self.def_pct.inner, other.def_pct.inner),
+            },
+            dam_pct: Dam {
+                inner: f(self.dam_pct.inner, other.dam_pct.inner),
+            },
+            common_stat: CommonStat {
+                inner: f(self.common_stat.inner, other.common_stat.inner),
+            },
+            sec_stat: SecStat {
+                inner: f(self.sec_stat.inner, other.sec_stat.inner),
+            },
+        }
+    }
+    /// lane-wise max
+    pub fn max(&self, other: &Self) -> Self {
+        self.zip(other, i32::max, |a, b| a.simd_max(b))
+    }
+    /// lane-wise min
+    pub fn min(&self, other: &Self) -> Self {
+        self.zip(other, i32::min, |a, b| a.simd_min(b))
+    }
+    /// (min, max) of the candidates of a slot, `None` if there is no candidate
+    pub fn range<'a>(candidates: impl IntoIterator<Item = &'a StatSum>) -> Option<(Self, Self)> {
+        let mut candidates = candidates.into_iter();
+        let first = candidates.next()?;
+        Some(
+            candidates.fold((first.clone(), first.clone()), |(min, max), v| {
+                (min.min(v), max.max(v))
+            }),
+        )
+    }
+    /// (min, max) of the max defences of the builds whose sums are between `lower` and `upper`,
+    /// see `sum_def_max_with_base`
+    ///
+    /// the defence is increasing with the def pct, and monotonic in the raw defence on each side of
+    /// 0, so the extremes are at the ends of the range or at 0
+    pub fn def_range(lower: &Self, upper: &Self) -> (Point, Point) {
+        let def = |raw: i32, pct: i32| raw + pct * raw.abs() / 100;
+        let mut min = [0; 5];
+        let mut max = [0; 5];
+        for i in 0..5 {
+            let (low, high) = (lower.def.inner[i] as i32, upper.def.inner[i] as i32);
+            let raws = [low, high, 0.clamp(low, high)];
+            min[i] = raws
+                .iter()
+                .map(|raw| def(*raw, lower.def_pct.inner[i] as i32))
+                .min()
+                .unwrap()
+                .clamp(i16::MIN as i32, i16::MAX as i32) as i16;
+            max[i] = raws
+                .iter()
+                .map(|raw| def(*raw, upper.def_pct.inner[i] as i32))
+                .max()
+                .unwrap()
+                .clamp(i16::MIN as i32, i16::MAX as i32) as i16;
+        }
+        (
+            Point::new(min[0], min[1], min[2], min[3], min[4]),
+            Point::new(max[0], max[1], max[2], max[3], max[4]),
+        )
+    }
+}
+impl From<&Apparel> for StatSum {
+    fn from(value: &Apparel) -> Self {
+        Self {
+            hp: value.hp + value.hp_bonus_max,
+            def: value.def.clone(),
+            def_pct: value.def_pct_max.clone(),
+            dam_pct: value.dam_pct_max.clone(),
+            common_stat: value.common_stat_max.clone(),
+            sec_stat: value.sec_stat_max.clone(),
+        }
+    }
+}
+impl From<&Weapon> for StatSum {
+    fn from(value: &Weapon) -> Self {
+        Self {
+            hp: value.hp_bonus_max,
+            def: Point::default(),
+            def_pct: value.def_pct_max.clone(),
+            dam_pct: value.dam_pct_max.clone(),
+            common_stat: value.common_stat_max.clone(),
+            sec_stat: value.sec_stat_max.clone(),
+        }
+    }
+}
+impl From<&TomeStat> for StatSum {
+    fn from(value: &TomeStat) -> Self {
+        Self {
+            hp: value.hp,
+            def: value.def.clone(),
+            def_pct: value.def_pct.clone(),
+            dam_pct: value.dam_pct.clone(),
+            common_stat: value.common_stat.clone(),
+            sec_stat: value.sec_stat.clone(),
+        }
+    }
+}
+impl From<&ATreeStat> for StatSum {
+    fn from(value: &ATreeStat) -> Self {
+        Self {
+            hp: value.hp_bonus,
+            dam_pct: value.dam_pct.clone(),
+            common_stat: value.common_stat.clone(),
+            ..Default::default()
+        }
+    }
+}
+impl Add<&StatSum> for &StatSum {
+    type Output = StatSum;
+
+    fn add(self, rhs: &StatSum) -> Self::Output {
+        self.zip(rhs, |a, b| a + b, |a, b| a + b)
+    }
+}
+impl AddAssign<&StatSum> for StatSum {
+    fn add_assign(&mut self, rhs: &StatSum) {
+        *self = &*self + rhs;
+    }
+}
+impl Sub<&StatSum> for &StatSum {
+    type Output = StatSum;
+
+    fn sub(self, rhs: &StatSum) -> Self::Output {
+        self.zip(rhs, |a, b| a - b, |a, b| a - b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn range_works() {
+        let a = StatSum {
+            hp: 100,
+            common_stat: CommonStat::new(0, 0, 5, 0, -2, 0, 0, 0),
+            ..Default::default()
+        };
+        let b = StatSum {
+            hp: 50,
+            common_stat: CommonStat::new(0, 0, -1, 0, 4, 0, 0, 0),
+            ..Default::default()
+        };
+        let (min, max) = StatSum::range([&a, &b]).unwrap();
+        assert_eq!(min.hp, 50);
+        assert_eq!(max.hp, 100);
+        assert_eq!(min.common_stat, CommonStat::new(0, 0, -1, 0, -2, 0, 0, 0));
+        assert_eq!(max.common_stat, CommonStat::new(0, 0, 5, 0, 4, 0, 0, 0));
+        assert_eq!((&a + &b).hp, 150);
+        assert_eq!((&a - &b).common_stat.mr(), 6);
+        assert!(StatSum::range([]).is_none());
+    }
+
+    #[test]
+    fn def_range_works() {
+        let sum = |def: Point, def_pct: Point| StatSum {
+            def,
+            def_pct,
+            ..Default::default()
+        };
+        let lower = sum(Point::new(-50, 10, 0, 0, 0), Point::new(-20, 0, 0, 0, 0));
+        let upper = sum(Point::new(-10, 100, 0, 0, 0), Point::new(150, 20, 0, 0, 0));
+        let (min, max) = StatSum::def_range(&lower, &upper);
+        // -50 - 20% of 50, 10 + 0%
+        assert_eq!(min.e(), -60);
+        assert_eq!(min.t(), 10);
+        // -50 + 150% of 50, 100 + 20%
+        assert_eq!(max.e(), 25);
+        assert_eq!(max.t(), 120);
+
+        // every build of the range is inside the bound
+        for raw in -50..=-10 {
+            for pct in -20..=150 {
+                let def = raw + pct * (raw as i32).abs() as i16 / 100;
+                assert!(min.e() <= def && def <= max.e());
+            }
+        }
+    }
+}
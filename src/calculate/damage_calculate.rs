@@ -37,13 +37,16 @@ pub struct Statistics {
     pub dam_add: Damages,
 }
 
+/// `ignore_speed` is true for spells that don't use the attack speed, e.g. a single melee hit
 /// https://github.com/hppeng-wynn/hppeng-wynn.github.io/blob/HEAD/js/damage_calc.js#L31
 pub fn damage_calculate(
     stats: &Statistics,
     weapon: &Weapon,
     dam_convert: &DamagesConvert,
+    ignore_speed: bool,
 ) -> (Damages, Damages) {
     // 1. Get weapon damage (with powders).
+    // powders are applied to `weapon.damage` when the weapon is loaded, see `Weapon::apply_powders`
 
     // 2. Conversions.
     // 2.0: First, modify conversions.
@@ -64,7 +67,9 @@ pub fn damage_calculate(
     damages += &(&Damages::splat(&total_damages).only_rainbow() * &conversions);
 
     // 3. Apply attack speed multiplier. Ignored for melee single hit
-    damages *= weapon.atk_spd.speed_mult();
+    if !ignore_speed {
+        damages *= weapon.atk_spd.speed_mult();
+    }
 
     // 4. Add additive damage. TODO: Is there separate additive damage?
     let mut present = weapon.damage_present.clone();
@@ -198,6 +203,7 @@ mod tests {
             },
             &weapon,
             &DamagesConvert::from_slice([1.1, 0.3, 0.0, 0.0, 0.0, 0.0]),
+            false,
         );
         assert_eq!(
             normal_damage,
@@ -4,8 +4,18 @@ use crate::items::*;
 
 // https://github.com/hppeng-wynn/hppeng-wynn.github.io/blob/f01c29a099ee21ed57bed9054b4651a311ee40cd/js/builder/builder_graph.js#L541
 pub fn sum_def_max(value: &[&Apparel], weapon: &Weapon) -> Point {
-    let mut def_total: Point = Default::default();
+    sum_def_max_with_base(value, weapon, &Point::default(), &Point::default())
+}
+/// same as `sum_def_max`, `base_def` and `base_def_pct` are stats that are always active (e.g. tomes)
+pub fn sum_def_max_with_base(
+    value: &[&Apparel],
+    weapon: &Weapon,
+    base_def: &Point,
+    base_def_pct: &Point,
+) -> Point {
+    let mut def_total: Point = base_def.clone();
     let mut def_pct_total: Point = weapon.def_pct_max.clone();
+    def_pct_total += base_def_pct;
     for item in value {
         def_total += &item.def;
         def_pct_total += &item.def_pct_max;
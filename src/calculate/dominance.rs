@@ -0,0 +1,498 @@
+use crate::config::*;
+use crate::items::*;
+
+/// how a stat must compare for an apparel to be at least as good as another
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Higher,
+    Lower,
+    /// used in both directions or in a way that is not known to be monotonic (e.g. a constraint)
+    Exact,
+}
+impl Direction {
+    fn combine(self, other: Self) -> Self {
+        if self == other {
+            self
+        } else {
+            Direction::Exact
+        }
+    }
+    fn reverse(self) -> Self {
+        match self {
+            Direction::Higher => Direction::Lower,
+            Direction::Lower => Direction::Higher,
+            Direction::Exact => Direction::Exact,
+        }
+    }
+}
+
+// layout of the stats of an apparel, see `dims`
+const HP: usize = 0;
+const COMMON_STAT: usize = 1;
+const SEC_STAT: usize = 9;
+const DEF: usize = 12;
+const DEF_PCT: usize = 17;
+const DAM_PCT: usize = 22;
+const ADD: usize = 28;
+const REQ: usize = 33;
+const SPELL_COST: usize = 38;
+const MELEE_STAT: usize = 46;
+const DAMAGE_STAT: usize = 63;
+const LEN: usize = 88;
+
+const ELEMENTS: [&str; 6] = ["neutral", "earth", "thunder", "water", "fire", "air"];
+const COMMON_STATS: [&str; 8] = [
+    "hpr_raw", "hpr_pct", "mr", "ls", "ms", "spd", "sd_raw", "sd_pct",
+];
+
+/// the stats of an apparel that matter for a config, an apparel dominates another of the same slot
+/// if it is at least as good on all of them, so the other can be dropped before the search
+///
+/// the defence is assumed to be increasing with the raw defence, which does not hold when the total
+/// raw defence is negative with more than 100% defence
+#[derive(Debug, Clone)]
+pub struct Dominance {
+    directions: [Option<Direction>; LEN],
+}
+impl Default for Dominance {
+    fn default() -> Self {
+        let mut dominance = Self {
+            directions: [None; LEN],
+        };
+        // lower requirements and higher skill point bonuses are always better, the bonuses lower
+        // the points to assign and so can make a combination valid
+        for lane in 0..5 {
+            dominance.set(ADD + lane, Direction::Higher);
+            dominance.set(REQ + lane, Direction::Lower);
+        }
+        dominance
+    }
+}
+impl Dominance {
+    /// the stats used by the thresholds, the constraints and the objective
+    pub fn from_config(config: &Config, constraints: &[Expr], objective: Option<&Expr>) -> Self {
+        let mut dominance = Self::default();
+        // (stat, min is set, max is set)
+        let mut thresholds: Vec<(BuildStat, bool, bool)> = Vec::new();
+        if let Some(t) = &config.threshold_first {
+            thresholds.push((BuildStat::MaxHp, t.min_hp.is_some(), t.max_hp.is_some()));
+        }
+        if let Some(t) = &config.threshold_second {
+            thresholds.extend([
+                (
+                    BuildStat::MaxHprRaw,
+                    t.min_hpr_raw.is_some(),
+                    t.max_hpr_raw.is_some(),
+                ),
+                (
+                    BuildStat::MaxHprPct,
+                    t.min_hpr_pct.is_some(),
+                    t.max_hpr_pct.is_some(),
+                ),
+                (BuildStat::MaxMr, t.min_mr.is_some(), t.max_mr.is_some()),
+                (BuildStat::MaxLs, t.min_ls.is_some(), t.max_ls.is_some()),
+                (BuildStat::MaxMs, t.min_ms.is_some(), t.max_ms.is_some()),
+                (BuildStat::MaxSpd, t.min_spd.is_some(), t.max_spd.is_some()),
+                (
+                    BuildStat::MaxSdRaw,
+                    t.min_sd_raw.is_some(),
+                    t.max_sd_raw.is_some(),
+                ),
+                (
+                    BuildStat::MaxSdPct,
+                    t.min_sd_pct.is_some(),
+                    t.max_sd_pct.is_some(),
+                ),
+            ]);
+        }
+        if let Some(t) = &config.threshold_third {
+            thresholds.push((BuildStat::MaxHpr, t.min_hpr.is_some(), t.max_hpr.is_some()));
+        }
+        if let Some(t) = &config.threshold_fourth {
+            let lanes = [
+                (t.min_earth_defense, t.max_earth_defense),
+                (t.min_thunder_defense, t.max_thunder_defense),
+                (t.min_water_defense, t.max_water_defense),
+                (t.min_fire_defense, t.max_fire_defense),
+                (t.min_air_defense, t.max_air_defense),
+            ];
+            thresholds.extend(lanes.iter().enumerate().map(|(lane, (min, max))| {
+                (BuildStat::MaxDefense(lane), min.is_some(), max.is_some())
+            }));
+        }
+        if let Some(t) = &config.threshold_fifth {
+            let lanes = [
+                (t.min_neutral_dam_pct, t.max_neutral_dam_pct),
+                (t.min_earth_dam_pct, t.max_earth_dam_pct),
+                (t.min_thunder_dam_pct, t.max_thunder_dam_pct),
+                (t.min_water_dam_pct, t.max_water_dam_pct),
+                (t.min_fire_dam_pct, t.max_fire_dam_pct),
+                (t.min_air_dam_pct, t.max_air_dam_pct),
+            ];
+            thresholds.extend(lanes.iter().enumerate().map(|(lane, (min, max))| {
+                (BuildStat::MaxDamPct(lane), min.is_some(), max.is_some())
+            }));
+        }
+        if let Some(t) = &config.threshold_sixth {
+            let lanes = [
+                (t.min_earth_point, t.max_earth_point),
+                (t.min_thunder_point, t.max_thunder_point),
+                (t.min_water_point, t.max_water_point),
+                (t.min_fire_point, t.max_fire_point),
+                (t.min_air_point, t.max_air_point),
+            ];
+            thresholds.extend(
+                lanes.iter().enumerate().map(|(lane, (min, max))| {
+                    (BuildStat::Point(lane), min.is_some(), max.is_some())
+                }),
+            );
+        }
+        if let Some(t) = &config.threshold_seventh {
+            thresholds.push((BuildStat::MaxEhp, t.min_ehp.is_some(), t.max_ehp.is_some()));
+        }
+        if let Some(t) = &config.threshold_eighth {
+            thresholds.extend([
+                (
+                    BuildStat::MaxExpBonus,
+                    t.min_exp_bonus.is_some(),
+                    t.max_exp_bonus.is_some(),
+                ),
+                (
+                    BuildStat::MaxLootBonus,
+                    t.min_loot_bonus.is_some(),
+                    t.max_loot_bonus.is_some(),
+                ),
+            ]);
+        }
+        for (stat, min, max) in thresholds {
+            if min {
+                dominance.stat(&stat, Direction::Higher);
+            }
+            if max {
+                dominance.stat(&stat, Direction::Lower);
+            }
+        }
+        if config.melee_dps_threshold().is_some() {
+            dominance.stat(&BuildStat::MeleeDps, Direction::Higher);
+        }
+        if !config.threshold_damages.is_empty() {
+            dominance.spell_damage(Direction::Higher);
+        }
+        if config.mana_sustain_threshold().is_some() {
+            dominance.stat(&BuildStat::ManaSustain, Direction::Higher);
+        }
+        if config.spam_spell().is_some() {
+            dominance.stat(&BuildStat::ManaSustain, Direction::Higher);
+            dominance.spell_cost(Direction::Lower);
+        }
+        for var in constraints.iter().flat_map(|constraint| constraint.vars()) {
+            dominance.var(var, Direction::Exact);
+        }
+        if let Some(objective) = objective {
+            match objective.linear() {
+                Some((_, terms)) => {
+                    for (weight, var) in terms {
+                        let direction = if weight >= 0.0 {
+                            Direction::Higher
+                        } else {
+                            Direction::Lower
+                        };
+                        dominance.var(var, direction);
+                    }
+                }
+                None => {
+                    for var in objective.vars() {
+                        dominance.var(var, Direction::Exact);
+                    }
+                }
+            }
+        }
+        dominance
+    }
+    fn set(&mut self, dim: usize, direction: Direction) {
+        let current = &mut self.directions[dim];
+        *current = Some(match current {
+            Some(current) => current.combine(direction),
+            None => direction,
+        });
+    }
+    fn var(&mut self, var: &Var, direction: Direction) {
+        match var {
+            Var::Stat(stat) => self.stat(stat, direction),
+            Var::SpellDamage(_, _) => self.spell_damage(direction),
+            Var::SpellCost(_) => self.spell_cost(direction),
+        }
+    }
+    fn stat(&mut self, stat: &BuildStat, direction: Direction) {
+        match stat {
+            BuildStat::MaxHp => self.set(HP, direction),
+            // skill points of defense and agility
+            BuildStat::MaxEhp => [HP, ADD + 3, ADD + 4]
+                .into_iter()
+                .for_each(|dim| self.set(dim, direction)),
+            BuildStat::MaxHpr => {
+                self.set(COMMON_STAT, direction);
+                self.set(COMMON_STAT + 1, direction);
+            }
+            BuildStat::MaxHprRaw => self.set(COMMON_STAT, direction),
+            BuildStat::MaxHprPct => self.set(COMMON_STAT + 1, direction),
+            BuildStat::MaxMr => self.set(COMMON_STAT + 2, direction),
+            BuildStat::MaxLs => self.set(COMMON_STAT + 3, direction),
+            BuildStat::MaxMs => self.set(COMMON_STAT + 4, direction),
+            BuildStat::MaxSpd => self.set(COMMON_STAT + 5, direction),
+            BuildStat::MaxSdRaw => self.set(COMMON_STAT + 6, direction),
+            BuildStat::MaxSdPct => self.set(COMMON_STAT + 7, direction),
+            BuildStat::MaxExpBonus => self.set(SEC_STAT, direction),
+            BuildStat::MaxLootBonus => self.set(SEC_STAT + 1, direction),
+            BuildStat::MaxHealPct => self.set(SEC_STAT + 2, direction),
+            BuildStat::ManaSustain => {
+                self.set(COMMON_STAT + 2, direction);
+                self.set(COMMON_STAT + 4, direction);
+            }
+            BuildStat::MaxDefense(lane) => {
+                self.set(DEF + lane, direction);
+                self.set(DEF_PCT + lane, direction);
+            }
+            BuildStat::MaxDamPct(lane) => self.set(DAM_PCT + lane, direction),
+            // the skill points are the assigned ones plus the ones of the items
+            BuildStat::Point(lane) => self.set(ADD + lane, direction),
+            BuildStat::MeleeDps => self.damage(direction),
+        }
+    }
+    /// the damage stats and skill points used by the damage calculation
+    fn damage(&mut self, direction: Direction) {
+        (DAM_PCT..DAM_PCT + 6)
+            .chain(ADD..ADD + 5)
+            .chain([COMMON_STAT + 6, COMMON_STAT + 7])
+            .chain(MELEE_STAT..LEN)
+            .for_each(|dim| self.set(dim, direction));
+    }
+    /// heals also scale with the hp and the healing efficiency
+    fn spell_damage(&mut self, direction: Direction) {
+        self.damage(direction);
+        self.set(HP, direction);
+        self.set(SEC_STAT + 2, direction);
+    }
+    /// intelligence lowers the costs
+    fn spell_cost(&mut self, direction: Direction) {
+        (SPELL_COST..SPELL_COST + 8).for_each(|dim| self.set(dim, direction));
+        self.set(ADD + 2, direction.reverse());
+    }
+    /// names of the stats that are compared
+    pub fn names(&self) -> Vec<String> {
+        self.directions
+            .iter()
+            .enumerate()
+            .filter(|(_, direction)| direction.is_some())
+            .map(|(dim, _)| dim_name(dim))
+            .collect()
+    }
+    /// whether `a` is at least as good as `b` on every stat that matters, apparels with major ids
+    /// must have the same ones
+    pub fn dominates(&self, a: &Apparel, b: &Apparel) -> bool {
+        if a.major_ids != b.major_ids {
+            return false;
+        }
+        let (a, b) = (dims(a), dims(b));
+        self.directions
+            .iter()
+            .zip(a.iter().zip(&b))
+            .all(|(direction, (a, b))| match direction {
+                None => true,
+                Some(Direction::Higher) => a >= b,
+                Some(Direction::Lower) => a <= b,
+                Some(Direction::Exact) => a == b,
+            })
+    }
+    /// remove the dominated apparels, the first one of equal apparels is kept, `keep` apparels are
+    /// neither removed nor used to remove others (e.g. set pieces whose bonuses are not compared),
+    /// return (removed, dominated by)
+    pub fn filter<'a>(
+        &self,
+        apparels: &mut Vec<&'a Apparel>,
+        keep: impl Fn(&Apparel) -> bool,
+    ) -> Vec<(&'a Apparel, &'a Apparel)> {
+        let mut removed = Vec::new();
+        let candidates = apparels.clone();
+        apparels.retain(|apparel| {
+            if keep(apparel) {
+                return true;
+            }
+            let index = candidates
+                .iter()
+                .position(|v| std::ptr::eq(*v, *apparel))
+                .unwrap();
+            let dominated_by = candidates.iter().enumerate().find(|(other_index, other)| {
+                *other_index != index
+                    && !keep(other)
+                    && self.dominates(other, apparel)
+                    // equal apparels dominate each other
+                    && (*other_index < index || !self.dominates(apparel, other))
+            });
+            match dominated_by {
+                Some((_, other)) => {
+                    removed.push((*apparel, *other));
+                    false
+                }
+                None => true,
+            }
+        });
+        removed
+    }
+}
+
+fn dims(apparel: &Apparel) -> [i32; LEN] {
+    let mut dims = [0; LEN];
+    let mut copy = |start: usize, values: &[i16]| {
+        for (offset, value) in values.iter().enumerate() {
+            dims[start + offset] = *value as i32;
+        }
+    };
+    copy(COMMON_STAT, &apparel.common_stat_max.inner.to_array());
+    copy(SEC_STAT, &apparel.sec_stat_max.inner.to_array()[..3]);
+    copy(DEF, &apparel.def.inner.to_array()[..5]);
+    copy(DEF_PCT, &apparel.def_pct_max.inner.to_array()[..5]);
+    copy(DAM_PCT, &apparel.dam_pct_max.inner.to_array()[..6]);
+    copy(ADD, &apparel.add.inner.to_array()[..5]);
+    copy(REQ, &apparel.req.inner.to_array()[..5]);
+    copy(SPELL_COST, &apparel.spell_cost_max.inner.to_array());
+    let melee = &apparel.melee_stat_max;
+    copy(MELEE_STAT, &[melee.md_pct, melee.md_raw]);
+    copy(MELEE_STAT + 2, &melee.md_pct_s.inner.to_array()[..6]);
+    copy(MELEE_STAT + 8, &melee.md_raw_s.inner.to_array()[..6]);
+    copy(
+        MELEE_STAT + 14,
+        &[melee.r_md_pct, melee.r_md_raw, melee.atk_tier],
+    );
+    let damage = &apparel.damage_stat_max;
+    copy(DAMAGE_STAT, &damage.sd_pct_s.inner.to_array()[..6]);
+    copy(DAMAGE_STAT + 6, &damage.sd_raw_s.inner.to_array()[..6]);
+    copy(
+        DAMAGE_STAT + 12,
+        &[
+            damage.r_sd_pct,
+            damage.r_sd_raw,
+            damage.dam_pct,
+            damage.dam_raw,
+        ],
+    );
+    copy(DAMAGE_STAT + 16, &damage.dam_raw_s.inner.to_array()[..6]);
+    copy(
+        DAMAGE_STAT + 22,
+        &[damage.r_dam_pct, damage.r_dam_raw, damage.crit_dam_pct],
+    );
+    dims[HP] = apparel.hp + apparel.hp_bonus_max;
+    dims
+}
+
+fn dim_name(dim: usize) -> String {
+    const POINTS: [&str; 5] = ["str", "dex", "int", "def", "agi"];
+    const DAMAGES: [&str; 7] = [
+        "r_sd_pct",
+        "r_sd_raw",
+        "dam_pct",
+        "dam_raw",
+        "r_dam_pct",
+        "r_dam_raw",
+        "crit_dam_pct",
+    ];
+    match dim {
+        HP => "hp".to_string(),
+        COMMON_STAT..SEC_STAT => COMMON_STATS[dim - COMMON_STAT].to_string(),
+        SEC_STAT..DEF => ["exp_bonus", "loot_bonus", "heal_pct"][dim - SEC_STAT].to_string(),
+        DEF..DEF_PCT => format!("{}_defense", ELEMENTS[dim - DEF + 1]),
+        DEF_PCT..DAM_PCT => format!("{}_defense_pct", ELEMENTS[dim - DEF_PCT + 1]),
+        DAM_PCT..ADD => format!("{}_dam_pct", ELEMENTS[dim - DAM_PCT]),
+        ADD..REQ => format!("{}_bonus", POINTS[dim - ADD]),
+        REQ..SPELL_COST => format!("{}_req", POINTS[dim - REQ]),
+        SPELL_COST..MELEE_STAT => {
+            let lane = dim - SPELL_COST;
+            format!("sp_{}{}", ["pct", "raw"][lane / 4], lane % 4 + 1)
+        }
+        MELEE_STAT..DAMAGE_STAT => "melee damage".to_string(),
+        DAMAGE_STAT..LEN => match dim - DAMAGE_STAT {
+            lane @ 0..6 => format!("{}_sd_pct", ELEMENTS[lane]),
+            lane @ 6..12 => format!("{}_sd_raw", ELEMENTS[lane - 6]),
+            lane @ 12..16 => DAMAGES[lane - 12].to_string(),
+            lane @ 16..22 => format!("{}_dam_raw", ELEMENTS[lane - 16]),
+            lane => DAMAGES[lane - 18].to_string(),
+        },
+        _ => unreachable!(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filter_works() {
+        let apparel = |name: &str, mr: i16, ms: i16, req: i16| Apparel {
+            name: name.to_string(),
+            req: Point::new(req, 0, 0, 0, 0),
+            common_stat_max: CommonStat::new(0, 0, mr, 0, ms, 0, 0, 0),
+            ..Default::default()
+        };
+        let a = apparel("a", 5, 0, 10);
+        let b = apparel("b", 3, 4, 10);
+        let worse = apparel("worse", 4, 9, 20);
+        let equal = apparel("equal", 5, 0, 10);
+        let set = apparel("set", 0, 0, 50);
+
+        let mut dominance = Dominance::default();
+        dominance.stat(&BuildStat::MaxMr, Direction::Higher);
+        assert_eq!(
+            dominance.names(),
+            [
+                "mr",
+                "str_bonus",
+                "dex_bonus",
+                "int_bonus",
+                "def_bonus",
+                "agi_bonus",
+                "str_req",
+                "dex_req",
+                "int_req",
+                "def_req",
+                "agi_req"
+            ]
+        );
+
+        let mut apparels = vec![&a, &b, &worse, &equal, &set];
+        let removed = dominance.filter(&mut apparels, |v| v.name == "set");
+        let names = |v: &[&Apparel]| v.iter().map(|v| v.name.clone()).collect::<Vec<_>>();
+        assert_eq!(names(&apparels), ["a", "set"]);
+        assert_eq!(
+            removed
+                .iter()
+                .map(|(removed, by)| (removed.name.as_str(), by.name.as_str()))
+                .collect::<Vec<_>>(),
+            [("b", "a"), ("worse", "a"), ("equal", "a")]
+        );
+
+        // ms must not be higher, b and worse are kept
+        dominance.stat(&BuildStat::MaxMs, Direction::Exact);
+        let mut apparels = vec![&a, &b, &worse, &equal];
+        dominance.filter(&mut apparels, |_| false);
+        assert_eq!(names(&apparels), ["a", "b", "worse"]);
+
+        // the skill point bonuses are compared even if no stat of the config uses them
+        let bonus = Apparel {
+            add: Point::new(0, 0, 5, 0, 0),
+            ..apparel("bonus", 5, 0, 10)
+        };
+        let mut apparels = vec![&a, &bonus];
+        let removed = Dominance::default().filter(&mut apparels, |_| false);
+        assert_eq!(names(&apparels), ["bonus"]);
+        assert_eq!(removed[0].0.name, "a");
+    }
+
+    #[test]
+    fn dim_names_are_unique() {
+        let names: std::collections::HashSet<String> = (0..LEN)
+            .filter(|dim| !(MELEE_STAT..DAMAGE_STAT).contains(dim))
+            .map(dim_name)
+            .collect();
+        assert_eq!(names.len(), LEN - (DAMAGE_STAT - MELEE_STAT));
+    }
+}
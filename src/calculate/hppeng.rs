@@ -27,29 +27,49 @@ pub struct HppengCodes {
 }
 impl HppengCodes {
     pub fn split_hppeng_url(url: &str) -> Self {
-        let mut url = Url::parse(url).unwrap();
-        let fragment = url.fragment().unwrap().to_owned();
+        Self::parse(url).unwrap()
+    }
+    /// split the url like `split_hppeng_url`, an invalid url is an error instead of a panic
+    pub fn parse(url: &str) -> Result<Self, String> {
+        let mut url = Url::parse(url).map_err(|err| format!("Invalid url {}: {}", url, err))?;
+        let fragment = url
+            .fragment()
+            .ok_or_else(|| format!("No build in url: {}", url))?
+            .to_owned();
+        let codes = fragment
+            .split_once('_')
+            .map(|(_, codes)| codes)
+            .ok_or_else(|| format!("Invalid build: {}", fragment))?;
+        if let Some(ch) = codes.chars().find(|ch| !CUSTOM_DIGITS.contains(ch)) {
+            return Err(format!("Invalid character {} in build: {}", ch, fragment));
+        }
+        let code = |start: usize, end: usize| {
+            fragment
+                .get(start..end)
+                .map(str::to_string)
+                .ok_or_else(|| format!("Build too short: {}", fragment))
+        };
 
         url.set_fragment(None);
         let url_prefix = url.to_string();
 
-        let version = fragment[0..1].to_string();
+        let version = code(0, 1)?;
         let items_end = 2 + 27;
-        let items = fragment[2..items_end].to_string();
+        let items = code(2, items_end)?;
         let skill_point_end = items_end + 10;
-        let skill_point = fragment[items_end..skill_point_end].to_string();
+        let skill_point = code(items_end, skill_point_end)?;
         let level_end = skill_point_end + 2;
-        let level = fragment[skill_point_end..level_end].to_string();
+        let level = code(skill_point_end, level_end)?;
 
-        let powder_length = calculate_powder_length(&fragment[level_end..].to_string());
+        let powder_length = calculate_powder_length(&code(level_end, fragment.len())?);
         let powder_end = level_end + powder_length;
-        let powder = fragment[level_end..powder_end].to_string();
+        let powder = code(level_end, powder_end)?;
 
         let tomes_end = powder_end + 16;
-        let tomes = fragment[powder_end..tomes_end].to_string();
-        let ability = fragment[tomes_end..].to_string();
+        let tomes = code(powder_end, tomes_end)?;
+        let ability = code(tomes_end, fragment.len())?;
 
-        Self {
+        Ok(Self {
             prefix: url_prefix,
             version,
             items,
@@ -58,7 +78,7 @@ impl HppengCodes {
             powder,
             tomes,
             ability,
-        }
+        })
     }
     pub fn generate_url(
         &self,
@@ -88,6 +108,238 @@ impl HppengCodes {
         template
     }
 }
+/// return powders of (helmet, chestplate, leggings, boots, weapon)
+/// https://github.com/hppeng-wynn/hppeng-wynn.github.io/blob/197e50863b366a32251dc77c0511d96004d754d4/js/builder/build_encode_decode.js#L91
+pub fn decode_powders(mut powder_info: &str) -> Result<[Vec<Powder>; 5], String> {
+    let mut powders: [Vec<Powder>; 5] = Default::default();
+
+    for powder_set in powders.iter_mut() {
+        let Some(n_blocks) = powder_info.get(0..1) else {
+            break;
+        };
+        let n_blocks = to_int(n_blocks) as usize;
+        powder_info = &powder_info[1..];
+
+        for _ in 0..n_blocks {
+            let block = powder_info
+                .get(0..5)
+                .ok_or_else(|| format!("Invalid powder block in: {}", powder_info))?;
+            let mut six_powders = to_int(block);
+            while six_powders != 0 {
+                powder_set.push(Powder::new(((six_powders & 0x1f) - 1) as u8)?);
+                six_powders >>= 5;
+            }
+            powder_info = &powder_info[5..];
+        }
+    }
+
+    Ok(powders)
+}
+
+/// https://github.com/hppeng-wynn/hppeng-wynn.github.io/blob/197e50863b366a32251dc77c0511d96004d754d4/js/builder/build_encode_decode.js#L175
+pub fn encode_powders(powders: &[Vec<Powder>; 5]) -> String {
+    let mut result = String::new();
+    for powder_set in powders {
+        let n_blocks = powder_set.len().div_ceil(6);
+        result.push_str(&from_int_n(n_blocks as i32, 1));
+        for six_powders in powder_set.chunks(6) {
+            // the first powder is stored in the lowest bits
+            let powder_hash = six_powders
+                .iter()
+                .rev()
+                .fold(0, |acc, powder| (acc << 5) + 1 + powder.id as i32);
+            result.push_str(&from_int_n(powder_hash, 5));
+        }
+    }
+    result
+}
+
+/// tome slots in url order: 2 weapon tomes, 4 armour tomes, 1 guild tome, 1 lootrun tome
+pub const TOME_SLOTS: [TomeType; 8] = [
+    TomeType::WeaponTome,
+    TomeType::WeaponTome,
+    TomeType::ArmourTome,
+    TomeType::ArmourTome,
+    TomeType::ArmourTome,
+    TomeType::ArmourTome,
+    TomeType::GuildTome,
+    TomeType::LootrunTome,
+];
+/// hppeng ids of the empty tome of each slot
+pub const NONE_TOME_IDS: [i32; 8] = [61, 61, 62, 62, 62, 62, 63, 93];
+
+/// return the tome ids of the 8 tome slots
+/// https://github.com/hppeng-wynn/hppeng-wynn.github.io/blob/197e50863b366a32251dc77c0511d96004d754d4/js/builder/build_encode_decode.js#L114
+pub fn decode_tomes(tomes_info: &str) -> Result<[i32; 8], String> {
+    let mut ids = NONE_TOME_IDS;
+    for (i, id) in ids.iter_mut().enumerate() {
+        let code = tomes_info
+            .get(i * 2..i * 2 + 2)
+            .ok_or_else(|| format!("Invalid tomes code: {}", tomes_info))?;
+        *id = to_int(code) as i32;
+    }
+    Ok(ids)
+}
+
+/// put every tome into the first free slot of its type, the remaining slots are left empty
+/// https://github.com/hppeng-wynn/hppeng-wynn.github.io/blob/197e50863b366a32251dc77c0511d96004d754d4/js/builder/build_encode_decode.js#L196
+pub fn encode_tomes(tomes: &[&Tome], id_map: &HashMap<String, i32>) -> Result<String, String> {
+    let mut ids = NONE_TOME_IDS;
+    let mut used = [false; 8];
+    for tome in tomes {
+        let id = *id_map
+            .get(&tome.name)
+            .ok_or_else(|| format!("Tome id not found: {}", tome.name))?;
+        let slot = (0..8)
+            .find(|&i| !used[i] && TOME_SLOTS[i] == tome.r#type)
+            .ok_or_else(|| format!("No free {} slot for: {}", tome.r#type, tome.name))?;
+        ids[slot] = id;
+        used[slot] = true;
+    }
+    Ok(ids.iter().map(|&id| from_int_n(id, 2)).collect())
+}
+
+/// the tomes of the tome code of a url, `tome_id_map` is the one of `load_hppeng_tome_id_map`
+///
+/// the ids missing from the map, or whose tome is not in `tomes`, are returned beside the tomes,
+/// the stats of their tomes are missing from the build
+pub fn find_decoded_tomes<'a>(
+    tomes_info: &str,
+    tomes: &'a [Tome],
+    tome_id_map: &HashMap<String, i32>,
+) -> Result<(Vec<&'a Tome>, Vec<i32>), String> {
+    let names: HashMap<i32, &String> = tome_id_map.iter().map(|(name, id)| (*id, name)).collect();
+    let mut found = Vec::new();
+    let mut missing = Vec::new();
+    for id in decode_tomes(tomes_info)?
+        .into_iter()
+        .filter(|id| !NONE_TOME_IDS.contains(id))
+    {
+        match names
+            .get(&id)
+            .and_then(|name| tomes.iter().find(|tome| &&tome.name == name))
+        {
+            Some(tome) => found.push(tome),
+            None => missing.push(id),
+        }
+    }
+    Ok((found, missing))
+}
+
+/// hppeng ids of the empty item (`none_items`) of each slot, in url order
+pub const NONE_ITEM_IDS: [i32; 9] = [
+    10000, 10001, 10002, 10003, 10004, 10005, 10006, 10007, 10008,
+];
+
+/// a build decoded from a hppeng url, see `decode_build`
+#[derive(Debug, Clone)]
+pub struct HppengBuild<'a> {
+    /// helmet, chest plate, leggings, boots, ring, ring, bracelet, necklace in url order, `None` if
+    /// the slot is empty
+    pub apparels: [Option<&'a Apparel>; 8],
+    pub weapon: Option<&'a Weapon>,
+    /// the skill points assigned by the url, like `SkillPoints::original`
+    pub skill_point: Point,
+    pub level: i32,
+    /// powders of (helmet, chestplate, leggings, boots, weapon)
+    pub powders: [Vec<Powder>; 5],
+    /// hppeng ids of the tomes of the filled tome slots, see `find_decoded_tomes`
+    pub tome_ids: Vec<i32>,
+    /// the active abilities, empty without a weapon
+    pub abilities: Vec<&'a ATreeNodeData>,
+}
+
+/// decode every part of a hppeng url into the items, the skill points, the level, the powders,
+/// the tome ids and the abilities, `id_map` is the one of `load_hppeng_id_map`
+///
+/// the tomes are left as ids, the tome id table is not complete and the tomes are read with
+/// `find_decoded_tomes` where they are needed
+///
+/// `apparels` are in the order of `load_from_wapi`, only the urls of version 9 with no crafted or
+/// custom item are supported
+pub fn decode_build<'a>(
+    url: &str,
+    apparels: &'a [Vec<Apparel>; 7],
+    weapons: &'a [Weapon],
+    abilities: &'a AbilitiesMap,
+    id_map: &HashMap<String, i32>,
+) -> Result<HppengBuild<'a>, String> {
+    let codes = HppengCodes::parse(url)?;
+    if codes.version != "9" {
+        return Err(format!("Unsupported url version: {}", codes.version));
+    }
+    let names: HashMap<i32, &String> = id_map.iter().map(|(name, id)| (*id, name)).collect();
+    let mut ids = [0; 9];
+    for (i, id) in ids.iter_mut().enumerate() {
+        *id = to_int(&codes.items[i * 3..i * 3 + 3]) as i32;
+    }
+    // the slots of the url in the order of `load_from_wapi`
+    const APPAREL_SLOTS: [usize; 8] = [0, 1, 2, 3, 4, 4, 5, 6];
+    let mut decoded_apparels = [None; 8];
+    for (i, apparel) in decoded_apparels.iter_mut().enumerate() {
+        if ids[i] == NONE_ITEM_IDS[i] {
+            continue;
+        }
+        let name = names
+            .get(&ids[i])
+            .ok_or_else(|| format!("Item id not found: {}", ids[i]))?;
+        *apparel = Some(
+            apparels[APPAREL_SLOTS[i]]
+                .iter()
+                .find(|apparel| &&apparel.name == name)
+                .ok_or_else(|| format!("Item not found: {}", name))?,
+        );
+    }
+    let weapon = match ids[8] {
+        id if id == NONE_ITEM_IDS[8] => None,
+        id => {
+            let name = names
+                .get(&id)
+                .ok_or_else(|| format!("Item id not found: {}", id))?;
+            Some(
+                weapons
+                    .iter()
+                    .find(|weapon| &&weapon.name == name)
+                    .ok_or_else(|| format!("Weapon not found: {}", name))?,
+            )
+        }
+    };
+
+    let mut skill_point = [0; 5];
+    for (i, point) in skill_point.iter_mut().enumerate() {
+        // 12 bits two's complement, the points can be negative
+        let value = to_int(&codes.skill_point[i * 2..i * 2 + 2]) as i16;
+        *point = if value >= 2048 { value - 4096 } else { value };
+    }
+    let [e, t, w, f, a] = skill_point;
+
+    let tome_ids = decode_tomes(&codes.tomes)?
+        .into_iter()
+        .filter(|id| !NONE_TOME_IDS.contains(id))
+        .collect();
+
+    let decoded_abilities = match weapon {
+        Some(weapon) => {
+            let class = Class::from(weapon);
+            let class_abilities = abilities
+                .get(&class)
+                .ok_or_else(|| format!("No abilities of class: {:?}", class))?;
+            decode_atree(class_abilities, &codes.ability)
+        }
+        None => Vec::new(),
+    };
+
+    Ok(HppengBuild {
+        apparels: decoded_apparels,
+        weapon,
+        skill_point: Point::new(e, t, w, f, a),
+        level: to_int(&codes.level) as i32,
+        powders: decode_powders(&codes.powder)?,
+        tome_ids,
+        abilities: decoded_abilities,
+    })
+}
+
 /// https://github.com/hppeng-wynn/hppeng-wynn.github.io/blob/d952c489f021694113ef89cd0a7452c42ce0ccac/js/builder/build_encode_decode.js#L11
 fn calculate_powder_length(mut powder_info: &str) -> usize {
     let mut total_length = 0;
@@ -104,7 +356,7 @@ fn calculate_powder_length(mut powder_info: &str) -> usize {
         let n_blocks = to_int(&n_blocks.to_string()) as usize;
 
         total_length += n_blocks * 5;
-        powder_info = &powder_info[1 + n_blocks * 5..];
+        powder_info = powder_info.get(1 + n_blocks * 5..).unwrap_or_default();
     }
 
     total_length
@@ -183,7 +435,7 @@ impl TreeNode for ATreeNodeData {
 /// https://github.com/hppeng-wynn/hppeng-wynn.github.io/blob/197e50863b366a32251dc77c0511d96004d754d4/js/builder/build_encode_decode.js#L416
 pub fn decode_atree<'a>(
     abilities: &'a Vec<ATreeNodeData>,
-    atree_url: &'a str,
+    atree_url: &str,
 ) -> Vec<&'a ATreeNodeData> {
     let mut active_status = to_base64_bits(atree_url);
     let graph = generate_graph(abilities);
@@ -501,6 +753,89 @@ mod tests {
         );
     }
 
+    #[test]
+    fn decode_powders_works() {
+        let [helmet, chest_plate, leggings, boots, weapon] =
+            decode_powders("00010039I1004fI").unwrap();
+        assert!(helmet.is_empty());
+        assert!(chest_plate.is_empty());
+        assert!(leggings.is_empty());
+        assert_eq!(
+            vec!["w6", "w6", "t6"],
+            boots.iter().map(|v| v.to_string()).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec!["w6", "w6", "w6"],
+            weapon.iter().map(|v| v.to_string()).collect::<Vec<_>>()
+        );
+    }
+    #[test]
+    fn encode_powders_works() {
+        for powder in ["00000", "00010039I1004fI"] {
+            assert_eq!(powder, encode_powders(&decode_powders(powder).unwrap()));
+        }
+        let seven_powders: Vec<Powder> = (0..7).map(|id| Powder::new(id).unwrap()).collect();
+        let powders: [Vec<Powder>; 5] = [
+            vec![],
+            seven_powders.clone(),
+            vec![],
+            vec![],
+            vec![Powder::new(29).unwrap()],
+        ];
+        let encoded = encode_powders(&powders);
+        assert_eq!(1 + 11 + 1 + 1 + 6, encoded.len());
+        assert_eq!(encoded.len(), calculate_powder_length(&encoded));
+        assert_eq!(powders, decode_powders(&encoded).unwrap());
+    }
+    #[test]
+    fn decode_tomes_works() {
+        assert_eq!(NONE_TOME_IDS, decode_tomes("0z0z0+0+0+0+0-1T").unwrap());
+        assert_eq!(50, decode_tomes("0z0z0+0+0+0+0o1T").unwrap()[6]);
+        assert!(decode_tomes("0z0z").is_err());
+    }
+    #[test]
+    fn encode_tomes_works() {
+        let id_map = HashMap::from([("Guild Tome".to_string(), 50)]);
+        assert_eq!("0z0z0+0+0+0+0-1T", encode_tomes(&[], &id_map).unwrap());
+        let tome = Tome {
+            name: "Guild Tome".to_string(),
+            r#type: TomeType::GuildTome,
+            ..Default::default()
+        };
+        assert_eq!("0z0z0+0+0+0+0o1T", encode_tomes(&[&tome], &id_map).unwrap());
+        assert!(encode_tomes(&[&tome, &tome], &id_map).is_err());
+    }
+    #[test]
+    fn find_decoded_tomes_works() {
+        let config: toml::Table =
+            toml::from_str(&std::fs::read_to_string("config/config.toml").unwrap()).unwrap();
+        let url = config["hppeng"]["template_url"].as_str().unwrap();
+        let codes = HppengCodes::parse(url).unwrap();
+        let ids: Vec<i32> = decode_tomes(&codes.tomes)
+            .unwrap()
+            .into_iter()
+            .filter(|id| !NONE_TOME_IDS.contains(id))
+            .collect();
+        assert!(!ids.is_empty());
+
+        let tomes: Vec<Tome> = ids
+            .iter()
+            .map(|id| Tome {
+                name: format!("Tome {}", id),
+                ..Default::default()
+            })
+            .collect();
+        let mut id_map: HashMap<String, i32> =
+            ids.iter().map(|id| (format!("Tome {}", id), *id)).collect();
+        let (found, missing) = find_decoded_tomes(&codes.tomes, &tomes, &id_map).unwrap();
+        assert_eq!(ids.len(), found.len());
+        assert!(missing.is_empty());
+
+        id_map.clear();
+        let (found, missing) = find_decoded_tomes(&codes.tomes, &tomes, &id_map).unwrap();
+        assert!(found.is_empty());
+        assert_eq!(ids, missing);
+    }
     #[test]
     fn from_int_n_works() {
         assert_eq!("JI", from_int_n(1234, 2));
@@ -606,4 +941,172 @@ mod tests {
                 .collect::<Vec<String>>()
         );
     }
+
+    #[test]
+    fn parse_works() {
+        assert!(HppengCodes::parse("https://hppeng-wynn.github.io/builder/?v=10").is_err());
+        assert!(HppengCodes::parse("https://hppeng-wynn.github.io/builder/#9_2SG2SH").is_err());
+        assert!(HppengCodes::parse("https://hppeng-wynn.github.io/builder/#9_2SG2SH2SI2SJ2SK2SL2SM2SN0Q!00002I00001g000000z0z0+0+0+0+0-1T--hOsK5v3").is_err());
+        assert_eq!(
+            HppengCodes::parse("https://hppeng-wynn.github.io/builder/?v=10#9_2SG2SH2SI2SJ2SK2SL2SM2SN0Qf00002I00001g000000z0z0+0+0+0+0-1T--hOsK5v3"),
+            Ok(HppengCodes::split_hppeng_url("https://hppeng-wynn.github.io/builder/?v=10#9_2SG2SH2SI2SJ2SK2SL2SM2SN0Qf00002I00001g000000z0z0+0+0+0+0-1T--hOsK5v3"))
+        );
+    }
+    #[test]
+    fn decode_build_works() {
+        let file = File::open("assets/atree_test_case.json")
+            .expect("The file `atree.json` should exist in the folder assets.");
+        let abilities: AbilitiesMap = serde_json::from_reader(BufReader::new(file)).unwrap();
+        let apparel = |name: &str| Apparel {
+            name: name.to_owned(),
+            ..Default::default()
+        };
+        let apparels = [
+            vec![apparel("Helmet")],
+            vec![],
+            vec![apparel("Leggings")],
+            vec![apparel("Boots")],
+            vec![apparel("Ring A"), apparel("Ring B")],
+            vec![apparel("Bracelet")],
+            vec![apparel("Necklace")],
+        ];
+        let weapons = [Weapon {
+            name: "Spear".to_owned(),
+            r#type: WeaponTypes::Spear,
+            ..Default::default()
+        }];
+        let id_map: HashMap<String, i32> = [
+            "Helmet", "Leggings", "Boots", "Ring A", "Ring B", "Bracelet", "Necklace", "Spear",
+        ]
+        .iter()
+        .enumerate()
+        .map(|(id, name)| (name.to_string(), id as i32 + 1))
+        .collect();
+
+        let codes = HppengCodes {
+            prefix: "https://hppeng-wynn.github.io/builder/".to_owned(),
+            version: "9".to_owned(),
+            items: "".to_owned(),
+            skill_point: "".to_owned(),
+            level: "".to_owned(),
+            powder: "00010039I1004fI".to_owned(),
+            tomes: "050z0+0+0+0+0-1T".to_owned(),
+            ability: "7".to_owned(),
+        };
+        let url = codes.generate_url(
+            None,
+            Some([1, 10001, 2, 3, 5, 4, 6, 7, 8]),
+            Some([-5, 0, 146, 0, 0]),
+            Some(106),
+        );
+        let build = decode_build(&url, &apparels, &weapons, &abilities, &id_map).unwrap();
+
+        assert_eq!(
+            vec![
+                Some("Helmet"),
+                None,
+                Some("Leggings"),
+                Some("Boots"),
+                Some("Ring B"),
+                Some("Ring A"),
+                Some("Bracelet"),
+                Some("Necklace")
+            ],
+            build
+                .apparels
+                .iter()
+                .map(|v| v.map(|v| v.name.as_str()))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!("Spear", build.weapon.unwrap().name);
+        assert_eq!(Point::new(-5, 0, 146, 0, 0), build.skill_point);
+        assert_eq!(106, build.level);
+        assert_eq!(3, build.powders[4].len());
+        assert_eq!(vec![5], build.tome_ids);
+        assert_eq!(
+            vec!["Bash", "Spear Proficiency 1", "Cheaper Bash", "Double Bash"],
+            build
+                .abilities
+                .iter()
+                .map(|v| v.display_name.to_owned())
+                .collect::<Vec<String>>()
+        );
+
+        // an item missing from the items file
+        let url = codes.generate_url(None, Some([1, 10001, 2, 3, 5, 4, 6, 7, 42]), None, None);
+        assert!(decode_build(&url, &apparels, &weapons, &abilities, &id_map).is_err());
+    }
+    #[test]
+    fn decode_template_works() {
+        let config: toml::Table =
+            toml::from_str(&std::fs::read_to_string("config/config.toml").unwrap()).unwrap();
+        let url = config["hppeng"]["template_url"].as_str().unwrap();
+        let file = File::open("assets/atree.json").unwrap();
+        let abilities: AbilitiesMap = serde_json::from_reader(BufReader::new(file)).unwrap();
+        let id_map = load_hppeng_id_map();
+        // the items of the template, the items file is not in the repository
+        let apparel = |name: &str| Apparel {
+            name: name.to_owned(),
+            ..Default::default()
+        };
+        let apparels = [
+            vec![apparel("Morph-Stardust")],
+            vec![apparel("Far Cosmos")],
+            vec![apparel("Anxiolytic")],
+            vec![apparel("Violet-Shift")],
+            vec![apparel("Facile")],
+            vec![apparel("Pandemonium")],
+            vec![apparel("Diamond Fusion Necklace")],
+        ];
+        let weapons = [Weapon {
+            name: "Fatal".to_owned(),
+            r#type: WeaponTypes::Wand,
+            ..Default::default()
+        }];
+
+        let build = decode_build(url, &apparels, &weapons, &abilities, &id_map).unwrap();
+        assert_eq!(
+            vec![
+                "Morph-Stardust",
+                "Far Cosmos",
+                "Anxiolytic",
+                "Violet-Shift",
+                "Facile",
+                "Facile",
+                "Pandemonium",
+                "Diamond Fusion Necklace"
+            ],
+            build
+                .apparels
+                .iter()
+                .map(|v| v.unwrap().name.as_str())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!("Fatal", build.weapon.unwrap().name);
+        assert!(!build.tome_ids.is_empty());
+        assert!(!build.abilities.is_empty());
+
+        // the tomes of the template decode, every id of the tome id map is found
+        let tome_id_map = load_hppeng_tome_id_map();
+        let tomes: Vec<Tome> = tome_id_map
+            .keys()
+            .map(|name| Tome {
+                name: name.clone(),
+                ..Default::default()
+            })
+            .collect();
+        let (found, missing) = find_decoded_tomes(
+            &HppengCodes::parse(url).unwrap().tomes,
+            &tomes,
+            &tome_id_map,
+        )
+        .unwrap();
+        assert_eq!(build.tome_ids.len(), found.len() + missing.len());
+        assert!(found
+            .iter()
+            .all(|tome| build.tome_ids.contains(&tome_id_map[&tome.name])));
+        assert!(missing
+            .iter()
+            .all(|id| build.tome_ids.contains(id) && !tome_id_map.values().any(|v| v == id)));
+    }
 }
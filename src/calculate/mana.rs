@@ -0,0 +1,44 @@
+use crate::items::*;
+
+use super::skill_points_to_percentage;
+
+/// mana cost of a spell after intelligence, item and ability tree modifiers
+/// https://github.com/hppeng-wynn/hppeng-wynn.github.io/blob/HEAD/js/builder/builder_graph.js
+pub fn spell_cost(spell: &Spell, int: i16, spell_cost: &SpellCost, atree_stat: &ATreeStat) -> f64 {
+    // intelligence reduces the base cost by half of its percentage
+    let mut cost = (spell.cost as f64 * (1.0 - skill_points_to_percentage(int) * 0.5)).floor();
+    cost += spell_cost.sp_raw(spell.id) as f64;
+    cost = (cost * (1.0 + spell_cost.sp_pct(spell.id) as f64 / 100.0)).floor();
+    (cost.max(1.0) * (1.0 + atree_stat.sp_pct_final(spell.id) / 100.0)).max(1.0)
+}
+
+/// mana gained per second from mana regen (per 5 seconds) and mana steal (per 3 seconds)
+pub fn mana_sustain(common_stat: &CommonStat) -> f64 {
+    common_stat.mr() as f64 / 5.0 + common_stat.ms() as f64 / 3.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spell_cost_works() {
+        let spell = Spell::new("Ice Snake".to_string(), 4, 30, Vec::new());
+        let atree_stat = ATreeStat::default();
+        assert_eq!(
+            spell_cost(&spell, 0, &SpellCost::default(), &atree_stat),
+            30.0
+        );
+        // 30 * (1 - 0.0951 / 2) = 28.57 -> 28, 28 - 4 = 24, 24 * 0.9 = 21.6 -> 21
+        let items = SpellCost::new([0, 0, 0, -10], [0, 0, 0, -4]);
+        assert_eq!(spell_cost(&spell, 10, &items, &atree_stat), 21.0);
+
+        let items = SpellCost::new([0, 0, 0, 0], [0, 0, 0, -40]);
+        assert_eq!(spell_cost(&spell, 0, &items, &atree_stat), 1.0);
+    }
+    #[test]
+    fn mana_sustain_works() {
+        let common_stat = CommonStat::new(0, 0, 10, 0, 9, 0, 0, 0);
+        assert_eq!(mana_sustain(&common_stat), 5.0);
+    }
+}
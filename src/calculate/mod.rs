@@ -1,17 +1,23 @@
 mod ability;
+mod bound;
 mod dam;
 mod damage_calculate;
 mod def;
+mod dominance;
 mod hp;
 mod hppeng;
+mod mana;
 mod skill_point;
 
 pub use ability::*;
+pub use bound::*;
 pub use dam::*;
 pub use damage_calculate::*;
 pub use def::*;
+pub use dominance::*;
 pub use hp::*;
 pub use hppeng::*;
+pub use mana::*;
 pub use skill_point::*;
 
 /// https://github.com/hppeng-wynn/hppeng-wynn.github.io/blob/HEAD/js/build_utils.js#L8
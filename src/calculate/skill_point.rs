@@ -1,3 +1,4 @@
+use std::simd::Select;
 use std::ops::{BitAnd, BitOr};
 use std::{fmt, simd::i16x8};
 
@@ -125,11 +126,54 @@ impl SkillPoints {
         items: &'a [&'a Apparel; LEN],
         weapon: &Weapon,
     ) -> (SkillPoints, [&'a Apparel; LEN]) {
+        Self::scc_put_calculate_with_base(items, weapon, &Point::default(), &Sets::default())
+    }
+    /// same as `scc_put_calculate`, `base` is the bonus point that is always active (e.g. tomes)
+    ///
+    /// the skill points of the set bonuses change with every piece put on, like hppeng's
+    /// `activeSetCounts`, so a piece only counts the bonus of the pieces put on before it
+    /// https://github.com/hppeng-wynn/hppeng-wynn.github.io/blob/HEAD/js/skillpoints.js
+    pub fn scc_put_calculate_with_base<'a, const LEN: usize>(
+        items: &'a [&'a Apparel; LEN],
+        weapon: &Weapon,
+        base: &Point,
+        sets: &Sets,
+    ) -> (SkillPoints, [&'a Apparel; LEN]) {
+        // (index of the set among the sets of the items, index of the set in `sets`)
+        let mut item_sets: [Option<(usize, usize)>; LEN] = [None; LEN];
+        for i in 0..LEN {
+            item_sets[i] = sets.set_of(&items[i].name).map(|set| {
+                let first = (0..i)
+                    .find(|&j| item_sets[j].is_some_and(|(_, other)| other == set))
+                    .unwrap_or(i);
+                (first, set)
+            });
+        }
+        // the change of the set bonus a piece can make, depending on the pieces put on before it
+        let mut add_min = items.map(|item| item.add.clone());
+        let mut add_max = add_min.clone();
+        for i in 0..LEN {
+            if let Some((local, set)) = item_sets[i] {
+                let pieces = item_sets
+                    .iter()
+                    .filter(|v| v.is_some_and(|(other, _)| other == local))
+                    .count();
+                for count in 0..pieces {
+                    let delta = sets.bonus_add(set, count + 1) - sets.bonus_add(set, count);
+                    let add = items[i].add.clone() + delta;
+                    add_min[i].inner = add_min[i].inner.simd_min(add.inner);
+                    add_max[i].inner = add_max[i].inner.simd_max(add.inner);
+                }
+            }
+        }
+
         let mut depend_relation = [[false; LEN]; LEN];
         for i in 0..LEN {
             for j in i..LEN {
-                depend_relation[i][j] = depend_check(items[i], items[j]);
-                depend_relation[j][i] = depend_check(items[j], items[i]);
+                depend_relation[i][j] =
+                    depend_check((&items[i].req, &add_min[i]), (&items[j].req, &add_max[j]));
+                depend_relation[j][i] =
+                    depend_check((&items[j].req, &add_min[j]), (&items[i].req, &add_max[i]));
             }
         }
 
@@ -138,9 +182,9 @@ impl SkillPoints {
         let depend_group = tarjans_scc(&depend_relation);
 
         let zero = i16x8::splat(0);
-        let compute = |mut context: (SkillPoints, Point, [usize; LEN], usize),
+        let compute = |mut context: (SkillPoints, Point, [usize; LEN], usize, [usize; LEN]),
                        index: usize|
-         -> (SkillPoints, Point, [usize; LEN], usize) {
+         -> (SkillPoints, Point, [usize; LEN], usize, [usize; LEN]) {
             let item = items[index];
 
             // permutation
@@ -162,6 +206,14 @@ impl SkillPoints {
             // add point
             context.0.original.inner += item.add.inner;
 
+            // the set bonus becomes the one of one more piece
+            if let Some((local, set)) = item_sets[index] {
+                let count = context.4[local];
+                context.0.original +=
+                    &(sets.bonus_add(set, count + 1) - sets.bonus_add(set, count));
+                context.4[local] += 1;
+            }
+
             // fill negative add gap
             let tolerance = item.req.inner.simd_ne(zero).select(item.add.inner, zero);
             context.1.inner = context.1.inner.simd_max(item.req.inner + tolerance);
@@ -180,8 +232,18 @@ impl SkillPoints {
         };
         let best = permutation_2d_usize(
             &depend_group,
-            // (skill point result, min point request, permutation array, permutation array index)
-            (SkillPoints::default(), Point::default(), [0; LEN], 0),
+            // (skill point result, min point request, permutation array, permutation array index,
+            // pieces put on of each set)
+            (
+                SkillPoints {
+                    assign: Point::default(),
+                    original: base.clone(),
+                },
+                Point::default(),
+                [0; LEN],
+                0,
+                [0; LEN],
+            ),
             compute,
         )
         .into_iter()
@@ -259,11 +321,13 @@ impl std::fmt::Display for SkillPoints {
     }
 }
 
-fn depend_check(b: &Apparel, a: &Apparel) -> bool {
+/// `b` and `a` are (req, add), the add of `b` is its lowest and the one of `a` its highest, they
+/// differ when the set bonus of a piece depends on the pieces put on before it
+fn depend_check(b: (&Point, &Point), a: (&Point, &Point)) -> bool {
     let zero = i16x8::splat(0);
-    let a_req_lt_b_req = a.req.inner.simd_lt(b.req.inner);
-    let b_add_gt_0 = b.add.inner.simd_lt(zero);
-    let a_add_lt_0 = a.add.inner.simd_gt(zero);
+    let a_req_lt_b_req = a.0.inner.simd_lt(b.0.inner);
+    let b_add_gt_0 = b.1.inner.simd_lt(zero);
+    let a_add_lt_0 = a.1.inner.simd_gt(zero);
     let b_depends_on_a = a_add_lt_0.bitand(a_req_lt_b_req.bitor(b_add_gt_0));
     b_depends_on_a.any()
 }
@@ -306,6 +370,72 @@ mod tests {
         }
     }
     #[test]
+    fn scc_put_calculate_with_base_works() {
+        let apparels = gen_test_apparels();
+        for v in apparels {
+            let apparels: [&Apparel; 8] = v.apparels.iter().collect::<Vec<_>>().try_into().unwrap();
+            let (req, _) = SkillPoints::scc_put_calculate_with_base(
+                &apparels,
+                &v.weapon,
+                &Point::default(),
+                &Sets::default(),
+            );
+            assert_eq!(req, v.skill_point);
+            let (req, _) = SkillPoints::scc_put_calculate_with_base(
+                &apparels,
+                &v.weapon,
+                &Point::new(200, 200, 200, 200, 200),
+                &Sets::default(),
+            );
+            assert_eq!(req.assign, Point::default());
+        }
+    }
+    #[test]
+    fn scc_put_calculate_with_sets_works() {
+        let sets: SetsMap = serde_json::from_str(
+            r#"{
+                "Test": {
+                    "items": ["Cap", "Boots", "Pants"],
+                    "bonuses": [{"str": 10}, {"str": 10}, {"str": 10, "dex": -5}]
+                }
+            }"#,
+        )
+        .unwrap();
+        let sets = Sets::new(&sets);
+        let item = |name: &str, req: Point| Apparel {
+            name: name.to_string(),
+            req,
+            ..Default::default()
+        };
+        let cap = item("Cap", Point::new(10, 0, 0, 0, 0));
+        let boots = item("Boots", Point::new(10, 0, 0, 0, 0));
+        let pants = item("Pants", Point::default());
+        let weapon = Weapon::default();
+
+        // the bonus is not active when the first piece is put on
+        let items = [&cap, &boots];
+        let (always_active, _) = SkillPoints::scc_put_calculate_with_base(
+            &items,
+            &weapon,
+            &sets.bonuses(&items)[0].add,
+            &Sets::default(),
+        );
+        assert!(always_active.check(5));
+        let (req, _) =
+            SkillPoints::scc_put_calculate_with_base(&items, &weapon, &Point::default(), &sets);
+        assert_eq!(req.assign, Point::new(10, 0, 0, 0, 0));
+        assert_eq!(req.original, Point::new(20, 0, 0, 0, 0));
+        assert!(!req.check(5));
+
+        // a piece without requirements is put on first, its bonus is active for the other pieces
+        let items = [&cap, &pants, &boots];
+        let (req, order) =
+            SkillPoints::scc_put_calculate_with_base(&items, &weapon, &Point::default(), &sets);
+        assert_eq!(req.assign, Point::default());
+        assert_eq!(req.original, Point::new(10, -5, 0, 0, 0));
+        assert_eq!(order[0].name, "Pants");
+    }
+    #[test]
     fn fast_gap_works() {
         let apparels = gen_test_apparels();
         for v in apparels {
@@ -1,7 +1,12 @@
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, path::Path, str};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    str,
+};
 use tokio::{fs::File, io::AsyncReadExt};
 
+use super::{BinOp, Expr};
 use crate::items::*;
 
 #[derive(Debug, Deserialize, Clone)]
@@ -10,6 +15,8 @@ pub struct Config {
     pub player: Player,
     pub hppeng: Hppeng,
     pub api: Option<Api>,
+    pub powders: Option<Powders>,
+    pub toggles: Option<HashMap<String, ToggleValue>>,
     pub threshold_first: Option<ThresholdFirst>,
     pub threshold_second: Option<ThresholdSecond>,
     pub threshold_third: Option<ThresholdThird>,
@@ -18,10 +25,17 @@ pub struct Config {
     pub threshold_sixth: Option<ThresholdSixth>,
     pub threshold_seventh: Option<ThresholdSeventh>,
     pub threshold_eighth: Option<ThresholdEighth>,
+    pub threshold_ninth: Option<ThresholdNinth>,
+    pub threshold_tenth: Option<ThresholdTenth>,
     #[serde(default)]
     pub threshold_damages: Vec<Damage>,
+    #[serde(default)]
+    pub constraints: Vec<Constraint>,
+    pub objective: Option<Objective>,
+    pub search: Option<Search>,
 }
 const MIN_16: i16 = i16::MIN / 2;
+const MAX_16: i16 = i16::MAX / 2;
 impl Config {
     pub fn hp_threshold(&self) -> Option<i32> {
         if let Some(threshold) = &self.threshold_first {
@@ -51,6 +65,7 @@ impl Config {
             Some(SecStat::new(
                 threshold.min_exp_bonus.unwrap_or(MIN_16),
                 threshold.min_loot_bonus.unwrap_or(MIN_16),
+                MIN_16,
             ))
         } else {
             None
@@ -110,6 +125,133 @@ impl Config {
             None
         }
     }
+    pub fn hp_max_threshold(&self) -> Option<i32> {
+        if let Some(threshold) = &self.threshold_first {
+            threshold.max_hp
+        } else {
+            None
+        }
+    }
+    pub fn common_stat_max_threshold(&self) -> Option<CommonStat> {
+        self.threshold_second.as_ref().map(|threshold| {
+            CommonStat::new(
+                threshold.max_hpr_raw.unwrap_or(MAX_16),
+                threshold.max_hpr_pct.unwrap_or(MAX_16),
+                threshold.max_mr.unwrap_or(MAX_16),
+                threshold.max_ls.unwrap_or(MAX_16),
+                threshold.max_ms.unwrap_or(MAX_16),
+                threshold.max_spd.unwrap_or(MAX_16),
+                threshold.max_sd_raw.unwrap_or(MAX_16),
+                threshold.max_sd_pct.unwrap_or(MAX_16),
+            )
+        })
+    }
+    pub fn sec_stat_max_threshold(&self) -> Option<SecStat> {
+        self.threshold_eighth.as_ref().map(|threshold| {
+            SecStat::new(
+                threshold.max_exp_bonus.unwrap_or(MAX_16),
+                threshold.max_loot_bonus.unwrap_or(MAX_16),
+                MAX_16,
+            )
+        })
+    }
+    pub fn hpr_max_threshold(&self) -> Option<i32> {
+        if let Some(threshold) = &self.threshold_third {
+            threshold.max_hpr
+        } else {
+            None
+        }
+    }
+    pub fn def_max_threshold(&self) -> Option<Point> {
+        self.threshold_fourth.as_ref().map(|threshold| {
+            Point::new(
+                threshold.max_earth_defense.unwrap_or(MAX_16),
+                threshold.max_thunder_defense.unwrap_or(MAX_16),
+                threshold.max_water_defense.unwrap_or(MAX_16),
+                threshold.max_fire_defense.unwrap_or(MAX_16),
+                threshold.max_air_defense.unwrap_or(MAX_16),
+            )
+        })
+    }
+    pub fn dam_max_threshold(&self) -> Option<Dam> {
+        self.threshold_fifth.as_ref().map(|threshold| {
+            Dam::new(
+                threshold.max_neutral_dam_pct.unwrap_or(MAX_16),
+                threshold.max_earth_dam_pct.unwrap_or(MAX_16),
+                threshold.max_thunder_dam_pct.unwrap_or(MAX_16),
+                threshold.max_water_dam_pct.unwrap_or(MAX_16),
+                threshold.max_fire_dam_pct.unwrap_or(MAX_16),
+                threshold.max_air_dam_pct.unwrap_or(MAX_16),
+            )
+        })
+    }
+    pub fn point_max_threshold(&self) -> Option<Point> {
+        self.threshold_sixth.as_ref().map(|threshold| {
+            Point::new(
+                threshold.max_earth_point.unwrap_or(MAX_16),
+                threshold.max_thunder_point.unwrap_or(MAX_16),
+                threshold.max_water_point.unwrap_or(MAX_16),
+                threshold.max_fire_point.unwrap_or(MAX_16),
+                threshold.max_air_point.unwrap_or(MAX_16),
+            )
+        })
+    }
+    pub fn ehp_max_threshold(&self) -> Option<i32> {
+        if let Some(threshold) = &self.threshold_seventh {
+            threshold.max_ehp
+        } else {
+            None
+        }
+    }
+    pub fn melee_dps_threshold(&self) -> Option<i32> {
+        if let Some(threshold) = &self.threshold_ninth {
+            threshold.min_melee_dps
+        } else {
+            None
+        }
+    }
+    pub fn mana_sustain_threshold(&self) -> Option<f64> {
+        if let Some(threshold) = &self.threshold_tenth {
+            threshold.min_mana_sustain
+        } else {
+            None
+        }
+    }
+    /// return (spell name, seconds between casts), the interval defaults to 1 second
+    pub fn spam_spell(&self) -> Option<(&str, f64)> {
+        let threshold = self.threshold_tenth.as_ref()?;
+        let name = threshold.spam_spell.as_deref()?;
+        Some((name, threshold.spam_interval.unwrap_or(1.0)))
+    }
+    /// return powders of (helmet, chestplate, leggings, boots, weapon),
+    /// slots not set in the config use the powders of `template`
+    pub fn powders(&self, template: [Vec<Powder>; 5]) -> [Vec<Powder>; 5] {
+        let Some(powders) = &self.powders else {
+            return template;
+        };
+        let [helmet, chest_plate, leggings, boots, weapon] = template;
+        [
+            powders.helmet.clone().unwrap_or(helmet),
+            powders.chest_plate.clone().unwrap_or(chest_plate),
+            powders.leggings.clone().unwrap_or(leggings),
+            powders.boots.clone().unwrap_or(boots),
+            powders.weapon.clone().unwrap_or(weapon),
+        ]
+    }
+    /// toggles and sliders of the atree, `true` is 1 and `false` is 0
+    pub fn toggles(&self) -> Toggles {
+        self.toggles
+            .iter()
+            .flatten()
+            .map(|(name, value)| {
+                let value = match value {
+                    ToggleValue::Bool(v) => *v as i32 as f64,
+                    ToggleValue::Number(v) => *v,
+                };
+                (name.clone(), value)
+            })
+            .collect()
+    }
     // (name, normal, crit, avg)
     pub fn damage_threshold(&self) -> HashMap<&str, (i32, i32, i32)> {
         self.threshold_damages
@@ -126,6 +268,103 @@ impl Config {
             })
             .collect()
     }
+    /// remove the thresholds and the constraints, the spell parts of `threshold_damages` are kept so
+    /// they are still calculated
+    pub fn clear_thresholds(&mut self) {
+        self.threshold_first = None;
+        self.threshold_second = None;
+        self.threshold_third = None;
+        self.threshold_fourth = None;
+        self.threshold_fifth = None;
+        self.threshold_sixth = None;
+        self.threshold_seventh = None;
+        self.threshold_eighth = None;
+        self.threshold_ninth = None;
+        self.threshold_tenth = None;
+        for damage in &mut self.threshold_damages {
+            damage.min_normal = None;
+            damage.min_crit = None;
+            damage.min_avg = None;
+        }
+        self.constraints.clear();
+    }
+    /// parse the `constraints`, the error points at the offending constraint
+    pub fn constraints(&self) -> Result<Vec<Expr>, String> {
+        self.constraints
+            .iter()
+            .enumerate()
+            .map(|(index, constraint)| {
+                Expr::condition(&constraint.expr).map_err(|err| {
+                    format!(
+                        "constraint {} is invalid: {}\n{}\n{}^",
+                        index + 1,
+                        err,
+                        constraint.expr,
+                        " ".repeat(err.column - 1)
+                    )
+                })
+            })
+            .collect()
+    }
+    pub fn branch_and_bound(&self) -> bool {
+        self.search
+            .as_ref()
+            .is_some_and(|search| search.branch_and_bound)
+    }
+    pub fn dominance(&self) -> bool {
+        self.search.as_ref().is_some_and(|search| search.dominance)
+    }
+    pub fn output(&self) -> Output {
+        self.hppeng.output.unwrap_or_default()
+    }
+    /// the file the builds are written to, `db_path` for sqlite, the other outputs write
+    /// `output_path`, or `db_path` with their extension
+    pub fn output_path(&self) -> PathBuf {
+        let db_path = Path::new(&self.hppeng.db_path);
+        match (self.output(), &self.hppeng.output_path) {
+            (Output::Sqlite, _) => db_path.to_path_buf(),
+            (_, Some(path)) => PathBuf::from(path),
+            (Output::Csv, None) => db_path.with_extension("csv"),
+            (Output::Jsonl, None) => db_path.with_extension("jsonl"),
+        }
+    }
+    pub fn checkpoint_interval(&self) -> Option<u64> {
+        self.search
+            .as_ref()
+            .and_then(|search| search.checkpoint_interval)
+    }
+    /// parse the weights of the `objective` into their weighted sum
+    pub fn objective(&self) -> Result<Option<Expr>, String> {
+        let Some(objective) = &self.objective else {
+            return Ok(None);
+        };
+        if objective.top_k == 0 {
+            return Err("objective top_k must be greater than 0".to_string());
+        }
+        if objective.weights.is_empty() {
+            return Err("objective has no weights".to_string());
+        }
+        let mut terms = objective.weights.iter().map(|(term, weight)| {
+            Expr::value(term)
+                .map(|expr| {
+                    Expr::Binary(BinOp::Mul, Box::new(Expr::Number(*weight)), Box::new(expr))
+                })
+                .map_err(|err| {
+                    format!(
+                        "objective weight is invalid: {}\n{}\n{}^",
+                        err,
+                        term,
+                        " ".repeat(err.column - 1)
+                    )
+                })
+        });
+        let first = terms.next().unwrap()?;
+        terms
+            .try_fold(first, |sum, term| {
+                Ok(Expr::Binary(BinOp::Add, Box::new(sum), Box::new(term?)))
+            })
+            .map(Some)
+    }
 }
 #[derive(Debug, Deserialize, Clone, Serialize)]
 pub struct Items {
@@ -136,9 +375,25 @@ pub struct Items {
     pub rings: Vec<String>,
     pub bracelets: Vec<String>,
     pub necklaces: Vec<String>,
-    pub weapon: String,
+    /// a weapon name or a list of weapons of the same class
+    pub weapon: WeaponNames,
+    pub tomes: Option<Vec<String>>,
     pub illegal_combinations: Option<Vec<Vec<String>>>,
 }
+impl Items {
+    pub fn weapons(&self) -> &[String] {
+        match &self.weapon {
+            WeaponNames::One(name) => std::slice::from_ref(name),
+            WeaponNames::Many(names) => names,
+        }
+    }
+}
+#[derive(Debug, Deserialize, Clone, Serialize)]
+#[serde(untagged)]
+pub enum WeaponNames {
+    One(String),
+    Many(Vec<String>),
+}
 #[derive(Debug, Deserialize, Clone)]
 pub struct Player {
     pub lvl: i32,
@@ -154,6 +409,22 @@ pub struct Hppeng {
     pub items_file: String,
     pub log_db_errors: bool,
     pub db_retry_count: u8,
+    /// where the builds are written, sqlite by default
+    pub output: Option<Output>,
+    /// the file of the csv and jsonl outputs, `db_path` with their extension by default
+    pub output_path: Option<String>,
+}
+/// where the builder writes the builds
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Output {
+    /// The `build`, `damage` and `spell_cost` tables of the database, the checkpoints are saved too
+    #[default]
+    Sqlite,
+    /// A header then one line of comma separated values per build
+    Csv,
+    /// One json object per line and build
+    Jsonl,
 }
 #[derive(Debug, Deserialize, Clone)]
 pub struct Api {
@@ -163,10 +434,29 @@ pub struct Api {
     pub query: String,
 }
 #[derive(Debug, Deserialize, Clone)]
+pub struct Powders {
+    pub helmet: Option<Vec<Powder>>,
+    pub chest_plate: Option<Vec<Powder>>,
+    pub leggings: Option<Vec<Powder>>,
+    pub boots: Option<Vec<Powder>>,
+    pub weapon: Option<Vec<Powder>>,
+}
+/// a toggle is on/off, a slider has a number
+#[derive(Debug, Deserialize, Clone)]
+#[serde(untagged)]
+pub enum ToggleValue {
+    Bool(bool),
+    Number(f64),
+}
+/// the thresholds reject the unknown keys, a misspelled threshold would be ignored otherwise
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct ThresholdFirst {
     pub min_hp: Option<i32>,
+    pub max_hp: Option<i32>,
 }
 #[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct ThresholdSecond {
     pub min_hpr_raw: Option<i16>,
     pub min_hpr_pct: Option<i16>,
@@ -176,20 +466,37 @@ pub struct ThresholdSecond {
     pub min_spd: Option<i16>,
     pub min_sd_raw: Option<i16>,
     pub min_sd_pct: Option<i16>,
+    pub max_hpr_raw: Option<i16>,
+    pub max_hpr_pct: Option<i16>,
+    pub max_mr: Option<i16>,
+    pub max_ls: Option<i16>,
+    pub max_ms: Option<i16>,
+    pub max_spd: Option<i16>,
+    pub max_sd_raw: Option<i16>,
+    pub max_sd_pct: Option<i16>,
 }
 #[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct ThresholdThird {
     pub min_hpr: Option<i32>,
+    pub max_hpr: Option<i32>,
 }
 #[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct ThresholdFourth {
     pub min_earth_defense: Option<i16>,
     pub min_thunder_defense: Option<i16>,
     pub min_water_defense: Option<i16>,
     pub min_fire_defense: Option<i16>,
     pub min_air_defense: Option<i16>,
+    pub max_earth_defense: Option<i16>,
+    pub max_thunder_defense: Option<i16>,
+    pub max_water_defense: Option<i16>,
+    pub max_fire_defense: Option<i16>,
+    pub max_air_defense: Option<i16>,
 }
 #[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct ThresholdFifth {
     pub min_neutral_dam_pct: Option<i16>,
     pub min_earth_dam_pct: Option<i16>,
@@ -197,33 +504,104 @@ pub struct ThresholdFifth {
     pub min_water_dam_pct: Option<i16>,
     pub min_fire_dam_pct: Option<i16>,
     pub min_air_dam_pct: Option<i16>,
+    pub max_neutral_dam_pct: Option<i16>,
+    pub max_earth_dam_pct: Option<i16>,
+    pub max_thunder_dam_pct: Option<i16>,
+    pub max_water_dam_pct: Option<i16>,
+    pub max_fire_dam_pct: Option<i16>,
+    pub max_air_dam_pct: Option<i16>,
 }
 #[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct ThresholdSixth {
     pub min_earth_point: Option<i16>,
     pub min_thunder_point: Option<i16>,
     pub min_water_point: Option<i16>,
     pub min_fire_point: Option<i16>,
     pub min_air_point: Option<i16>,
+    pub max_earth_point: Option<i16>,
+    pub max_thunder_point: Option<i16>,
+    pub max_water_point: Option<i16>,
+    pub max_fire_point: Option<i16>,
+    pub max_air_point: Option<i16>,
 }
 #[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct ThresholdSeventh {
     pub min_ehp: Option<i32>,
+    pub max_ehp: Option<i32>,
 }
 #[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct ThresholdEighth {
     pub min_exp_bonus: Option<i16>,
     pub min_loot_bonus: Option<i16>,
+    pub max_exp_bonus: Option<i16>,
+    pub max_loot_bonus: Option<i16>,
+}
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ThresholdNinth {
+    pub min_melee_dps: Option<i32>,
 }
 #[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ThresholdTenth {
+    pub min_mana_sustain: Option<f64>,
+    pub spam_spell: Option<String>,
+    pub spam_interval: Option<f64>,
+}
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct Damage {
     pub name: String,
     pub min_normal: Option<i32>,
     pub min_crit: Option<i32>,
     pub min_avg: Option<i32>,
 }
+/// a condition over the stats and spell damages of a build, see `Expr`
+#[derive(Debug, Deserialize, Clone)]
+pub struct Constraint {
+    pub expr: String,
+}
+/// keep the best builds by a weighted sum instead of saving every build
+#[derive(Debug, Deserialize, Clone)]
+pub struct Objective {
+    /// number of builds kept
+    pub top_k: usize,
+    /// seconds between the reports of the best builds while running
+    pub report_interval: Option<u64>,
+    /// stat or spell value (see `Expr`) to weight, e.g. `max_ehp` or `spell("Meteor.Total").avg`
+    pub weights: HashMap<String, f64>,
+}
+/// how the combinations are enumerated
+#[derive(Debug, Deserialize, Clone)]
+pub struct Search {
+    /// depth-first search that skips the partial combinations whose bounds can not pass the
+    /// thresholds or beat the best builds of the objective, instead of the random order
+    #[serde(default)]
+    pub branch_and_bound: bool,
+    /// remove the candidates that are worse than another of the same slot on every stat used by
+    /// the thresholds, the constraints and the objective, see `Dominance`
+    #[serde(default)]
+    pub dominance: bool,
+    /// seconds between the checkpoints of the finished segments of the random order, a run with the
+    /// same config and items resumes from its checkpoint
+    pub checkpoint_interval: Option<u64>,
+}
 
 pub async fn load_config(path: impl AsRef<Path>) -> Result<Config, String> {
+    load_config_with_overrides(path, &[])
+        .await
+        .map(|(config, _)| config)
+}
+
+/// load the config with the `overrides` (dotted key, value) applied, e.g.
+/// `("threshold_first.min_hp", "12000")`, and return the merged table too
+pub async fn load_config_with_overrides(
+    path: impl AsRef<Path>,
+    overrides: &[(String, String)],
+) -> Result<(Config, toml::Table), String> {
     // Check if the config folder exists
     let config_folder = path.as_ref().parent().unwrap();
     if !config_folder.exists() {
@@ -252,8 +630,111 @@ pub async fn load_config(path: impl AsRef<Path>) -> Result<Config, String> {
         Err(err) => Err(err.to_string()),
     }?;
 
-    match toml::from_str(str::from_utf8(&buffer).unwrap()) {
+    let mut table: toml::Table = match toml::from_str(str::from_utf8(&buffer).unwrap()) {
         Ok(ok) => Ok(ok),
         Err(err) => Err(err.to_string()),
+    }?;
+    for (key, value) in overrides {
+        set_value(&mut table, key, value)?;
+    }
+
+    match toml::Value::Table(table.clone()).try_into() {
+        Ok(ok) => Ok((ok, table)),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+/// set the value of a dotted key, the missing tables are created, the value is parsed as a toml
+/// value or kept as a string
+fn set_value(table: &mut toml::Table, key: &str, value: &str) -> Result<(), String> {
+    let mut keys: Vec<&str> = key.split('.').collect();
+    let last = keys.pop().unwrap();
+    let mut table = table;
+    for name in keys {
+        table = match table
+            .entry(name)
+            .or_insert_with(|| toml::Value::Table(toml::Table::new()))
+        {
+            toml::Value::Table(table) => table,
+            _ => return Err(format!("can not set `{}`, `{}` is not a table", key, name)),
+        };
+    }
+    let value = toml::from_str::<toml::Table>(&format!("v = {}", value))
+        .ok()
+        .and_then(|mut table| table.remove("v"))
+        .unwrap_or_else(|| toml::Value::String(value.to_owned()));
+    table.insert(last.to_owned(), value);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_value_works() {
+        let mut table: toml::Table = toml::from_str(
+            r#"
+            [player]
+            lvl = 106
+            [threshold_first]
+            "#,
+        )
+        .unwrap();
+        set_value(&mut table, "threshold_first.min_hp", "12000").unwrap();
+        set_value(&mut table, "threshold_second.min_mr", "5").unwrap();
+        set_value(&mut table, "items.weapon", "Idol").unwrap();
+        set_value(&mut table, "items.rings", r#"["Facile", "Prism"]"#).unwrap();
+        assert!(set_value(&mut table, "player.lvl.max", "1").is_err());
+
+        assert_eq!(
+            table["threshold_first"]["min_hp"],
+            toml::Value::Integer(12000)
+        );
+        assert_eq!(table["threshold_second"]["min_mr"], toml::Value::Integer(5));
+        assert_eq!(
+            table["items"]["weapon"],
+            toml::Value::String("Idol".to_owned())
+        );
+        assert_eq!(table["items"]["rings"].as_array().unwrap().len(), 2);
+    }
+    #[test]
+    fn unknown_threshold_is_rejected() {
+        let config = |key: &str| {
+            let mut table: toml::Table =
+                toml::from_str(&std::fs::read_to_string("config/config.toml").unwrap()).unwrap();
+            set_value(&mut table, key, "1").unwrap();
+            toml::Value::Table(table).try_into::<Config>()
+        };
+        assert!(config("threshold_first.min_hp").is_ok());
+        assert!(config("threshold_first.min_hpp")
+            .unwrap_err()
+            .to_string()
+            .contains("min_hpp"));
+        assert!(config("threshold_tenth.min_mana").is_err());
+    }
+    #[test]
+    fn weapons_works() {
+        let items = |weapon: &str| {
+            toml::from_str::<Items>(&format!(
+                r#"
+                helmets = []
+                chest_plates = []
+                leggings = []
+                boots = []
+                rings = []
+                bracelets = []
+                necklaces = []
+                weapon = {}
+                "#,
+                weapon
+            ))
+        };
+        assert_eq!(items(r#""Fatal""#).unwrap().weapons(), ["Fatal"]);
+        assert_eq!(
+            items(r#"["Fatal", "Nirvana"]"#).unwrap().weapons(),
+            ["Fatal", "Nirvana"]
+        );
+        assert!(items("1").is_err());
     }
 }
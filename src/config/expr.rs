@@ -0,0 +1,633 @@
+use std::fmt;
+
+const ELEMENTS: [&str; 5] = ["earth", "thunder", "water", "fire", "air"];
+
+/// a stat of a build, the names follow the thresholds, e.g. `max_mr`, `max_fire_defense`,
+/// `max_neutral_dam_pct` and `earth_point`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuildStat {
+    MaxHp,
+    MaxEhp,
+    MaxHpr,
+    MaxHprRaw,
+    MaxHprPct,
+    MaxMr,
+    MaxLs,
+    MaxMs,
+    MaxSpd,
+    MaxSdRaw,
+    MaxSdPct,
+    MaxExpBonus,
+    MaxLootBonus,
+    MaxHealPct,
+    MeleeDps,
+    ManaSustain,
+    /// e t w f a
+    MaxDefense(usize),
+    /// n e t w f a
+    MaxDamPct(usize),
+    /// e t w f a, the skill points like in `threshold_sixth`
+    Point(usize),
+}
+impl BuildStat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        let stat = match name {
+            "max_hp" => BuildStat::MaxHp,
+            "max_ehp" => BuildStat::MaxEhp,
+            "max_hpr" => BuildStat::MaxHpr,
+            "max_hpr_raw" => BuildStat::MaxHprRaw,
+            "max_hpr_pct" => BuildStat::MaxHprPct,
+            "max_mr" => BuildStat::MaxMr,
+            "max_ls" => BuildStat::MaxLs,
+            "max_ms" => BuildStat::MaxMs,
+            "max_spd" => BuildStat::MaxSpd,
+            "max_sd_raw" => BuildStat::MaxSdRaw,
+            "max_sd_pct" => BuildStat::MaxSdPct,
+            "max_exp_bonus" => BuildStat::MaxExpBonus,
+            "max_loot_bonus" => BuildStat::MaxLootBonus,
+            "max_heal_pct" => BuildStat::MaxHealPct,
+            "melee_dps" => BuildStat::MeleeDps,
+            "mana_sustain" => BuildStat::ManaSustain,
+            _ => {
+                let element = |name: Option<&str>| {
+                    name.and_then(|name| ELEMENTS.iter().position(|v| *v == name))
+                };
+                if let Some(index) = element(
+                    name.strip_prefix("max_")
+                        .and_then(|v| v.strip_suffix("_defense")),
+                ) {
+                    BuildStat::MaxDefense(index)
+                } else if let Some(index) = name
+                    .strip_prefix("max_")
+                    .and_then(|v| v.strip_suffix("_dam_pct"))
+                    .and_then(|v| {
+                        if v == "neutral" {
+                            Some(0)
+                        } else {
+                            element(Some(v)).map(|index| index + 1)
+                        }
+                    })
+                {
+                    BuildStat::MaxDamPct(index)
+                } else {
+                    BuildStat::Point(element(name.strip_suffix("_point"))?)
+                }
+            }
+        };
+        Some(stat)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DamageKind {
+    Normal,
+    Crit,
+    Avg,
+}
+
+/// a value of a build used by an expression
+#[derive(Debug, Clone, PartialEq)]
+pub enum Var {
+    Stat(BuildStat),
+    /// `spell("Meteor.Total").avg`, the name is a spell part like in `threshold_damages`
+    SpellDamage(String, DamageKind),
+    /// `spell_cost("Meteor")`
+    SpellCost(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+    Eq,
+    Ne,
+    And,
+    Or,
+}
+
+/// an expression over the stats of a build, conditions evaluate to 1.0 (true) or 0.0 (false)
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Number(f64),
+    Var(Var),
+    Neg(Box<Expr>),
+    Not(Box<Expr>),
+    Binary(BinOp, Box<Expr>, Box<Expr>),
+}
+impl Expr {
+    /// parse an expression that must be a condition, e.g. `max_mr + max_ms / 2 >= 15`
+    pub fn condition(source: &str) -> Result<Self, ParseError> {
+        parse(source, Type::Bool)
+    }
+    /// parse an expression that must be a number, e.g. `spell("Meteor.Total").avg`
+    pub fn value(source: &str) -> Result<Self, ParseError> {
+        parse(source, Type::Number)
+    }
+    pub fn eval(&self, value: &impl Fn(&Var) -> f64) -> f64 {
+        let bool = |v: bool| if v { 1.0 } else { 0.0 };
+        match self {
+            Expr::Number(v) => *v,
+            Expr::Var(var) => value(var),
+            Expr::Neg(expr) => -expr.eval(value),
+            Expr::Not(expr) => bool(expr.eval(value) == 0.0),
+            Expr::Binary(op, lhs, rhs) => {
+                let lhs = lhs.eval(value);
+                // short circuit
+                match op {
+                    BinOp::And if lhs == 0.0 => return 0.0,
+                    BinOp::Or if lhs != 0.0 => return 1.0,
+                    _ => {}
+                }
+                let rhs = rhs.eval(value);
+                match op {
+                    BinOp::Add => lhs + rhs,
+                    BinOp::Sub => lhs - rhs,
+                    BinOp::Mul => lhs * rhs,
+                    BinOp::Div => lhs / rhs,
+                    BinOp::Gt => bool(lhs > rhs),
+                    BinOp::Ge => bool(lhs >= rhs),
+                    BinOp::Lt => bool(lhs < rhs),
+                    BinOp::Le => bool(lhs <= rhs),
+                    BinOp::Eq => bool(lhs == rhs),
+                    BinOp::Ne => bool(lhs != rhs),
+                    BinOp::And | BinOp::Or => bool(rhs != 0.0),
+                }
+            }
+        }
+    }
+    pub fn test(&self, value: &impl Fn(&Var) -> f64) -> bool {
+        self.eval(value) != 0.0
+    }
+    /// (constant, [(weight, value)]) if the expression is a weighted sum of values
+    pub fn linear(&self) -> Option<(f64, Vec<(f64, &Var)>)> {
+        fn scale(
+            (constant, terms): (f64, Vec<(f64, &Var)>),
+            factor: f64,
+        ) -> (f64, Vec<(f64, &Var)>) {
+            (
+                constant * factor,
+                terms
+                    .into_iter()
+                    .map(|(weight, var)| (weight * factor, var))
+                    .collect(),
+            )
+        }
+        match self {
+            Expr::Number(v) => Some((*v, Vec::new())),
+            Expr::Var(var) => Some((0.0, vec![(1.0, var)])),
+            Expr::Neg(expr) => expr.linear().map(|v| scale(v, -1.0)),
+            Expr::Not(_) => None,
+            Expr::Binary(op, lhs, rhs) => {
+                let (lhs, rhs) = (lhs.linear()?, rhs.linear()?);
+                match op {
+                    BinOp::Add | BinOp::Sub => {
+                        let sign = if *op == BinOp::Add { 1.0 } else { -1.0 };
+                        let (constant, mut terms) = lhs;
+                        let rhs = scale(rhs, sign);
+                        terms.extend(rhs.1);
+                        Some((constant + rhs.0, terms))
+                    }
+                    BinOp::Mul if lhs.1.is_empty() => Some(scale(rhs, lhs.0)),
+                    BinOp::Mul if rhs.1.is_empty() => Some(scale(lhs, rhs.0)),
+                    BinOp::Div if rhs.1.is_empty() => Some(scale(lhs, 1.0 / rhs.0)),
+                    _ => None,
+                }
+            }
+        }
+    }
+    /// all the values used by the expression
+    pub fn vars(&self) -> Vec<&Var> {
+        let mut vars = Vec::new();
+        self.collect_vars(&mut vars);
+        vars
+    }
+    fn collect_vars<'a>(&'a self, vars: &mut Vec<&'a Var>) {
+        match self {
+            Expr::Number(_) => {}
+            Expr::Var(var) => vars.push(var),
+            Expr::Neg(expr) | Expr::Not(expr) => expr.collect_vars(vars),
+            Expr::Binary(_, lhs, rhs) => {
+                lhs.collect_vars(vars);
+                rhs.collect_vars(vars);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    /// 1-based column of the offending token
+    pub column: usize,
+    pub message: String,
+}
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at column {}", self.message, self.column)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Ident(String),
+    Str(String),
+    Op(&'static str),
+}
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Number(v) => write!(f, "{}", v),
+            Token::Ident(v) => write!(f, "{}", v),
+            Token::Str(v) => write!(f, "\"{}\"", v),
+            Token::Op(v) => write!(f, "{}", v),
+        }
+    }
+}
+/// longer operators first
+const OPS: [&str; 16] = [
+    ">=", "<=", "==", "!=", "&&", "||", "+", "-", "*", "/", "(", ")", ".", ">", "<", "!",
+];
+
+/// (column, token)
+fn tokenize(source: &str) -> Result<Vec<(usize, Token)>, ParseError> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let column = i + 1;
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() {
+            let start = i;
+            while i < chars.len() && chars[i].is_ascii_digit() {
+                i += 1;
+            }
+            if i + 1 < chars.len() && chars[i] == '.' && chars[i + 1].is_ascii_digit() {
+                i += 1;
+                while i < chars.len() && chars[i].is_ascii_digit() {
+                    i += 1;
+                }
+            }
+            let number: String = chars[start..i].iter().collect();
+            tokens.push((column, Token::Number(number.parse().unwrap())));
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push((column, Token::Ident(chars[start..i].iter().collect())));
+        } else if c == '"' {
+            let start = i + 1;
+            i = start;
+            while i < chars.len() && chars[i] != '"' {
+                i += 1;
+            }
+            if i == chars.len() {
+                return Err(ParseError {
+                    column,
+                    message: "unterminated string".to_string(),
+                });
+            }
+            tokens.push((column, Token::Str(chars[start..i].iter().collect())));
+            i += 1;
+        } else {
+            let Some(op) = OPS.iter().find(|op| {
+                op.chars()
+                    .enumerate()
+                    .all(|(offset, v)| chars.get(i + offset) == Some(&v))
+            }) else {
+                return Err(ParseError {
+                    column,
+                    message: format!("unexpected character `{}`", c),
+                });
+            };
+            tokens.push((column, Token::Op(op)));
+            i += op.len();
+        }
+    }
+    Ok(tokens)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Type {
+    Number,
+    Bool,
+}
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Type::Number => write!(f, "a number"),
+            Type::Bool => write!(f, "a condition"),
+        }
+    }
+}
+
+fn parse(source: &str, expected: Type) -> Result<Expr, ParseError> {
+    let mut parser = Parser {
+        tokens: tokenize(source)?,
+        index: 0,
+        end: source.chars().count() + 1,
+    };
+    let (expr, ty) = parser.or()?;
+    if let Some((column, token)) = parser.tokens.get(parser.index) {
+        return Err(ParseError {
+            column: *column,
+            message: format!("unexpected `{}`", token),
+        });
+    }
+    if ty != expected {
+        return Err(ParseError {
+            column: 1,
+            message: format!("expected {}, found {}", expected, ty),
+        });
+    }
+    Ok(expr)
+}
+
+/// recursive descent, from the lowest precedence: `||`, `&&`, comparisons, `+ -`, `* /`, unary
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    index: usize,
+    /// column after the last character
+    end: usize,
+}
+impl Parser {
+    fn peek_op(&self, ops: &[&str]) -> Option<(usize, &'static str)> {
+        match self.tokens.get(self.index) {
+            Some((column, Token::Op(op))) if ops.contains(op) => Some((*column, *op)),
+            _ => None,
+        }
+    }
+    fn next(&mut self) -> Result<(usize, Token), ParseError> {
+        let token = self.tokens.get(self.index).cloned().ok_or(ParseError {
+            column: self.end,
+            message: "unexpected end of expression".to_string(),
+        })?;
+        self.index += 1;
+        Ok(token)
+    }
+    fn expect_op(&mut self, op: &str) -> Result<(), ParseError> {
+        match self.next()? {
+            (_, Token::Op(v)) if v == op => Ok(()),
+            (column, token) => Err(ParseError {
+                column,
+                message: format!("expected `{}`, found `{}`", op, token),
+            }),
+        }
+    }
+    fn check(column: usize, op: &str, ty: Type, expected: Type) -> Result<(), ParseError> {
+        if ty == expected {
+            Ok(())
+        } else {
+            Err(ParseError {
+                column,
+                message: format!("`{}` expects {}, found {}", op, expected, ty),
+            })
+        }
+    }
+    /// left associative binary operators of the same precedence
+    fn binary(
+        &mut self,
+        ops: &[(&str, BinOp)],
+        operand: Type,
+        result: Type,
+        next: fn(&mut Self) -> Result<(Expr, Type), ParseError>,
+    ) -> Result<(Expr, Type), ParseError> {
+        let names: Vec<&str> = ops.iter().map(|(name, _)| *name).collect();
+        let (mut lhs, mut lhs_ty) = next(self)?;
+        while let Some((column, name)) = self.peek_op(&names) {
+            self.index += 1;
+            let (rhs, rhs_ty) = next(self)?;
+            Self::check(column, name, lhs_ty, operand)?;
+            Self::check(column, name, rhs_ty, operand)?;
+            let op = ops.iter().find(|(v, _)| *v == name).unwrap().1;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+            lhs_ty = result;
+        }
+        Ok((lhs, lhs_ty))
+    }
+    fn or(&mut self) -> Result<(Expr, Type), ParseError> {
+        self.binary(&[("||", BinOp::Or)], Type::Bool, Type::Bool, Self::and)
+    }
+    fn and(&mut self) -> Result<(Expr, Type), ParseError> {
+        self.binary(
+            &[("&&", BinOp::And)],
+            Type::Bool,
+            Type::Bool,
+            Self::comparison,
+        )
+    }
+    fn comparison(&mut self) -> Result<(Expr, Type), ParseError> {
+        const OPS: [(&str, BinOp); 6] = [
+            (">", BinOp::Gt),
+            (">=", BinOp::Ge),
+            ("<", BinOp::Lt),
+            ("<=", BinOp::Le),
+            ("==", BinOp::Eq),
+            ("!=", BinOp::Ne),
+        ];
+        let names = OPS.map(|(name, _)| name);
+        let (lhs, lhs_ty) = self.sum()?;
+        let Some((column, name)) = self.peek_op(&names) else {
+            return Ok((lhs, lhs_ty));
+        };
+        self.index += 1;
+        let (rhs, rhs_ty) = self.sum()?;
+        Self::check(column, name, lhs_ty, Type::Number)?;
+        Self::check(column, name, rhs_ty, Type::Number)?;
+        if let Some((column, _)) = self.peek_op(&names) {
+            return Err(ParseError {
+                column,
+                message: "comparisons can not be chained, use `&&`".to_string(),
+            });
+        }
+        let op = OPS.iter().find(|(v, _)| *v == name).unwrap().1;
+        Ok((Expr::Binary(op, Box::new(lhs), Box::new(rhs)), Type::Bool))
+    }
+    fn sum(&mut self) -> Result<(Expr, Type), ParseError> {
+        self.binary(
+            &[("+", BinOp::Add), ("-", BinOp::Sub)],
+            Type::Number,
+            Type::Number,
+            Self::product,
+        )
+    }
+    fn product(&mut self) -> Result<(Expr, Type), ParseError> {
+        self.binary(
+            &[("*", BinOp::Mul), ("/", BinOp::Div)],
+            Type::Number,
+            Type::Number,
+            Self::unary,
+        )
+    }
+    fn unary(&mut self) -> Result<(Expr, Type), ParseError> {
+        if let Some((column, op)) = self.peek_op(&["-", "!"]) {
+            self.index += 1;
+            let (expr, ty) = self.unary()?;
+            return if op == "-" {
+                Self::check(column, op, ty, Type::Number)?;
+                Ok((Expr::Neg(Box::new(expr)), Type::Number))
+            } else {
+                Self::check(column, op, ty, Type::Bool)?;
+                Ok((Expr::Not(Box::new(expr)), Type::Bool))
+            };
+        }
+        self.primary()
+    }
+    fn primary(&mut self) -> Result<(Expr, Type), ParseError> {
+        match self.next()? {
+            (_, Token::Number(v)) => Ok((Expr::Number(v), Type::Number)),
+            (_, Token::Op("(")) => {
+                let expr = self.or()?;
+                self.expect_op(")")?;
+                Ok(expr)
+            }
+            (column, Token::Ident(name)) if self.peek_op(&["("]).is_some() => {
+                self.index += 1;
+                let argument = match self.next()? {
+                    (_, Token::Str(v)) => v,
+                    (column, token) => {
+                        return Err(ParseError {
+                            column,
+                            message: format!("expected a spell name, found `{}`", token),
+                        })
+                    }
+                };
+                self.expect_op(")")?;
+                let var = match name.as_str() {
+                    "spell" => {
+                        self.expect_op(".")?;
+                        let kind = match self.next()? {
+                            (_, Token::Ident(v)) if v == "normal" => DamageKind::Normal,
+                            (_, Token::Ident(v)) if v == "crit" => DamageKind::Crit,
+                            (_, Token::Ident(v)) if v == "avg" => DamageKind::Avg,
+                            (column, token) => {
+                                return Err(ParseError {
+                                    column,
+                                    message: format!(
+                                        "expected `normal`, `crit` or `avg`, found `{}`",
+                                        token
+                                    ),
+                                })
+                            }
+                        };
+                        Var::SpellDamage(argument, kind)
+                    }
+                    "spell_cost" => Var::SpellCost(argument),
+                    _ => {
+                        return Err(ParseError {
+                            column,
+                            message: format!(
+                                "unknown function `{}`, expected `spell` or `spell_cost`",
+                                name
+                            ),
+                        })
+                    }
+                };
+                Ok((Expr::Var(var), Type::Number))
+            }
+            (column, Token::Ident(name)) => match BuildStat::from_name(&name) {
+                Some(stat) => Ok((Expr::Var(Var::Stat(stat)), Type::Number)),
+                None => Err(ParseError {
+                    column,
+                    message: format!("unknown stat `{}`", name),
+                }),
+            },
+            (column, token) => Err(ParseError {
+                column,
+                message: format!("expected a number, a stat or `(`, found `{}`", token),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn value(var: &Var) -> f64 {
+        match var {
+            Var::Stat(BuildStat::MaxMr) => 10.0,
+            Var::Stat(BuildStat::MaxMs) => 12.0,
+            Var::Stat(BuildStat::MaxEhp) => 60000.0,
+            Var::Stat(BuildStat::MaxDamPct(0)) => 5.0,
+            Var::SpellDamage(name, DamageKind::Avg) if name == "Meteor.Total" => 15000.0,
+            Var::SpellCost(name) if name == "Meteor" => 40.0,
+            _ => 0.0,
+        }
+    }
+
+    #[test]
+    fn condition_works() {
+        let test = |source: &str| Expr::condition(source).unwrap().test(&value);
+        assert!(test("max_mr + max_ms/2 >= 15"));
+        assert!(!test("max_mr + max_ms/2 > 16"));
+        assert!(test(r#"max_ehp * 0.5 + spell("Meteor.Total").avg > 40000"#));
+        assert!(test("2 + 3 * 4 == 14 && (2 + 3) * 4 == 20"));
+        assert!(test("-max_mr < 0 || max_hp > 0"));
+        assert!(test(
+            r#"!(spell_cost("Meteor") > 40) && max_neutral_dam_pct == 5"#
+        ));
+        assert!(test("earth_point == 0 && max_fire_defense <= 0"));
+    }
+
+    #[test]
+    fn vars_works() {
+        let expr = Expr::condition(r#"max_mr > spell_cost("Meteor") / 3"#).unwrap();
+        assert_eq!(
+            expr.vars(),
+            vec![
+                &Var::Stat(BuildStat::MaxMr),
+                &Var::SpellCost("Meteor".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn linear_works() {
+        let expr = Expr::value("2 * (max_mr - max_ms / 4) + 3 - -max_hp").unwrap();
+        assert_eq!(
+            expr.linear(),
+            Some((
+                3.0,
+                vec![
+                    (2.0, &Var::Stat(BuildStat::MaxMr)),
+                    (-0.5, &Var::Stat(BuildStat::MaxMs)),
+                    (1.0, &Var::Stat(BuildStat::MaxHp)),
+                ]
+            ))
+        );
+        assert_eq!(Expr::value("max_mr * max_ms").unwrap().linear(), None);
+    }
+
+    #[test]
+    fn parse_errors() {
+        let error = |source: &str| Expr::condition(source).unwrap_err();
+        assert_eq!(error("max_mr + >= 15").column, 10);
+        assert_eq!(error("max_mana > 15").column, 1);
+        assert_eq!(
+            error("max_mr + max_ms").message,
+            "expected a condition, found a number"
+        );
+        assert_eq!(
+            error("max_mr > 1 > 0").message,
+            "comparisons can not be chained, use `&&`"
+        );
+        assert_eq!(error("(max_mr > 1").column, 12);
+        assert_eq!(error("max_mr > 1 + (max_ms > 1)").column, 12);
+        assert_eq!(error(r#"spell("Meteor.Total").max > 0"#).column, 23);
+        assert_eq!(error("max_mr # 1").column, 8);
+        assert_eq!(
+            Expr::value("max_mr > 1").unwrap_err().message,
+            "expected a number, found a condition"
+        );
+        assert_eq!(error("max_mr > 1 max_ms").column, 12);
+    }
+}
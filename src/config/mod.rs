@@ -1,3 +1,5 @@
 mod build_config;
+mod expr;
 
 pub use build_config::*;
+pub use expr::*;
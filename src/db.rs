@@ -3,10 +3,15 @@ use std::future::Future;
 use std::io::Write;
 
 use rand::Rng;
-use sqlx::{migrate::Migrator, sqlite::SqlitePoolOptions, Pool, Sqlite};
+use sqlx::{
+    migrate::Migrator,
+    sqlite::{SqliteConnection, SqlitePoolOptions},
+    Pool, Sqlite,
+};
 use tokio::fs::metadata;
 use tokio::fs::{DirBuilder, File};
 
+use crate::output::{self, Build, Row};
 use crate::*;
 
 pub async fn init(config: &Config) -> Pool<Sqlite> {
@@ -67,59 +72,129 @@ pub async fn init(config: &Config) -> Pool<Sqlite> {
     pool
 }
 
-pub async fn save_build(
-    pool: sqlx::SqlitePool,
-    url: String,
-    status: Status,
-    combination: [&Apparel; 8],
-) {
-    let config = load_config("config/config.toml").await.unwrap();
+/// the run the builds are saved for, see `save_run`
+pub struct Run {
+    pub id: i64,
+    /// row ids of the items by name
+    pub items: HashMap<String, i64>,
+}
+impl Run {
+    /// the items of the builds are the candidates saved with the run, another item is an error
+    fn item_id(&self, name: &str) -> Result<i64, sqlx::Error> {
+        self.items.get(name).copied().ok_or_else(|| {
+            sqlx::Error::Encode(format!("item {} is not saved with the run", name).into())
+        })
+    }
+}
 
-    // why use 'let':
-    // [Cannot use temporaries as query! arguments when using SQLite](https://github.com/launchbadge/sqlx/issues/1430)
-    let assign_strength = status.skill_point.assign.e();
-    let assign_dexterity = status.skill_point.assign.t();
-    let assign_intelligence = status.skill_point.assign.w();
-    let assign_defense = status.skill_point.assign.f();
-    let assign_agility = status.skill_point.assign.a();
-
-    let original_e = status.skill_point.original.e();
-    let original_t = status.skill_point.original.t();
-    let original_w = status.skill_point.original.w();
-    let original_f = status.skill_point.original.f();
-    let original_a = status.skill_point.original.a();
-
-    let max_def_e = status.max_def.e();
-    let max_def_t = status.max_def.t();
-    let max_def_w = status.max_def.w();
-    let max_def_f = status.max_def.f();
-    let max_def_a = status.max_def.a();
-
-    let mr = status.max_common_stat.mr();
-    let ms = status.max_common_stat.ms();
-    let spd = status.max_common_stat.spd();
-    let ls = status.max_common_stat.ls();
-    let hpr_raw = status.max_common_stat.hpr_raw();
-    let hpr_pct = status.max_common_stat.hpr_pct();
-    let sd_raw = status.max_common_stat.sd_raw();
-    let sd_pct = status.max_common_stat.sd_pct();
-
-    let max_dam_pct_n = status.max_dam_pct.n();
-    let max_dam_pct_e = status.max_dam_pct.e();
-    let max_dam_pct_t = status.max_dam_pct.t();
-    let max_dam_pct_w = status.max_dam_pct.w();
-    let max_dam_pct_f = status.max_dam_pct.f();
-    let max_dam_pct_a = status.max_dam_pct.a();
-
-    let max_exp_bonus = status.max_sec_stat.exp_bonus();
-    let max_loot_bonus = status.max_sec_stat.loot_bonus();
+/// record a run, its config and the items it can use, `items` are (name, type), the items of an
+/// items version are only saved once
+pub async fn save_run(
+    pool: &sqlx::SqlitePool,
+    key: &str,
+    config: &str,
+    items_version: &str,
+    items: &[(String, String)],
+) -> Run {
+    let mut tx = pool.begin().await.unwrap();
+    let id = sqlx::query("INSERT INTO run(key, config, items_version) VALUES ($1, $2, $3)")
+        .bind(key)
+        .bind(config)
+        .bind(items_version)
+        .execute(&mut *tx)
+        .await
+        .unwrap()
+        .last_insert_rowid();
+    let mut ids = HashMap::new();
+    for (name, r#type) in items {
+        sqlx::query(
+            "INSERT INTO item(name, type, items_version) VALUES ($1, $2, $3) ON CONFLICT(name, items_version) DO NOTHING",
+        )
+        .bind(name)
+        .bind(r#type)
+        .bind(items_version)
+        .execute(&mut *tx)
+        .await
+        .unwrap();
+        let (item_id,): (i64,) =
+            sqlx::query_as("SELECT row_id FROM item WHERE name = $1 AND items_version = $2")
+                .bind(name)
+                .bind(items_version)
+                .fetch_one(&mut *tx)
+                .await
+                .unwrap();
+        ids.insert(name.clone(), item_id);
+    }
+    tx.commit().await.unwrap();
+    Run { id, items: ids }
+}
 
+/// write the rows in one transaction
+pub async fn write(pool: &sqlx::SqlitePool, config: &Config, run: &Run, rows: &[Row]) {
     retry_with_backoff(
         || async {
             let mut tx = pool.begin().await?;
+            for row in rows {
+                match row {
+                    Row::Build(build) => insert_build(&mut tx, run, build).await?,
+                    Row::Checkpoint(run, segments) => {
+                        insert_checkpoint(&mut tx, run, segments).await?
+                    }
+                }
+            }
+            tx.commit().await
+        },
+        config,
+    )
+    .await;
+}
 
-            let result = sqlx::query(
-                r#"
+async fn insert_build(
+    conn: &mut SqliteConnection,
+    run: &Run,
+    build: &Build,
+) -> Result<(), sqlx::Error> {
+    // why use 'let':
+    // [Cannot use temporaries as query! arguments when using SQLite](https://github.com/launchbadge/sqlx/issues/1430)
+    let assign_strength = build.status.skill_point.assign.e();
+    let assign_dexterity = build.status.skill_point.assign.t();
+    let assign_intelligence = build.status.skill_point.assign.w();
+    let assign_defense = build.status.skill_point.assign.f();
+    let assign_agility = build.status.skill_point.assign.a();
+
+    let original_e = build.status.skill_point.original.e();
+    let original_t = build.status.skill_point.original.t();
+    let original_w = build.status.skill_point.original.w();
+    let original_f = build.status.skill_point.original.f();
+    let original_a = build.status.skill_point.original.a();
+
+    let max_def_e = build.status.max_def.e();
+    let max_def_t = build.status.max_def.t();
+    let max_def_w = build.status.max_def.w();
+    let max_def_f = build.status.max_def.f();
+    let max_def_a = build.status.max_def.a();
+
+    let mr = build.status.max_common_stat.mr();
+    let ms = build.status.max_common_stat.ms();
+    let spd = build.status.max_common_stat.spd();
+    let ls = build.status.max_common_stat.ls();
+    let hpr_raw = build.status.max_common_stat.hpr_raw();
+    let hpr_pct = build.status.max_common_stat.hpr_pct();
+    let sd_raw = build.status.max_common_stat.sd_raw();
+    let sd_pct = build.status.max_common_stat.sd_pct();
+
+    let max_dam_pct_n = build.status.max_dam_pct.n();
+    let max_dam_pct_e = build.status.max_dam_pct.e();
+    let max_dam_pct_t = build.status.max_dam_pct.t();
+    let max_dam_pct_w = build.status.max_dam_pct.w();
+    let max_dam_pct_f = build.status.max_dam_pct.f();
+    let max_dam_pct_a = build.status.max_dam_pct.a();
+
+    let max_exp_bonus = build.status.max_sec_stat.exp_bonus();
+    let max_loot_bonus = build.status.max_sec_stat.loot_bonus();
+
+    let result = sqlx::query(
+        r#"
         INSERT INTO build (
             url,
             helmet,
@@ -131,7 +206,7 @@ pub async fn save_build(
             bracelet,
             necklace,
             earth_assign,thunder_assign,water_assign,fire_assign,air_assign,
-            earth_original,thunder_original,water_original,fire_original,ari_original,
+            earth_original,thunder_original,water_original,fire_original,air_original,
             earth_def,thunder_def,water_def,fire_def,air_def,
             max_mr,
             max_ms,
@@ -145,106 +220,190 @@ pub async fn save_build(
             max_hp,
             max_hpr,
             max_neutral_dam_pct,max_earth_dam_pct,max_thunder_dam_pct,max_water_dam_pct,max_fire_dam_pct,max_air_dam_pct,
-            max_exp_bonus,max_loot_bonus
+            max_exp_bonus,max_loot_bonus,
+            melee_dps,
+            mana_sustain,
+            weapon,
+            run_id
         ) VALUES (
             $1,$2,$3,$4,$5,$6,$7,$8,$9,$10,
             $11,$12,$13,$14,$15,$16,$17,$18,$19,$20,
             $21,$22,$23,$24,$25,$26,$27,$28,$29,$30,
             $31,$32,$33,$34,$35,$36,$37,$38,$39,$40,
-            $41,$42,$43
-        );
+            $41,$42,$43,$44,$45,$46,$47
+        ) ON CONFLICT(url) DO NOTHING;
         "#,
+    )
+    .bind(&build.url)
+    .bind(&build.items[2])
+    .bind(&build.items[3])
+    .bind(&build.items[4])
+    .bind(&build.items[5])
+    .bind(&build.items[0])
+    .bind(&build.items[1])
+    .bind(&build.items[6])
+    .bind(&build.items[7])
+    .bind(assign_strength)
+    .bind(assign_dexterity)
+    .bind(assign_intelligence)
+    .bind(assign_defense)
+    .bind(assign_agility)
+    .bind(original_e)
+    .bind(original_t)
+    .bind(original_w)
+    .bind(original_f)
+    .bind(original_a)
+    .bind(max_def_e)
+    .bind(max_def_t)
+    .bind(max_def_w)
+    .bind(max_def_f)
+    .bind(max_def_a)
+    .bind(mr)
+    .bind(ms)
+    .bind(spd)
+    .bind(ls)
+    .bind(hpr_raw)
+    .bind(hpr_pct)
+    .bind(sd_raw)
+    .bind(sd_pct)
+    .bind(build.status.max_ehp)
+    .bind(build.status.max_hp)
+    .bind(build.status.max_hpr)
+    .bind(max_dam_pct_n)
+    .bind(max_dam_pct_e)
+    .bind(max_dam_pct_t)
+    .bind(max_dam_pct_w)
+    .bind(max_dam_pct_f)
+    .bind(max_dam_pct_a)
+    .bind(max_exp_bonus)
+    .bind(max_loot_bonus)
+    .bind(build.status.melee_dps)
+    .bind(build.status.mana_sustain)
+    .bind(&build.weapon)
+    .bind(run.id)
+    .execute(&mut *conn)
+    .await?;
+    // the url is already saved by this or an earlier run, only the run is recorded
+    if result.rows_affected() == 0 {
+        let (row_id,): (i64,) = sqlx::query_as("SELECT row_id FROM build WHERE url = $1")
+            .bind(&build.url)
+            .fetch_one(&mut *conn)
+            .await?;
+        return insert_build_run(conn, row_id, run).await;
+    }
+    let row_id = result.last_insert_rowid();
+    insert_build_run(conn, row_id, run).await?;
+
+    for v in &build.status.spell_damages {
+        sqlx::query(
+            r#"
+            INSERT INTO damage(
+            build_id,
+            name,
+            normal,
+            crit,
+            avg
+            ) VALUES (
+            $1,
+            $2,
+            $3,
+            $4,
+            $5
             )
-            .bind(url.clone())
-            .bind(combination[2].name.clone())
-            .bind(combination[3].name.clone())
-            .bind(combination[4].name.clone())
-            .bind(combination[5].name.clone())
-            .bind(combination[0].name.clone())
-            .bind(combination[1].name.clone())
-            .bind(combination[6].name.clone())
-            .bind(combination[7].name.clone())
-            .bind(assign_strength)
-            .bind(assign_dexterity)
-            .bind(assign_intelligence)
-            .bind(assign_defense)
-            .bind(assign_agility)
-            .bind(original_e)
-            .bind(original_t)
-            .bind(original_w)
-            .bind(original_f)
-            .bind(original_a)
-            .bind(max_def_e)
-            .bind(max_def_t)
-            .bind(max_def_w)
-            .bind(max_def_f)
-            .bind(max_def_a)
-            .bind(mr)
-            .bind(ms)
-            .bind(spd)
-            .bind(ls)
-            .bind(hpr_raw)
-            .bind(hpr_pct)
-            .bind(sd_raw)
-            .bind(sd_pct)
-            .bind(status.max_ehp)
-            .bind(status.max_hp)
-            .bind(status.max_hpr)
-            .bind(max_dam_pct_n)
-            .bind(max_dam_pct_e)
-            .bind(max_dam_pct_t)
-            .bind(max_dam_pct_w)
-            .bind(max_dam_pct_f)
-            .bind(max_dam_pct_a)
-            .bind(max_exp_bonus)
-            .bind(max_loot_bonus)
-            .execute(&mut *tx)
-            .await;
-
-            let row_id = match result {
-                Ok(value) => value.last_insert_rowid(),
-                Err(sqlx::Error::Database(e)) => {
-                    // continue UNIQUE url
-                    if e.code() == Some("2067".into()) {
-                        return tx.rollback().await;
-                    }
-                    return Err(sqlx::Error::Database(e));
-                }
-                Err(e) => return Err(e),
-            };
-
-            for v in &status.spell_damages {
-                sqlx::query(
-                    r#"
-                    INSERT INTO damage(
-                    build_id,
-                    name,
-                    normal,
-                    crit,
-                    avg
-                    ) VALUES (
-                    $1,
-                    $2,
-                    $3,
-                    $4,
-                    $5
-                    )
-                "#,
-                )
-                .bind(row_id)
-                .bind(v.name.clone())
-                .bind(v.normal)
-                .bind(v.crit)
-                .bind(v.avg)
-                .execute(&mut *tx)
-                .await?;
-            }
+        "#,
+        )
+        .bind(row_id)
+        .bind(&v.name)
+        .bind(v.normal)
+        .bind(v.crit)
+        .bind(v.avg)
+        .execute(&mut *conn)
+        .await?;
+    }
 
-            tx.commit().await
-        },
-        &config,
-    )
-    .await;
+    for (name, cost) in &build.status.spell_costs {
+        sqlx::query(
+            r#"
+            INSERT INTO spell_cost(
+            build_id,
+            name,
+            cost
+            ) VALUES (
+            $1,
+            $2,
+            $3
+            )
+        "#,
+        )
+        .bind(row_id)
+        .bind(name)
+        .bind(cost)
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    let items = build.items.iter().chain([&build.weapon]);
+    for (slot, name) in output::SLOTS.iter().zip(items) {
+        sqlx::query("INSERT INTO build_item(build_id, slot, item_id) VALUES ($1, $2, $3)")
+            .bind(row_id)
+            .bind(slot)
+            .bind(run.item_id(name)?)
+            .execute(&mut *conn)
+            .await?;
+    }
+    Ok(())
+}
+
+/// record that the run found the build, a build found again by a later run keeps its first
+/// `run_id` but gets a row for every run
+async fn insert_build_run(
+    conn: &mut SqliteConnection,
+    build_id: i64,
+    run: &Run,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("INSERT OR IGNORE INTO build_run(build_id, run_id) VALUES ($1, $2)")
+        .bind(build_id)
+        .bind(run.id)
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
+}
+
+/// the finished segments of the run, see `output::Writer::save_checkpoint`
+pub async fn load_checkpoint(pool: &sqlx::SqlitePool, run: &str) -> HashSet<usize> {
+    let segments: Vec<(i64,)> = sqlx::query_as("SELECT segment FROM checkpoint WHERE run = $1")
+        .bind(run)
+        .fetch_all(pool)
+        .await
+        .unwrap();
+    segments
+        .into_iter()
+        .map(|(segment,)| segment as usize)
+        .collect()
+}
+
+async fn insert_checkpoint(
+    conn: &mut SqliteConnection,
+    run: &str,
+    segments: &[usize],
+) -> Result<(), sqlx::Error> {
+    for segment in segments {
+        sqlx::query("INSERT OR IGNORE INTO checkpoint(run, segment) VALUES ($1, $2)")
+            .bind(run)
+            .bind(*segment as i64)
+            .execute(&mut *conn)
+            .await?;
+    }
+    Ok(())
+}
+
+/// forget the segments of a finished run, so the next run with the same config starts over
+pub async fn clear_checkpoint(pool: &sqlx::SqlitePool, run: &str) {
+    sqlx::query("DELETE FROM checkpoint WHERE run = $1")
+        .bind(run)
+        .execute(pool)
+        .await
+        .unwrap();
 }
 
 async fn retry_with_backoff<F, FUT>(func: F, config: &Config)
@@ -253,7 +412,6 @@ where
     FUT: Future<Output = Result<(), sqlx::Error>>,
 {
     let mut retry_count = 0;
-    let mut rng = rand::thread_rng();
 
     loop {
         match func().await {
@@ -265,8 +423,10 @@ where
                 }
 
                 retry_count += 1;
-                // Maximum retries defined in config
-                if retry_count > config.hppeng.db_retry_count {
+                // Maximum retries defined in config, a value that can not be encoded fails again
+                if retry_count > config.hppeng.db_retry_count
+                    || matches!(err, sqlx::Error::Encode(_))
+                {
                     if config.hppeng.log_db_errors {
                         println!(
                             "max retries exceeded. giving up on writing to db.\nerror: {}",
@@ -278,7 +438,7 @@ where
 
                 // Exponential backoff with jitter
                 let base_wait_ms = 2u64.pow(retry_count.into()) * 100;
-                let jitter = rng.gen_range(0..100);
+                let jitter = rand::thread_rng().gen_range(0..100);
                 let wait_duration = Duration::from_millis(base_wait_ms + jitter);
 
                 tokio::time::sleep(wait_duration).await;
@@ -297,3 +457,96 @@ fn log_db_error<E: error::Error>(err: &E) {
         let _ = writeln!(file, "{}", err);
     }
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    pub(crate) async fn memory_pool() -> Pool<Sqlite> {
+        let pool = SqlitePoolOptions::new()
+            // every connection has its own in-memory database
+            .max_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        Migrator::new(std::path::Path::new("migrations"))
+            .await
+            .unwrap()
+            .run(&pool)
+            .await
+            .unwrap();
+        pool
+    }
+    pub(crate) fn config() -> Config {
+        toml::from_str(&std::fs::read_to_string("config/config.toml").unwrap()).unwrap()
+    }
+    /// a run whose items are `Item` and `Weapon`
+    pub(crate) async fn save_run(pool: &sqlx::SqlitePool, key: &str) -> Run {
+        let items = [("Item", "ring"), ("Weapon", "wand")]
+            .map(|(name, r#type)| (name.to_string(), r#type.to_string()));
+        super::save_run(pool, key, "", "version", &items).await
+    }
+    /// a build of the items of `save_run` with a spell
+    pub(crate) fn build(url: &str) -> Build {
+        Build {
+            url: url.to_string(),
+            items: std::array::from_fn(|_| "Item".to_string()),
+            ..output::tests::build(&["Bash"])
+        }
+    }
+
+    #[tokio::test]
+    async fn build_run_works() {
+        let pool = memory_pool().await;
+        let config = config();
+        let first = save_run(&pool, "first").await;
+        let second = save_run(&pool, "second").await;
+        let rows = |urls: &[&str]| {
+            urls.iter()
+                .map(|url| Row::Build(Box::new(build(url))))
+                .collect::<Vec<_>>()
+        };
+        write(&pool, &config, &first, &rows(&["a", "b"])).await;
+        write(&pool, &config, &second, &rows(&["b", "c"])).await;
+
+        let runs: Vec<(String, i64)> = sqlx::query_as(
+            "SELECT build.url, build_run.run_id FROM build_run
+            JOIN build ON build.row_id = build_run.build_id
+            ORDER BY build.url, build_run.run_id",
+        )
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        let (first, second) = (first.id, second.id);
+        assert_eq!(
+            runs,
+            [
+                ("a".to_string(), first),
+                ("b".to_string(), first),
+                ("b".to_string(), second),
+                ("c".to_string(), second)
+            ]
+        );
+        // the build keeps the run that found it first
+        let (run_id,): (i64,) = sqlx::query_as("SELECT run_id FROM build WHERE url = 'b'")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(run_id, first);
+    }
+
+    #[tokio::test]
+    async fn unknown_item_is_an_error() {
+        let pool = memory_pool().await;
+        let run = save_run(&pool, "run").await;
+        let mut build = build("a");
+        build.items[0] = "Unknown".to_string();
+        let mut conn = pool.acquire().await.unwrap();
+        let err = insert_build(&mut conn, &run, &build).await.unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("item Unknown is not saved with the run"));
+    }
+}
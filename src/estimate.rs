@@ -0,0 +1,130 @@
+use std::time::{Duration, Instant};
+
+use rayon::prelude::*;
+use wynn_build_tools::util::map_to_index_space;
+
+use crate::Stage;
+
+/// how the sampled combinations are rejected, see `sample`
+pub struct Estimate {
+    samples: usize,
+    /// rejected samples by stage, in the order of `Stage::ALL`
+    rejected: [usize; Stage::ALL.len()],
+    elapsed: Duration,
+}
+
+/// evaluate `samples` random indexes of the index space `dims`, `evaluate` returns the stage
+/// rejecting the combination of the indexes
+pub fn sample<const LEN: usize>(
+    samples: usize,
+    dims: &[usize; LEN],
+    rng: fastrand::Rng,
+    evaluate: impl Fn([usize; LEN]) -> Result<(), Stage> + Sync,
+) -> Estimate {
+    let total = dims.iter().product::<usize>();
+    let indexes: Vec<usize> = if total == 0 {
+        Vec::new()
+    } else {
+        (0..samples).map(|_| rng.usize(0..total)).collect()
+    };
+
+    let start = Instant::now();
+    let stages: Vec<Stage> = indexes
+        .par_iter()
+        .filter_map(|index| evaluate(map_to_index_space(dims, *index)).err())
+        .collect();
+    let elapsed = start.elapsed();
+
+    let mut rejected = [0; Stage::ALL.len()];
+    for stage in stages {
+        rejected[stage as usize] += 1;
+    }
+    Estimate {
+        samples: indexes.len(),
+        rejected,
+        elapsed,
+    }
+}
+
+impl Estimate {
+    /// the pass rate of each stage, the projected builds and runtime of `combinations`, at most
+    /// `top_k` builds are stored with an objective
+    pub fn print(&self, combinations: usize, top_k: Option<usize>) {
+        if self.samples == 0 {
+            println!("no combination to sample");
+            return;
+        }
+        println!("samples: {}", self.samples);
+        println!(
+            "{:<20}{:>10}{:>10}{:>10}",
+            "stage", "reached", "passed", "pass rate"
+        );
+        let mut reached = self.samples;
+        for (stage, rejected) in Stage::ALL.iter().zip(self.rejected) {
+            let passed = reached - rejected;
+            println!(
+                "{:<20}{:>10}{:>10}{:>9.2}%",
+                stage.name(),
+                reached,
+                passed,
+                rate(passed, reached) * 100.0
+            );
+            reached = passed;
+        }
+
+        let pass_rate = rate(reached, self.samples);
+        let builds = (pass_rate * combinations as f64).round() as usize;
+        println!("pass rate: {:.4}%", pass_rate * 100.0);
+        match top_k {
+            Some(top_k) => println!(
+                "projected builds: {}, {} stored by the objective",
+                builds,
+                builds.min(top_k)
+            ),
+            None => println!("projected builds: {}", builds),
+        }
+        let speed = self.samples as f64 / self.elapsed.as_secs_f64();
+        println!("speed: {:.0} builds per second", speed);
+        println!(
+            "projected time: {:.2}h",
+            combinations as f64 / speed / 3600.0
+        );
+    }
+}
+
+/// 1 if nothing is reached, a stage that is never reached rejects nothing
+fn rate(passed: usize, reached: usize) -> f64 {
+    if reached == 0 {
+        1.0
+    } else {
+        passed as f64 / reached as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sample_works() {
+        let estimate = sample(
+            100,
+            &[2, 5],
+            fastrand::Rng::with_seed(1),
+            |index| match index {
+                [0, _] => Err(Stage::Hp),
+                [_, 0] => Err(Stage::Def),
+                _ => Ok(()),
+            },
+        );
+        assert_eq!(estimate.samples, 100);
+        assert!(estimate.rejected[Stage::Hp as usize] > estimate.rejected[Stage::Def as usize]);
+        assert_eq!(
+            estimate.rejected.iter().sum::<usize>(),
+            estimate.rejected[Stage::Hp as usize] + estimate.rejected[Stage::Def as usize]
+        );
+
+        let estimate = sample(100, &[2, 0], fastrand::Rng::new(), |_| Ok(()));
+        assert_eq!(estimate.samples, 0);
+    }
+}
@@ -1,10 +1,15 @@
 use serde::{Deserialize, Deserializer, Serialize};
-use std::collections::HashMap;
+use std::{collections::HashMap, simd::i16x8, str::FromStr};
 
 use super::*;
 
 pub type AbilitiesMap = HashMap<Class, Vec<ATreeNodeData>>;
 
+/// id of the default melee ability, "base_abil": 999
+pub const MELEE_ABILITY_ID: i32 = 999;
+/// id of the melee spell, "base_spell": 0
+pub const MELEE_SPELL_ID: i32 = 0;
+
 /// https://github.com/hppeng-wynn/hppeng-wynn.github.io/blob/50ed4620bd0a4e3af7dd5646971c6dcd78e8b783/js/builder/atree.js#L36
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(tag = "type")]
@@ -27,16 +32,56 @@ pub struct ATreeNodeData {
     pub parents: Vec<i32>,
     pub base_abil: Option<i32>,
     pub cost: i32,
+    /// properties of an ability merged into its base ability are added to the base ability
+    #[serde(default)]
+    pub properties: HashMap<String, NumberOrString>,
     pub effects: Vec<Effect>,
 }
 
 impl ATreeNodeData {
+    /// the default melee ability of the weapon, abilities with base_abil 999 are merged into it
+    /// https://github.com/hppeng-wynn/hppeng-wynn.github.io/blob/HEAD/js/builder/atree.js
+    pub fn melee(weapon_type: &WeaponTypes) -> Self {
+        let (name, parts) = match weapon_type {
+            WeaponTypes::Wand => ("Wand Melee", vec![("Melee", 100)]),
+            WeaponTypes::Spear => ("Spear Melee", vec![("Melee", 100)]),
+            WeaponTypes::Bow => ("Bow Shot", vec![("Single Shot", 100)]),
+            WeaponTypes::Dagger => ("Dagger Melee", vec![("Melee", 100)]),
+            WeaponTypes::Relik => ("Relik Melee", vec![("Single Beam", 35)]),
+        };
+        ATreeNodeData {
+            display_name: name.to_string(),
+            id: MELEE_ABILITY_ID,
+            parents: Vec::new(),
+            base_abil: None,
+            cost: 0,
+            properties: Default::default(),
+            effects: vec![Effect::ReplaceSpell(ReplaceSpell {
+                name: name.to_string(),
+                cost: None,
+                base_spell: MELEE_SPELL_ID,
+                spell_type: None,
+                scaling: Some("melee".to_string()),
+                use_atkspd: Some(false),
+                parts: parts
+                    .into_iter()
+                    .map(|(name, multiplier)| Part::Damage {
+                        name: name.to_string(),
+                        multipliers: [multiplier, 0, 0, 0, 0, 0],
+                    })
+                    .collect(),
+            })],
+        }
+    }
     pub fn join_spell(&self) -> HashMap<i32, Spell> {
         let mut spell: HashMap<i32, Spell> = HashMap::new();
         for effect in &self.effects {
             match effect {
                 Effect::ReplaceSpell(replace_spell) => {
-                    if spell.contains_key(&replace_spell.base_spell) {
+                    // only the melee ability merges abilities that replace its spell,
+                    // e.g. "Relik Proficiency 1", the later one is used
+                    if spell.contains_key(&replace_spell.base_spell) && self.id != MELEE_ABILITY_ID
+                    {
                         panic!(
                             "Duplicate spell detected: {:?}, replace_spell: {:?}, spell name: {}",
                             spell.get(&replace_spell.base_spell),
@@ -44,45 +89,31 @@ impl ATreeNodeData {
                             self.display_name
                         );
                     }
-                    spell.insert(
+                    let mut new_spell = Spell::new(
+                        replace_spell.name.clone(),
                         replace_spell.base_spell,
-                        Spell::new(
-                            replace_spell.name.clone(),
-                            replace_spell.base_spell,
-                            replace_spell.cost.unwrap_or(0),
-                            replace_spell
-                                .parts
-                                .iter()
-                                .filter_map(|part| match part {
-                                    Part::Damage { name, multipliers } => Some(DamagePart::new(
-                                        name.clone(),
-                                        DamagesConvert::from_slice_i32(multipliers),
-                                    )),
-                                    Part::Total { name: _, hits: _ } => None,
-                                    Part::Heal { name: _, power: _ } => None,
-                                })
-                                .collect(),
-                        ),
+                        replace_spell.cost.unwrap_or(0),
+                        Vec::new(),
                     );
+                    for part in &replace_spell.parts {
+                        new_spell.merge_part(Behavior::Merge, part);
+                    }
+                    new_spell.melee = replace_spell.scaling.as_deref() == Some("melee");
+                    new_spell.use_atk_spd = replace_spell.use_atkspd.unwrap_or(true);
+                    spell.insert(replace_spell.base_spell, new_spell);
                 }
                 _ => continue,
             }
         }
         spell
     }
-    /// return Map<id,(cost,parts)>
-    pub fn join_spell_property(&self) -> HashMap<i32, (i32, Vec<DamagePart>)> {
-        let mut part_add: HashMap<i32, (i32, Vec<DamagePart>)> = HashMap::new();
+    /// return Map<id,(cost,parts)>, overwrite parts (only used by major ids) replace the target part
+    /// https://github.com/hppeng-wynn/hppeng-wynn.github.io/blob/a30a0aa1a8b9f857ae66d31e67bdc5df0a4d0cfc/js/builder/atree.js#L941
+    pub fn join_spell_property(&self) -> HashMap<i32, (i32, Vec<(Behavior, Part)>)> {
+        let mut part_add: HashMap<i32, (i32, Vec<(Behavior, Part)>)> = HashMap::new();
 
         for effect in &self.effects {
             if let Effect::AddSpellProp(add_spell_prop) = effect {
-                match add_spell_prop.behavior {
-                    // https://github.com/hppeng-wynn/hppeng-wynn.github.io/blob/a30a0aa1a8b9f857ae66d31e67bdc5df0a4d0cfc/js/builder/atree.js#L941
-                    Behavior::Merge => (),
-                    Behavior::Modify => (),
-                    Behavior::Overwrite => panic!("add_spell_prop should not have overwrite"),
-                }
-
                 let (cost, parts) = part_add
                     .entry(add_spell_prop.base_spell)
                     .or_insert_with(|| (0, Vec::new()));
@@ -90,245 +121,297 @@ impl ATreeNodeData {
                 // https://github.com/hppeng-wynn/hppeng-wynn.github.io/blob/a30a0aa1a8b9f857ae66d31e67bdc5df0a4d0cfc/js/builder/atree.js#L927
                 *cost += add_spell_prop.cost.unwrap_or(0);
 
-                if let (Some(name), Some(multipliers)) =
-                    (&add_spell_prop.target_part, add_spell_prop.multipliers)
-                {
-                    parts.push(DamagePart::new(
-                        name.clone(),
-                        DamagesConvert::from_slice_i32(&multipliers),
-                    ));
+                if let Some(part) = add_spell_prop.part() {
+                    parts.push((add_spell_prop.behavior, part));
                 }
             }
         }
 
         part_add
     }
-    /// return (common_stat, dam_raw, dam_pct, dam_add)
-    pub fn join_stat(&self) -> (CommonStat, i32, Dam, Damages) {
-        let mut common_stat: CommonStat = Default::default();
-        let mut dam_raw: i32 = 0;
-        let mut dam_pct: Dam = Default::default();
-        let mut dam_add: Damages = Default::default();
+    /// return the element conversions of spell parts, applied after all parts are merged
+    pub fn join_spell_conv(&self) -> Vec<&ConvertSpellConv> {
+        self.effects
+            .iter()
+            .filter_map(|effect| match effect {
+                Effect::ConvertSpellConv(convert) => Some(convert),
+                _ => None,
+            })
+            .collect()
+    }
+    /// merge the fixed stats of the ability, toggled effects are merged only if they are on,
+    /// `StatScaling` without slider depends on the build and is kept in `ATreeStat::scalings`
+    pub fn join_stat(&self, toggles: &Toggles) -> ATreeStat {
+        let mut stat = ATreeStat::default();
         for effect in &self.effects {
             match effect {
-                Effect::RawStat(raw_stat) => match raw_stat.toggle {
-                    // optional effects are not currently supported
-                    Some(_) => continue,
-                    None => match raw_stat.behavior {
+                Effect::RawStat(raw_stat) => {
+                    if let Some(toggle) = &raw_stat.toggle {
+                        if !is_toggle_on(toggles, toggle) {
+                            continue;
+                        }
+                    }
+                    match raw_stat.behavior {
                         // currently there(atree.json) is no "modify" type in raw_stat
                         Behavior::Modify => continue,
                         Behavior::Overwrite => continue,
                         Behavior::Merge => {
-                            // TODO: calculate bonuses during initialization
                             for bonus in &raw_stat.bonuses {
                                 match bonus {
-                                    // Prop not relevant to damage calculation
-                                    StatBonus::Prop {
-                                        abil: _,
-                                        name: _,
-                                        value: _,
-                                    } => continue,
-                                    StatBonus::Stat { name, value } => match name {
-                                        StatName::DamMulti(_) => continue,
-                                        StatName::DefMulti(_) => continue,
-                                        StatName::HealMulti(_) => continue,
-                                        // TODO: support jump height
-                                        StatName::JH => continue,
-                                        StatName::SPD => {
-                                            common_stat +=
-                                                &CommonStat::new(0, 0, 0, 0, 0, *value as i16, 0, 0)
-                                        }
-                                        StatName::DamRaw => dam_raw += value,
-                                        // this is only used by Shaman
-                                        // handle it later
-                                        StatName::SpPct1Final => continue,
-                                        StatName::SpPct2Final => continue,
-                                        StatName::SpPct3Final => continue,
-                                        StatName::EDamPct => {
-                                            dam_pct += &Dam::new(0, *value as i16, 0, 0, 0, 0)
-                                        }
-                                        StatName::TDamPct => {
-                                            dam_pct += &Dam::new(0, 0, *value as i16, 0, 0, 0)
-                                        }
-                                        StatName::WDamPct => {
-                                            dam_pct += &Dam::new(0, 0, 0, *value as i16, 0, 0)
-                                        }
-                                        StatName::FDamPct => {
-                                            dam_pct += &Dam::new(0, 0, 0, 0, *value as i16, 0)
-                                        }
-                                        StatName::ADamPct => {
-                                            dam_pct += &Dam::new(0, 0, 0, 0, 0, *value as i16)
-                                        }
-                                        StatName::EDamAddMax => {
-                                            dam_add += &Damages::new(
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                                *value as f64,
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                            )
-                                        }
-                                        StatName::TDamAddMax => {
-                                            dam_add += &Damages::new(
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                                *value as f64,
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                            )
-                                        }
-                                        StatName::WDamAddMax => {
-                                            dam_add += &Damages::new(
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                                *value as f64,
-                                                0.0,
-                                                0.0,
-                                            )
-                                        }
-                                        StatName::FDamAddMax => {
-                                            dam_add += &Damages::new(
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                                *value as f64,
-                                                0.0,
-                                            )
-                                        }
-                                        StatName::ADamAddMax => {
-                                            dam_add += &Damages::new(
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                                *value as f64,
-                                            )
-                                        }
-                                        StatName::EDamAddMin => {
-                                            dam_add += &Damages::new(
-                                                0.0,
-                                                *value as f64,
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                            )
-                                        }
-                                        StatName::TDamAddMin => {
-                                            dam_add += &Damages::new(
-                                                0.0,
-                                                0.0,
-                                                *value as f64,
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                            )
-                                        }
-                                        StatName::WDamAddMin => {
-                                            dam_add += &Damages::new(
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                                *value as f64,
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                            )
-                                        }
-                                        StatName::FDamAddMin => {
-                                            dam_add += &Damages::new(
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                                *value as f64,
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                            )
-                                        }
-                                        StatName::ADamAddMin => {
-                                            dam_add += &Damages::new(
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                                *value as f64,
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                                0.0,
-                                            )
-                                        }
-                                    },
+                                    StatBonus::Prop { abil, name, value } => {
+                                        *stat
+                                            .props
+                                            .entry(format!("{}.{}", abil, name))
+                                            .or_default() += *value as f64
+                                    }
+                                    StatBonus::Stat { name, value } => {
+                                        stat.merge_stat(name, *value as f64)
+                                    }
                                 }
                             }
                         }
-                    },
-                },
+                    }
+                }
+                Effect::StatScaling(stat_scaling) => {
+                    if stat_scaling.is_slider() {
+                        let total = stat_scaling.eval(|_| 0.0, toggles);
+                        stat.merge_scaling(stat_scaling, total);
+                    } else {
+                        stat.scalings.push(stat_scaling.clone());
+                    }
+                }
                 _ => continue,
             }
         }
-        (common_stat, dam_raw, dam_pct, dam_add)
+        stat
+    }
+}
+
+/// toggle name (or slider name) to its value, a toggle is on when the value is not 0
+pub type Toggles = HashMap<String, f64>;
+
+pub fn is_toggle_on(toggles: &Toggles, name: &str) -> bool {
+    toggles.get(name).is_some_and(|v| *v != 0.0)
+}
+
+/// stats given by the atree
+#[derive(Debug, Clone, Default)]
+pub struct ATreeStat {
+    pub common_stat: CommonStat,
+    pub hp_bonus: i32,
+    /// damRaw
+    pub dam_raw: i32,
+    /// nDamRaw eDamRaw ...
+    pub dam_raw_s: [i32; 6],
+    pub dam_pct: Dam,
+    pub dam_add: Damages,
+    /// mdPct mdRaw
+    pub melee_stat: MeleeStat,
+    /// "damMult.<name>", key is "<name>" or "<name>:<spell id>.<part name>", value is percentage
+    pub dam_mult: HashMap<String, f64>,
+    /// "defMult.<name>", percentage of damage reduction
+    pub def_mult: HashMap<String, f64>,
+    /// "healMult.<name>"
+    pub heal_mult: HashMap<String, f64>,
+    /// "nConvBase:<spell id>.<part name>", (element index, "<spell id>.<part name>", percentage)
+    pub conv_base: Vec<(usize, String, f64)>,
+    /// "<ability id>.<property name>", value added to the ability property
+    pub props: HashMap<String, f64>,
+    /// "spPct<spell id>Final", percentage applied after the other spell cost modifiers
+    pub sp_pct_final: [f64; 4],
+    /// effects depend on the build stats, see `ATreeStat::scale`
+    pub scalings: Vec<StatScaling>,
+}
+
+impl ATreeStat {
+    /// https://github.com/hppeng-wynn/hppeng-wynn.github.io/blob/50ed4620bd0a4e3af7dd5646971c6dcd78e8b783/js/builder/atree.js#L1011
+    pub fn merge_stat(&mut self, name: &StatName, value: f64) {
+        let common_stat = |index: usize| {
+            let mut inner = [0; 8];
+            inner[index] = value as i16;
+            CommonStat {
+                inner: i16x8::from(inner),
+            }
+        };
+        let dam_add = |index: usize, is_max: bool| {
+            let mut ranges: [Range; 6] = Default::default();
+            if is_max {
+                ranges[index].max = value;
+            } else {
+                ranges[index].min = value;
+            }
+            Damages::from_slice(ranges)
+        };
+        let mut dam_pct = [0; 6];
+        match name {
+            StatName::DamMulti(key) => *self.dam_mult.entry(key.clone()).or_default() += value,
+            StatName::DefMulti(key) => *self.def_mult.entry(key.clone()).or_default() += value,
+            StatName::HealMulti(key) => *self.heal_mult.entry(key.clone()).or_default() += value,
+            StatName::ConvBase(index, target) => {
+                self.conv_base.push((*index, target.clone(), value))
+            }
+            // TODO: support jump height
+            StatName::JH => (),
+            StatName::SpPct1Final => self.sp_pct_final[0] += value,
+            StatName::SpPct2Final => self.sp_pct_final[1] += value,
+            StatName::SpPct3Final => self.sp_pct_final[2] += value,
+            // not calculated yet, only used as scaling input
+            StatName::Poison => (),
+            StatName::Ref => (),
+            StatName::ESteal => (),
+            StatName::Str => (),
+            StatName::Dex => (),
+            StatName::Int => (),
+            StatName::Def => (),
+            StatName::Agi => (),
+            StatName::HprRaw => self.common_stat += &common_stat(0),
+            StatName::HprPct => self.common_stat += &common_stat(1),
+            StatName::MR => self.common_stat += &common_stat(2),
+            StatName::LS => self.common_stat += &common_stat(3),
+            StatName::MS => self.common_stat += &common_stat(4),
+            StatName::SPD => self.common_stat += &common_stat(5),
+            StatName::SdRaw => self.common_stat += &common_stat(6),
+            StatName::SdPct => self.common_stat += &common_stat(7),
+            StatName::HpBonus => self.hp_bonus += value as i32,
+            StatName::MdPct => self.melee_stat.md_pct += value as i16,
+            StatName::MdRaw => self.melee_stat.md_raw += value as i16,
+            StatName::DamRaw => self.dam_raw += value as i32,
+            StatName::NDamRaw => self.dam_raw_s[0] += value as i32,
+            StatName::EDamRaw => self.dam_raw_s[1] += value as i32,
+            StatName::TDamRaw => self.dam_raw_s[2] += value as i32,
+            StatName::WDamRaw => self.dam_raw_s[3] += value as i32,
+            StatName::FDamRaw => self.dam_raw_s[4] += value as i32,
+            StatName::ADamRaw => self.dam_raw_s[5] += value as i32,
+            StatName::EDamPct => dam_pct[1] = value as i16,
+            StatName::TDamPct => dam_pct[2] = value as i16,
+            StatName::WDamPct => dam_pct[3] = value as i16,
+            StatName::FDamPct => dam_pct[4] = value as i16,
+            StatName::ADamPct => dam_pct[5] = value as i16,
+            StatName::EDamAddMax => self.dam_add += &dam_add(1, true),
+            StatName::TDamAddMax => self.dam_add += &dam_add(2, true),
+            StatName::WDamAddMax => self.dam_add += &dam_add(3, true),
+            StatName::FDamAddMax => self.dam_add += &dam_add(4, true),
+            StatName::ADamAddMax => self.dam_add += &dam_add(5, true),
+            StatName::EDamAddMin => self.dam_add += &dam_add(1, false),
+            StatName::TDamAddMin => self.dam_add += &dam_add(2, false),
+            StatName::WDamAddMin => self.dam_add += &dam_add(3, false),
+            StatName::FDamAddMin => self.dam_add += &dam_add(4, false),
+            StatName::ADamAddMin => self.dam_add += &dam_add(5, false),
+        }
+        if dam_pct != [0; 6] {
+            let [n, e, t, w, f, a] = dam_pct;
+            self.dam_pct += &Dam::new(n, e, t, w, f, a);
+        }
+    }
+    /// merge the result of a `StatScaling` to its outputs
+    pub fn merge_scaling(&mut self, stat_scaling: &StatScaling, total: f64) {
+        for output in stat_scaling.outputs() {
+            match output {
+                ScalingTarget::Stat { name } => self.merge_stat(name, total),
+                ScalingTarget::Prop { abil, name } => {
+                    *self.props.entry(format!("{}.{}", abil, name)).or_default() += total
+                }
+            }
+        }
+    }
+    /// evaluate the scaling effects with the stats of a build, return the stats they give
+    pub fn scale<F>(&self, stat: F) -> ATreeStat
+    where
+        F: Fn(&StatName) -> f64,
+    {
+        let mut scaled = ATreeStat::default();
+        for stat_scaling in &self.scalings {
+            let total = stat_scaling.eval(&stat, &Toggles::new());
+            scaled.merge_scaling(stat_scaling, total);
+        }
+        scaled
+    }
+    /// the max stats the scaling effects can give, `None` if a scaling effect of a stat has no
+    /// numeric `max`, the stat is not bounded; a negative `max` only lowers the stat
+    pub fn scaling_bound(&self) -> Option<ATreeStat> {
+        let mut bound = ATreeStat::default();
+        for stat_scaling in &self.scalings {
+            match stat_scaling.max {
+                Some(NumberOrString::Number(max)) if max > 0.0 => {
+                    bound.merge_scaling(stat_scaling, max)
+                }
+                Some(NumberOrString::Number(_)) => (),
+                // the props are not stats of the build
+                _ if stat_scaling
+                    .outputs()
+                    .iter()
+                    .all(|output| matches!(output, ScalingTarget::Prop { .. })) => {}
+                _ => return None,
+            }
+        }
+        Some(bound)
+    }
+    /// product of all damage multipliers that apply to the spell part
+    pub fn dam_mult(&self, spell_id: i32, part_name: &str) -> f64 {
+        mult_product(&self.dam_mult, spell_id, part_name)
+    }
+    /// product of all heal multipliers that apply to the spell part
+    pub fn heal_mult(&self, spell_id: i32, part_name: &str) -> f64 {
+        mult_product(&self.heal_mult, spell_id, part_name)
+    }
+    /// "spPct<spell id>Final" of the spell, 0 if the spell is not one of the 4 base spells
+    pub fn sp_pct_final(&self, spell_id: i32) -> f64 {
+        match spell_id {
+            1..=4 => self.sp_pct_final[spell_id as usize - 1],
+            _ => 0.0,
+        }
+    }
+    /// product of all damage reduction multipliers
+    pub fn def_mult(&self) -> f64 {
+        self.def_mult
+            .values()
+            .map(|value| 1.0 - value / 100.0)
+            .product()
+    }
+}
+
+/// keys are "<name>" or "<name>:<spell id>.<part name>", the latter only applies to the part
+fn mult_product(mults: &HashMap<String, f64>, spell_id: i32, part_name: &str) -> f64 {
+    mults
+        .iter()
+        .filter(|(key, _)| match key.split_once(':') {
+            Some((_, target)) => target
+                .split_once('.')
+                .is_some_and(|(id, name)| id.parse() == Ok(spell_id) && name == part_name),
+            None => true,
+        })
+        .map(|(_, value)| 1.0 + value / 100.0)
+        .product()
+}
+
+impl std::ops::AddAssign<&ATreeStat> for ATreeStat {
+    fn add_assign(&mut self, rhs: &ATreeStat) {
+        self.common_stat += &rhs.common_stat;
+        self.hp_bonus += rhs.hp_bonus;
+        self.dam_raw += rhs.dam_raw;
+        for (a, b) in self.dam_raw_s.iter_mut().zip(rhs.dam_raw_s) {
+            *a += b;
+        }
+        self.dam_pct += &rhs.dam_pct;
+        self.dam_add += &rhs.dam_add;
+        self.melee_stat += &rhs.melee_stat;
+        for (key, value) in &rhs.dam_mult {
+            *self.dam_mult.entry(key.clone()).or_default() += value;
+        }
+        for (key, value) in &rhs.def_mult {
+            *self.def_mult.entry(key.clone()).or_default() += value;
+        }
+        for (key, value) in &rhs.heal_mult {
+            *self.heal_mult.entry(key.clone()).or_default() += value;
+        }
+        self.conv_base.extend(rhs.conv_base.iter().cloned());
+        for (key, value) in &rhs.props {
+            *self.props.entry(key.clone()).or_default() += value;
+        }
+        for (a, b) in self.sp_pct_final.iter_mut().zip(rhs.sp_pct_final) {
+            *a += b;
+        }
+        self.scalings.extend(rhs.scalings.iter().cloned());
     }
 }
 
@@ -378,14 +461,77 @@ pub struct AddSpellProp {
     display: Option<String>,
     hide: Option<bool>,
 }
+impl AddSpellProp {
+    /// the part added to `target_part`, None if the effect only changes the cost
+    pub fn part(&self) -> Option<Part> {
+        let name = self.target_part.clone()?;
+        if let Some(multipliers) = self.multipliers {
+            Some(Part::Damage { name, multipliers })
+        } else if let Some(power) = self.power {
+            Some(Part::Heal {
+                name,
+                power: power as f64,
+            })
+        } else {
+            self.hits.clone().map(|hits| Part::Total { name, hits })
+        }
+    }
+}
 
 /// https://github.com/hppeng-wynn/hppeng-wynn.github.io/blob/50ed4620bd0a4e3af7dd5646971c6dcd78e8b783/js/builder/atree.js#L63
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ConvertSpellConv {
     pub base_spell: i32,
     pub target_part: String,
+    /// an element of `ELEMENTS`, an unknown element fails the loading of the atree
+    #[serde(deserialize_with = "deserialize_element")]
     pub conversion: String,
 }
+impl ConvertSpellConv {
+    const ELEMENTS: [&'static str; 6] = ["Neutral", "Earth", "Thunder", "Water", "Fire", "Air"];
+    /// index of `conversion` in n e t w f a
+    pub fn element(&self) -> Option<usize> {
+        element_index(&self.conversion)
+    }
+    /// move all elemental conversion of the target parts ("all" targets every part) to `conversion`,
+    /// neutral conversion is kept
+    /// https://github.com/hppeng-wynn/hppeng-wynn.github.io/blob/HEAD/js/builder/atree.js
+    pub fn apply(&self, spell: &mut Spell) {
+        let Some(element) = self.element() else {
+            eprintln!("unknown spell conversion element: {}", self.conversion);
+            return;
+        };
+        spell
+            .parts
+            .iter_mut()
+            .filter(|part| self.target_part == "all" || part.name == self.target_part)
+            .for_each(|part| {
+                let rainbow = part.dam_convert.only_rainbow().total();
+                let mut dam_convert = [part.dam_convert.neutral(), 0.0, 0.0, 0.0, 0.0, 0.0];
+                dam_convert[element] += rainbow;
+                part.dam_convert = DamagesConvert::from_slice(dam_convert);
+            });
+    }
+}
+
+fn element_index(name: &str) -> Option<usize> {
+    ConvertSpellConv::ELEMENTS
+        .iter()
+        .position(|v| v.eq_ignore_ascii_case(name))
+}
+fn deserialize_element<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let name: String = Deserialize::deserialize(deserializer)?;
+    match element_index(&name) {
+        Some(_) => Ok(name),
+        None => Err(serde::de::Error::custom(format!(
+            "Unknown spell conversion element: {}",
+            name
+        ))),
+    }
+}
 
 /// https://github.com/hppeng-wynn/hppeng-wynn.github.io/blob/50ed4620bd0a4e3af7dd5646971c6dcd78e8b783/js/builder/atree.js#L70
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -397,7 +543,7 @@ pub struct RawStat {
     pub bonuses: Vec<StatBonus>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
 #[serde(rename_all = "snake_case")]
 pub enum Behavior {
     Merge,
@@ -428,11 +574,13 @@ pub enum StatBonus {
     },
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub enum StatName {
     DamMulti(String),
     DefMulti(String),
     HealMulti(String),
+    /// "nConvBase:3.Winded Damage", (element index, "<spell id>.<part name>")
+    ConvBase(usize, String),
 
     JH,
     SPD,
@@ -441,6 +589,37 @@ pub enum StatName {
     SpPct2Final,
     SpPct3Final,
 
+    // CommonStat
+    HprRaw,
+    HprPct,
+    MR,
+    LS,
+    MS,
+    SdRaw,
+    SdPct,
+
+    HpBonus,
+    Poison,
+    Ref,
+    ESteal,
+    MdPct,
+    MdRaw,
+
+    // skill points
+    Str,
+    Dex,
+    Int,
+    Def,
+    Agi,
+
+    // DamRaw
+    NDamRaw,
+    EDamRaw,
+    TDamRaw,
+    WDamRaw,
+    FDamRaw,
+    ADamRaw,
+
     // DamPct
     EDamPct,
     TDamPct,
@@ -462,66 +641,196 @@ pub enum StatName {
     FDamAddMin,
     ADamAddMin,
 }
+impl FromStr for StatName {
+    type Err = String;
+
+    fn from_str(type_str: &str) -> Result<Self, Self::Err> {
+        if let Some((element, target)) = type_str.split_once("ConvBase:") {
+            return match ['n', 'e', 't', 'w', 'f', 'a']
+                .iter()
+                .position(|v| element.len() == 1 && element.starts_with(*v))
+            {
+                Some(index) => Ok(StatName::ConvBase(index, target.to_string())),
+                None => Err(format!("Unknown type: {}", type_str)),
+            };
+        }
+        if type_str.contains('.') {
+            let parts: Vec<&str> = type_str.split('.').collect();
+            let sub = parts[1..].join(".").to_string();
+            return match parts[0] {
+                "damMult" => Ok(StatName::DamMulti(sub)),
+                "defMult" => Ok(StatName::DefMulti(sub)),
+                "healMult" => Ok(StatName::HealMulti(sub)),
+                _ => Err(format!("Unknown type: {}", type_str)),
+            };
+        }
+        match type_str {
+            "jh" => Ok(StatName::JH),
+            "spd" => Ok(StatName::SPD),
+            "damRaw" => Ok(StatName::DamRaw),
+            "spPct1Final" => Ok(StatName::SpPct1Final),
+            "spPct2Final" => Ok(StatName::SpPct2Final),
+            "spPct3Final" => Ok(StatName::SpPct3Final),
+            "hprRaw" => Ok(StatName::HprRaw),
+            "hprPct" => Ok(StatName::HprPct),
+            "mr" => Ok(StatName::MR),
+            "ls" => Ok(StatName::LS),
+            "ms" => Ok(StatName::MS),
+            "sdRaw" => Ok(StatName::SdRaw),
+            "sdPct" => Ok(StatName::SdPct),
+            "hpBonus" => Ok(StatName::HpBonus),
+            "poison" => Ok(StatName::Poison),
+            "ref" => Ok(StatName::Ref),
+            "eSteal" => Ok(StatName::ESteal),
+            "mdPct" => Ok(StatName::MdPct),
+            "mdRaw" => Ok(StatName::MdRaw),
+            "str" => Ok(StatName::Str),
+            "dex" => Ok(StatName::Dex),
+            "int" => Ok(StatName::Int),
+            "def" => Ok(StatName::Def),
+            "agi" => Ok(StatName::Agi),
+            "nDamRaw" => Ok(StatName::NDamRaw),
+            "eDamRaw" => Ok(StatName::EDamRaw),
+            "tDamRaw" => Ok(StatName::TDamRaw),
+            "wDamRaw" => Ok(StatName::WDamRaw),
+            "fDamRaw" => Ok(StatName::FDamRaw),
+            "aDamRaw" => Ok(StatName::ADamRaw),
+            "eDamPct" => Ok(StatName::EDamPct),
+            "tDamPct" => Ok(StatName::TDamPct),
+            "wDamPct" => Ok(StatName::WDamPct),
+            "fDamPct" => Ok(StatName::FDamPct),
+            "aDamPct" => Ok(StatName::ADamPct),
+            "eDamAddMax" => Ok(StatName::EDamAddMax),
+            "tDamAddMax" => Ok(StatName::TDamAddMax),
+            "wDamAddMax" => Ok(StatName::WDamAddMax),
+            "fDamAddMax" => Ok(StatName::FDamAddMax),
+            "aDamAddMax" => Ok(StatName::ADamAddMax),
+            "eDamAddMin" => Ok(StatName::EDamAddMin),
+            "tDamAddMin" => Ok(StatName::TDamAddMin),
+            "wDamAddMin" => Ok(StatName::WDamAddMin),
+            "fDamAddMin" => Ok(StatName::FDamAddMin),
+            "aDamAddMin" => Ok(StatName::ADamAddMin),
+            _ => Err(format!("Unknown type: {}", type_str)),
+        }
+    }
+}
 fn deserialize_stat_name<'de, D>(deserializer: D) -> Result<StatName, D::Error>
 where
     D: Deserializer<'de>,
 {
     let type_str: String = Deserialize::deserialize(deserializer)?;
+    StatName::from_str(&type_str).map_err(serde::de::Error::custom)
+}
 
-    if type_str.contains('.') {
-        let parts: Vec<&str> = type_str.split('.').collect();
-        let sub = parts[1..].join(".").to_string();
-        return match parts[0] {
-            "damMult" => Ok(StatName::DamMulti(sub)),
-            "defMult" => Ok(StatName::DefMulti(sub)),
-            "healMult" => Ok(StatName::HealMulti(sub)),
-            _ => Err(serde::de::Error::custom(format!(
-                "Unknown type: {}",
-                type_str
-            ))),
-        };
+/// https://github.com/hppeng-wynn/hppeng-wynn.github.io/blob/50ed4620bd0a4e3af7dd5646971c6dcd78e8b783/js/builder/atree.js#L85
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StatScaling {
+    /// the value comes from a slider (`[toggles]` in config.toml) instead of the build stats
+    #[serde(default)]
+    pub slider: bool,
+    pub slider_name: Option<String>,
+    pub slider_max: Option<f64>,
+    pub slider_step: Option<f64>,
+    pub slider_default: Option<f64>,
+    #[serde(default)]
+    pub behavior: Behavior,
+    #[serde(default)]
+    pub inputs: Vec<ScalingTarget>,
+    pub output: Option<ScalingOutput>,
+    /// numbers, or property references like "21.penalty_per_clone" which are not supported
+    #[serde(default)]
+    pub scaling: Vec<NumberOrString>,
+    /// a number, or a property reference like "21.max_penalty" which is not supported
+    pub max: Option<NumberOrString>,
+    pub round: Option<bool>,
+}
+
+impl StatScaling {
+    pub fn is_slider(&self) -> bool {
+        self.slider
     }
-    match type_str.as_str() {
-        "jh" => Ok(StatName::JH),
-        "spd" => Ok(StatName::SPD),
-        "damRaw" => Ok(StatName::DamRaw),
-        "spPct1Final" => Ok(StatName::SpPct1Final),
-        "spPct2Final" => Ok(StatName::SpPct2Final),
-        "spPct3Final" => Ok(StatName::SpPct3Final),
-        "eDamPct" => Ok(StatName::EDamPct),
-        "tDamPct" => Ok(StatName::TDamPct),
-        "wDamPct" => Ok(StatName::WDamPct),
-        "fDamPct" => Ok(StatName::FDamPct),
-        "aDamPct" => Ok(StatName::ADamPct),
-        "eDamAddMax" => Ok(StatName::EDamAddMax),
-        "tDamAddMax" => Ok(StatName::TDamAddMax),
-        "wDamAddMax" => Ok(StatName::WDamAddMax),
-        "fDamAddMax" => Ok(StatName::FDamAddMax),
-        "aDamAddMax" => Ok(StatName::ADamAddMax),
-        "eDamAddMin" => Ok(StatName::EDamAddMin),
-        "tDamAddMin" => Ok(StatName::TDamAddMin),
-        "wDamAddMin" => Ok(StatName::WDamAddMin),
-        "fDamAddMin" => Ok(StatName::FDamAddMin),
-        "aDamAddMin" => Ok(StatName::ADamAddMin),
-        _ => Err(serde::de::Error::custom(format!(
-            "Unknown type: {}",
-            type_str
-        ))),
+    pub fn outputs(&self) -> &[ScalingTarget] {
+        match &self.output {
+            Some(ScalingOutput::One(output)) => std::slice::from_ref(output),
+            Some(ScalingOutput::Many(outputs)) => outputs,
+            None => &[],
+        }
+    }
+    /// https://github.com/hppeng-wynn/hppeng-wynn.github.io/blob/50ed4620bd0a4e3af7dd5646971c6dcd78e8b783/js/builder/atree.js#L1041
+    pub fn eval<F>(&self, stat: F, toggles: &Toggles) -> f64
+    where
+        F: Fn(&StatName) -> f64,
+    {
+        let mut round = self.round.unwrap_or(true);
+        let mut total = if self.slider {
+            round = false;
+            let slider_value = self
+                .slider_name
+                .as_ref()
+                .and_then(|name| toggles.get(name).copied())
+                .or(self.slider_default)
+                .unwrap_or(0.0);
+            slider_value * self.scaling.first().map_or(0.0, NumberOrString::number)
+        } else {
+            self.scaling
+                .iter()
+                .zip(&self.inputs)
+                .map(|(scaling, input)| match input {
+                    ScalingTarget::Stat { name } => scaling.number() * stat(name),
+                    // props are not relevant to the build stats
+                    ScalingTarget::Prop { abil: _, name: _ } => 0.0,
+                })
+                .sum()
+        };
+        if round {
+            total = total.floor();
+        }
+        if let Some(NumberOrString::Number(max)) = self.max {
+            if (max > 0.0 && total > max) || (max < 0.0 && total < max) {
+                total = max;
+            }
+        }
+        total
     }
 }
 
-/// https://github.com/hppeng-wynn/hppeng-wynn.github.io/blob/50ed4620bd0a4e3af7dd5646971c6dcd78e8b783/js/builder/atree.js#L85
 #[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct StatScaling {
-    // TODO
+#[serde(tag = "type")]
+#[serde(rename_all = "snake_case")]
+pub enum ScalingTarget {
+    Prop {
+        abil: i32,
+        name: String,
+    },
+    Stat {
+        #[serde(deserialize_with = "deserialize_stat_name")]
+        name: StatName,
+    },
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(untagged)]
+pub enum ScalingOutput {
+    One(ScalingTarget),
+    Many(Vec<ScalingTarget>),
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(untagged)]
 pub enum NumberOrString {
     Number(f64),
     Text(String),
 }
+impl NumberOrString {
+    /// text is a property reference ("<ability id>.<property name>"), it is treated as 0,
+    /// the references in spell totals are resolved by `Spell::resolve_hits`
+    pub fn number(&self) -> f64 {
+        match self {
+            NumberOrString::Number(v) => *v,
+            NumberOrString::Text(_) => 0.0,
+        }
+    }
+}
 
 #[cfg(test)]
 mod tests {
@@ -537,4 +846,117 @@ mod tests {
 
         let _: AbilitiesMap = serde_json::from_reader(reader).unwrap();
     }
+    #[test]
+    fn stat_scaling_eval_works() {
+        let traveler: StatScaling = serde_json::from_str(
+            r#"{
+                "inputs": [{ "name": "spd", "type": "stat" }],
+                "max": 100,
+                "output": { "name": "sdRaw", "type": "stat" },
+                "scaling": [1],
+                "slider": false
+            }"#,
+        )
+        .unwrap();
+        let spd =
+            |value: f64| move |name: &StatName| if name == &StatName::SPD { value } else { 0.0 };
+        assert_eq!(30.0, traveler.eval(spd(30.5), &Toggles::new()));
+        assert_eq!(100.0, traveler.eval(spd(150.0), &Toggles::new()));
+
+        let corrupted: StatScaling = serde_json::from_str(
+            r#"{
+                "max": 120,
+                "output": { "name": "damRaw", "type": "stat" },
+                "scaling": [0.5],
+                "slider": true,
+                "slider_name": "Corrupted"
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(0.0, corrupted.eval(|_| 0.0, &Toggles::new()));
+        let toggles = Toggles::from([("Corrupted".to_string(), 51.0)]);
+        assert_eq!(25.5, corrupted.eval(|_| 0.0, &toggles));
+        let toggles = Toggles::from([("Corrupted".to_string(), 300.0)]);
+        assert_eq!(120.0, corrupted.eval(|_| 0.0, &toggles));
+    }
+    #[test]
+    fn scaling_bound_works() {
+        let scaling = |json: &str| -> StatScaling { serde_json::from_str(json).unwrap() };
+        let mut stat = ATreeStat {
+            scalings: vec![scaling(
+                r#"{
+                    "inputs": [{ "name": "spd", "type": "stat" }],
+                    "max": 100,
+                    "output": { "name": "sdRaw", "type": "stat" },
+                    "scaling": [1]
+                }"#,
+            )],
+            ..Default::default()
+        };
+        assert_eq!(100, stat.scaling_bound().unwrap().common_stat.sd_raw());
+
+        stat.scalings.push(scaling(
+            r#"{
+                "inputs": [{ "name": "spd", "type": "stat" }],
+                "output": { "abil": 1, "name": "cost", "type": "prop" },
+                "scaling": [1]
+            }"#,
+        ));
+        assert!(stat.scaling_bound().is_some());
+
+        stat.scalings.push(scaling(
+            r#"{
+                "inputs": [{ "name": "spd", "type": "stat" }],
+                "output": { "name": "sdRaw", "type": "stat" },
+                "scaling": [1]
+            }"#,
+        ));
+        assert!(stat.scaling_bound().is_none());
+    }
+    #[test]
+    fn join_stat_works() {
+        let node: ATreeNodeData = serde_json::from_str(
+            r#"{
+                "display_name": "Test",
+                "id": 0,
+                "parents": [],
+                "cost": 1,
+                "effects": [
+                    {
+                        "type": "raw_stat",
+                        "toggle": "Activate Test",
+                        "bonuses": [
+                            { "type": "stat", "name": "damMult.Test", "value": 60 },
+                            { "type": "stat", "name": "defMult.Test", "value": 20 }
+                        ]
+                    },
+                    {
+                        "type": "raw_stat",
+                        "bonuses": [{ "type": "stat", "name": "damMult.Always:3.Uppercut", "value": 50 }]
+                    },
+                    {
+                        "type": "stat_scaling",
+                        "inputs": [{ "name": "spd", "type": "stat" }],
+                        "output": { "name": "sdRaw", "type": "stat" },
+                        "scaling": [1]
+                    }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let stat = node.join_stat(&Toggles::new());
+        assert_eq!(1, stat.scalings.len());
+        assert_eq!(1.5, stat.dam_mult(3, "Uppercut"));
+        assert_eq!(1.0, stat.dam_mult(3, "Fireworks"));
+        assert_eq!(1.0, stat.def_mult());
+
+        let stat = node.join_stat(&Toggles::from([("Activate Test".to_string(), 1.0)]));
+        assert_eq!(1.5 * 1.6, stat.dam_mult(3, "Uppercut"));
+        assert_eq!(1.6, stat.dam_mult(3, "Fireworks"));
+        assert_eq!(0.8, stat.def_mult());
+
+        let scaled = stat.scale(|name| if name == &StatName::SPD { 20.0 } else { 0.0 });
+        assert_eq!(20, scaled.common_stat.sd_raw());
+    }
 }
@@ -25,9 +25,34 @@ pub struct Apparel {
     pub sec_stat_max: SecStat,
     pub sec_stat_min: SecStat,
 
+    pub melee_stat_max: MeleeStat,
+    pub melee_stat_min: MeleeStat,
+    pub damage_stat_max: DamageStat,
+    pub damage_stat_min: DamageStat,
+    pub spell_cost_max: SpellCost,
+    pub spell_cost_min: SpellCost,
+
+    pub major_ids: Vec<String>,
+
     pub fix_id: bool,
 }
 
+impl Apparel {
+    /// apply armour powders to the base defense
+    /// https://github.com/hppeng-wynn/hppeng-wynn.github.io/blob/HEAD/js/build_utils.js#L98
+    ///
+    /// the powders beyond the powder slots of the item are dropped
+    pub fn apply_powders(&mut self, powders: &[Powder]) {
+        let slots = (self.slots.max(0) as usize).min(powders.len());
+        for powder in &powders[..slots] {
+            let stat = powder.stat();
+            let element = powder.element();
+            self.def.inner[element] += stat.def_plus;
+            self.def.inner[(element + 4) % 5] -= stat.def_minus;
+        }
+    }
+}
+
 impl AsRef<Apparel> for Apparel {
     fn as_ref(&self) -> &Apparel {
         self
@@ -58,7 +83,42 @@ impl TryFrom<&WApiItem> for Apparel {
             common_stat_min: value.common_stat_min(),
             sec_stat_max: value.sec_stat_max(),
             sec_stat_min: value.sec_stat_min(),
+            melee_stat_max: value.melee_stat_max(),
+            melee_stat_min: value.melee_stat_min(),
+            damage_stat_max: value.damage_stat_max(),
+            damage_stat_min: value.damage_stat_min(),
+            spell_cost_max: value.spell_cost_max(),
+            spell_cost_min: value.spell_cost_min(),
+            major_ids: value.major_ids(),
             fix_id: value.identified.unwrap_or(false),
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn apply_powders_works() {
+        let powders = ["e6", "e6", "e6"].map(|v| Powder::from_str(v).unwrap());
+        let powdered = |slots| {
+            let mut apparel = Apparel {
+                slots,
+                ..Default::default()
+            };
+            apparel.apply_powders(&powders);
+            apparel.def
+        };
+        let stat = powders[0].stat();
+        // earth powders add earth defense and remove air defense
+        let def = |count: i16| Point::new(count * stat.def_plus, 0, 0, 0, -count * stat.def_minus);
+
+        assert_eq!(powdered(3), def(3));
+        // the powders beyond the slots are dropped
+        assert_eq!(powdered(2), def(2));
+        assert_eq!(powdered(0), def(0));
+    }
+}
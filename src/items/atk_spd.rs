@@ -2,7 +2,7 @@ use std::str::FromStr;
 
 use super::*;
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, PartialEq)]
 pub enum AtkSpd {
     #[default]
     SuperSlow,
@@ -26,6 +26,21 @@ impl AtkSpd {
             AtkSpd::SuperFast => 4.3,
         }
     }
+    /// attack speed after the raw attack speed bonus, clamped to the fastest and slowest speed
+    /// https://github.com/hppeng-wynn/hppeng-wynn.github.io/blob/HEAD/js/builder/builder_graph.js
+    pub fn with_tier(&self, atk_tier: i16) -> AtkSpd {
+        const SPEEDS: [AtkSpd; 7] = [
+            AtkSpd::SuperSlow,
+            AtkSpd::VerySlow,
+            AtkSpd::Slow,
+            AtkSpd::Normal,
+            AtkSpd::Fast,
+            AtkSpd::VeryFast,
+            AtkSpd::SuperFast,
+        ];
+        let index = SPEEDS.iter().position(|v| v == self).unwrap_or(0) as i16;
+        SPEEDS[(index + atk_tier).clamp(0, 6) as usize].clone()
+    }
 }
 
 impl From<AttackSpeed> for AtkSpd {
@@ -58,3 +73,16 @@ impl FromStr for AtkSpd {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn with_tier_works() {
+        assert_eq!(AtkSpd::Normal.with_tier(2), AtkSpd::VeryFast);
+        assert_eq!(AtkSpd::Normal.with_tier(-1), AtkSpd::Slow);
+        assert_eq!(AtkSpd::Fast.with_tier(5), AtkSpd::SuperFast);
+        assert_eq!(AtkSpd::VerySlow.with_tier(-3), AtkSpd::SuperSlow);
+    }
+}
@@ -36,6 +36,9 @@ impl CommonStat {
     pub fn any_lt(&self, other: &Self) -> bool {
         self.inner.simd_lt(other.inner).any()
     }
+    pub fn any_gt(&self, other: &Self) -> bool {
+        self.inner.simd_gt(other.inner).any()
+    }
     pub fn hpr_raw(&self) -> i16 {
         self.inner[0]
     }
@@ -166,4 +169,33 @@ mod tests {
         assert_eq!(CommonStat::new(100, -10, 0, 0, 0, 0, 0, 0).hpr(), 90);
         assert_eq!(CommonStat::new(-100, -10, 0, 0, 0, 0, 0, 0).hpr(), -110);
     }
+
+    #[test]
+    fn any_lt_and_any_gt_works() {
+        let stat = CommonStat::new(0, 0, 0, 0, 0, 15, 0, 0);
+        let min = CommonStat::new(
+            i16::MIN,
+            i16::MIN,
+            i16::MIN,
+            i16::MIN,
+            i16::MIN,
+            -10,
+            i16::MIN,
+            i16::MIN,
+        );
+        let max = CommonStat::new(
+            i16::MAX,
+            i16::MAX,
+            i16::MAX,
+            i16::MAX,
+            i16::MAX,
+            20,
+            i16::MAX,
+            i16::MAX,
+        );
+        assert!(!stat.any_lt(&min));
+        assert!(!stat.any_gt(&max));
+        assert!(CommonStat::new(0, 0, 0, 0, 0, 25, 0, 0).any_gt(&max));
+        assert!(CommonStat::new(0, 0, 0, 0, 0, -15, 0, 0).any_lt(&min));
+    }
 }
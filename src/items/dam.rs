@@ -16,6 +16,9 @@ impl Dam {
     pub fn any_lt(&self, other: &Self) -> bool {
         self.inner.simd_lt(other.inner).any()
     }
+    pub fn any_gt(&self, other: &Self) -> bool {
+        self.inner.simd_gt(other.inner).any()
+    }
     pub fn n(&self) -> i16 {
         self.inner[0]
     }
@@ -49,6 +52,19 @@ impl<'a> Add<&'a Dam> for &Dam {
         }
     }
 }
+impl From<&Dam> for [i32; 6] {
+    fn from(value: &Dam) -> Self {
+        [
+            value.n(),
+            value.e(),
+            value.t(),
+            value.w(),
+            value.f(),
+            value.a(),
+        ]
+        .map(i32::from)
+    }
+}
 impl std::fmt::Display for Dam {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -0,0 +1,84 @@
+use std::ops::AddAssign;
+
+use super::*;
+
+/// spell damage and generic damage identifications that are not in `CommonStat`
+#[derive(Clone, Debug, Default)]
+pub struct DamageStat {
+    /// nSdPct eSdPct tSdPct wSdPct fSdPct aSdPct
+    pub sd_pct_s: Dam,
+    /// nSdRaw eSdRaw tSdRaw wSdRaw fSdRaw aSdRaw
+    pub sd_raw_s: Dam,
+    /// rSdPct
+    pub r_sd_pct: i16,
+    /// rSdRaw
+    pub r_sd_raw: i16,
+    /// damPct
+    pub dam_pct: i16,
+    /// damRaw
+    pub dam_raw: i16,
+    /// nDamRaw eDamRaw tDamRaw wDamRaw fDamRaw aDamRaw
+    pub dam_raw_s: Dam,
+    /// rDamPct
+    pub r_dam_pct: i16,
+    /// rDamRaw
+    pub r_dam_raw: i16,
+    /// critDamPct
+    pub crit_dam_pct: i16,
+}
+
+impl DamageStat {
+    pub fn sum_max_stats(apparels: &[&Apparel], weapon: &Weapon) -> Self {
+        let mut total = weapon.damage_stat_max.clone();
+        for item in apparels {
+            total += &item.damage_stat_max;
+        }
+        total
+    }
+}
+
+impl AddAssign<&DamageStat> for DamageStat {
+    fn add_assign(&mut self, rhs: &DamageStat) {
+        self.sd_pct_s += &rhs.sd_pct_s;
+        self.sd_raw_s += &rhs.sd_raw_s;
+        self.r_sd_pct += rhs.r_sd_pct;
+        self.r_sd_raw += rhs.r_sd_raw;
+        self.dam_pct += rhs.dam_pct;
+        self.dam_raw += rhs.dam_raw;
+        self.dam_raw_s += &rhs.dam_raw_s;
+        self.r_dam_pct += rhs.r_dam_pct;
+        self.r_dam_raw += rhs.r_dam_raw;
+        self.crit_dam_pct += rhs.crit_dam_pct;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sum_max_stats_works() {
+        let apparel = Apparel {
+            damage_stat_max: DamageStat {
+                sd_pct_s: Dam::new(0, 0, 0, 15, 0, 0),
+                r_sd_raw: 30,
+                crit_dam_pct: 10,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let weapon = Weapon {
+            damage_stat_max: DamageStat {
+                r_sd_raw: -5,
+                dam_pct: 20,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let total = DamageStat::sum_max_stats(&[&apparel, &apparel], &weapon);
+        assert_eq!(total.sd_pct_s.w(), 30);
+        assert_eq!(total.r_sd_raw, 55);
+        assert_eq!(total.dam_pct, 20);
+        assert_eq!(total.crit_dam_pct, 20);
+    }
+}
@@ -53,6 +53,42 @@ impl Damages {
             max: max_sum,
         }
     }
+    /// mask of elements that have non-zero max damage
+    pub fn present(&self) -> Mask {
+        Mask::from_slice(self.inner.clone().map(|v| v.max > 0.0))
+    }
+    /// apply weapon powders, converting neutral damage and adding the powder damage
+    /// https://github.com/hppeng-wynn/hppeng-wynn.github.io/blob/HEAD/js/build_utils.js#L135
+    pub fn apply_powders(&mut self, powders: &[Powder]) {
+        let neutral_base = self.inner[0].clone();
+        let mut neutral_remaining = self.inner[0].clone();
+
+        // powders of the same element are merged, applied in the order of first appearance
+        // (element, convert, min, max)
+        let mut apply_order: Vec<(usize, f64, f64, f64)> = Vec::new();
+        for powder in powders {
+            let stat = powder.stat();
+            let convert = stat.convert / 100.0;
+            match apply_order.iter_mut().find(|v| v.0 == powder.element()) {
+                Some(apply_info) => {
+                    apply_info.1 += convert;
+                    apply_info.2 += stat.min;
+                    apply_info.3 += stat.max;
+                }
+                None => apply_order.push((powder.element(), convert, stat.min, stat.max)),
+            }
+        }
+
+        for (element, convert, min, max) in apply_order {
+            let min_diff = f64::min(neutral_remaining.min, convert * neutral_base.min);
+            let max_diff = f64::min(neutral_remaining.max, convert * neutral_base.max);
+            neutral_remaining.min -= min_diff;
+            neutral_remaining.max -= max_diff;
+            self.inner[element + 1].min += min_diff + min;
+            self.inner[element + 1].max += max_diff + max;
+        }
+        self.inner[0] = neutral_remaining;
+    }
     pub fn only_rainbow(&self) -> Self {
         Self::from_slice([
             Range { min: 0.0, max: 0.0 },
@@ -347,3 +383,26 @@ impl std::ops::BitOr for Mask {
         ])
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn apply_powders_works() {
+        let mut damages = Damages::new(
+            100.0, 0.0, 0.0, 0.0, 0.0, 0.0, 200.0, 0.0, 0.0, 0.0, 0.0, 0.0,
+        );
+        damages.apply_powders(&[
+            Powder::from_str("e6").unwrap(),
+            Powder::from_str("t6").unwrap(),
+            Powder::from_str("e6").unwrap(),
+        ]);
+        assert_eq!(
+            damages,
+            Damages::new(0.0, 114.0, 13.0, 0.0, 0.0, 0.0, 0.0, 210.0, 38.0, 0.0, 0.0, 0.0)
+        );
+    }
+}
@@ -0,0 +1,75 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::*;
+
+/// key is the major id name used by hppeng, e.g. "PEACEFUL_EFFIGY"
+pub type MajorIdsMap = HashMap<String, MajorId>;
+
+/// https://github.com/hppeng-wynn/hppeng-wynn.github.io/blob/HEAD/js/builder/atree.js#L502
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MajorId {
+    #[serde(rename = "displayName")]
+    pub display_name: String,
+    pub id: String,
+    pub abilities: Vec<MajorIdAbility>,
+}
+
+/// an ability granted by a major id, it is merged into the atree like a normal ability
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MajorIdAbility {
+    /// only the given class gets the ability, all classes if it is None or "Any"
+    pub class: Option<String>,
+    pub base_abil: Option<i32>,
+    /// atree abilities required to activate the ability
+    #[serde(default)]
+    pub dependencies: Vec<i32>,
+    #[serde(default)]
+    pub effects: Vec<Effect>,
+}
+
+impl MajorIdAbility {
+    pub fn is_class(&self, class: &Class) -> bool {
+        match self.class.as_deref() {
+            None | Some("Any") => true,
+            Some(name) => name.parse::<Class>().is_ok_and(|v| &v == class),
+        }
+    }
+}
+
+impl MajorId {
+    /// find a major id by the name shown on items (e.g. "Peaceful Effigy") or by its id
+    pub fn find<'a>(major_ids: &'a MajorIdsMap, name: &str) -> Option<&'a MajorId> {
+        major_ids.get(name).or_else(|| {
+            major_ids
+                .values()
+                .find(|v| v.display_name.eq_ignore_ascii_case(name))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_works() {
+        let major_ids: MajorIdsMap = serde_json::from_str(
+            r#"{
+                "PEACEFUL_EFFIGY": {
+                    "displayName": "Peaceful Effigy",
+                    "id": "PEACEFUL_EFFIGY",
+                    "abilities": [{ "class": "Shaman", "base_abil": 3, "effects": [] }]
+                }
+            }"#,
+        )
+        .unwrap();
+        assert!(MajorId::find(&major_ids, "PEACEFUL_EFFIGY").is_some());
+        assert!(MajorId::find(&major_ids, "Peaceful Effigy").is_some());
+        assert!(MajorId::find(&major_ids, "Plague").is_none());
+
+        let ability = &major_ids["PEACEFUL_EFFIGY"].abilities[0];
+        assert!(ability.is_class(&Class::Shaman));
+        assert!(!ability.is_class(&Class::Mage));
+    }
+}
@@ -0,0 +1,74 @@
+use std::ops::AddAssign;
+
+use super::*;
+
+/// main attack identifications, the melee counterpart of the spell damage stats
+#[derive(Clone, Debug, Default)]
+pub struct MeleeStat {
+    /// mdPct
+    pub md_pct: i16,
+    /// mdRaw
+    pub md_raw: i16,
+    /// nMdPct eMdPct tMdPct wMdPct fMdPct aMdPct
+    pub md_pct_s: Dam,
+    /// nMdRaw eMdRaw tMdRaw wMdRaw fMdRaw aMdRaw
+    pub md_raw_s: Dam,
+    /// rMdPct
+    pub r_md_pct: i16,
+    /// rMdRaw
+    pub r_md_raw: i16,
+    /// atkTier, raw attack speed bonus
+    pub atk_tier: i16,
+}
+
+impl MeleeStat {
+    pub fn sum_max_stats(apparels: &[&Apparel], weapon: &Weapon) -> Self {
+        let mut total = weapon.melee_stat_max.clone();
+        for item in apparels {
+            total += &item.melee_stat_max;
+        }
+        total
+    }
+}
+
+impl AddAssign<&MeleeStat> for MeleeStat {
+    fn add_assign(&mut self, rhs: &MeleeStat) {
+        self.md_pct += rhs.md_pct;
+        self.md_raw += rhs.md_raw;
+        self.md_pct_s += &rhs.md_pct_s;
+        self.md_raw_s += &rhs.md_raw_s;
+        self.r_md_pct += rhs.r_md_pct;
+        self.r_md_raw += rhs.r_md_raw;
+        self.atk_tier += rhs.atk_tier;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sum_max_stats_works() {
+        let apparel = Apparel {
+            melee_stat_max: MeleeStat {
+                md_pct: 10,
+                md_raw_s: Dam::new(0, 0, 20, 0, 0, 0),
+                atk_tier: 1,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let weapon = Weapon {
+            melee_stat_max: MeleeStat {
+                md_pct: 5,
+                atk_tier: -1,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let total = MeleeStat::sum_max_stats(&[&apparel, &apparel], &weapon);
+        assert_eq!(total.md_pct, 25);
+        assert_eq!(total.md_raw_s.t(), 40);
+        assert_eq!(total.atk_tier, 1);
+    }
+}
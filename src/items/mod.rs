@@ -4,11 +4,18 @@ mod atk_spd;
 mod class;
 mod common_stat;
 mod dam;
+mod damage_stat;
 mod damages;
+mod major_id;
+mod melee_stat;
 mod point;
+mod powder;
 mod range;
 mod sec_stat;
+mod set;
 mod spell;
+mod spell_cost;
+mod tome;
 mod wapi_item;
 mod weapon;
 
@@ -25,11 +32,18 @@ pub use atk_spd::*;
 pub use class::*;
 pub use common_stat::*;
 pub use dam::*;
+pub use damage_stat::*;
 pub use damages::*;
+pub use major_id::*;
+pub use melee_stat::*;
 pub use point::*;
+pub use powder::*;
 pub use range::*;
 pub use sec_stat::*;
+pub use set::*;
 pub use spell::*;
+pub use spell_cost::*;
+pub use tome::*;
 pub use wapi_item::*;
 pub use weapon::*;
 
@@ -43,7 +57,46 @@ pub fn load_hppeng_id_map() -> HashMap<String, i32> {
     id_map
 }
 
-pub fn load_from_wapi<P>(path: P) -> Result<([Vec<Apparel>; 7], Vec<Weapon>), String>
+/// tome name to hppeng tome id, hppeng keeps tomes in a separate id space from the other items
+///
+/// the ids of hppeng's tomes.json at assets/tomes.json are added to the ones of
+/// assets/tome_id_map.json when the file is there
+/// https://github.com/hppeng-wynn/hppeng-wynn.github.io/blob/HEAD/data/tomes.json
+pub fn load_hppeng_tome_id_map() -> HashMap<String, i32> {
+    let path = "assets/tome_id_map.json";
+    let mut file = File::open(path).expect("fs should be able to open tome_id_map.json file");
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)
+        .expect("fs should be able to read tome_id_map.json file");
+    let mut id_map: HashMap<String, i32> = serde_json::from_str(&contents).unwrap();
+    if let Ok(contents) = std::fs::read_to_string("assets/tomes.json") {
+        id_map.extend(hppeng_tome_ids(&contents).unwrap());
+    }
+    id_map
+}
+
+/// the file is hppeng's majid.json, the major ids are keyed by their id
+pub fn load_major_ids<P>(path: P) -> Result<MajorIdsMap, String>
+where
+    P: AsRef<Path>,
+{
+    let file = File::open(path).map_err(|e| format!("Could not open major ids file: {}", e))?;
+    serde_json::from_reader(BufReader::new(file))
+        .map_err(|e| format!("Could not parse major ids file: {}", e))
+}
+
+/// the file uses the format of hppeng's sets, the sets are keyed by their name
+pub fn load_sets<P>(path: P) -> Result<SetsMap, String>
+where
+    P: AsRef<Path>,
+{
+    let file = File::open(path).map_err(|e| format!("Could not open sets file: {}", e))?;
+    serde_json::from_reader(BufReader::new(file))
+        .map_err(|e| format!("Could not parse sets file: {}", e))
+}
+
+#[allow(clippy::type_complexity)]
+pub fn load_from_wapi<P>(path: P) -> Result<([Vec<Apparel>; 7], Vec<Weapon>, Vec<Tome>), String>
 where
     P: AsRef<Path>,
 {
@@ -56,13 +109,14 @@ where
 
         let mut apparels: [Vec<Apparel>; 7] = Default::default();
         let mut weapons: Vec<Weapon> = Vec::new();
+        let mut tomes: Vec<Tome> = Vec::new();
         items.iter().for_each(|(_, value)| match &value.r#type {
             Some(item_type) => match item_type {
                 ItemType::Material => (),
                 ItemType::Tool => (),
                 ItemType::Ingredient => (),
                 ItemType::Charm => (),
-                ItemType::Tome => (),
+                ItemType::Tome => tomes.push(Tome::try_from(value).unwrap()),
                 ItemType::Accessory => {
                     match &value.accessory_type {
                         Some(accessory_type) => match accessory_type {
@@ -103,7 +157,7 @@ where
             None => (),
         });
 
-        Ok((apparels, weapons))
+        Ok((apparels, weapons, tomes))
     } else {
         Err("Could not open file".to_string())
     }
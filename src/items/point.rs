@@ -1,3 +1,4 @@
+use std::simd::Select;
 use std::simd::cmp::SimdPartialOrd;
 use std::simd::num::SimdInt;
 use std::{
@@ -27,6 +28,9 @@ impl Point {
     pub fn any_lt(&self, other: &Self) -> bool {
         self.inner.simd_lt(other.inner).any()
     }
+    pub fn any_gt(&self, other: &Self) -> bool {
+        self.inner.simd_gt(other.inner).any()
+    }
     pub fn merge_max(&self, other: &Self) -> Self {
         let mask = self.inner.simd_lt(other.inner);
         Self {
@@ -0,0 +1,152 @@
+use std::{fmt::Display, str::FromStr};
+
+use serde::{de, Deserialize};
+
+/// powder stat, (min, max) is the damage added to the weapon, `convert` is the percentage of
+/// neutral damage converted to the powder element
+/// https://github.com/hppeng-wynn/hppeng-wynn.github.io/blob/HEAD/js/powders.js#L25
+#[derive(Clone, Debug, PartialEq)]
+pub struct PowderStat {
+    pub min: f64,
+    pub max: f64,
+    pub convert: f64,
+    /// defense added to the powder element when applied to armour
+    pub def_plus: i16,
+    /// defense removed from the element before the powder element (e <- t <- w <- f <- a <- e)
+    pub def_minus: i16,
+}
+const fn stat(min: f64, max: f64, convert: f64, def_plus: i16, def_minus: i16) -> PowderStat {
+    PowderStat {
+        min,
+        max,
+        convert,
+        def_plus,
+        def_minus,
+    }
+}
+
+/// index = element * 6 + (tier - 1), element: e t w f a
+/// https://github.com/hppeng-wynn/hppeng-wynn.github.io/blob/HEAD/js/powders.js#L37
+pub const POWDER_STATS: [PowderStat; 30] = [
+    stat(3.0, 6.0, 17.0, 2, 1),
+    stat(5.0, 8.0, 21.0, 4, 2),
+    stat(6.0, 10.0, 25.0, 8, 3),
+    stat(7.0, 10.0, 31.0, 14, 5),
+    stat(9.0, 11.0, 38.0, 22, 9),
+    stat(11.0, 13.0, 46.0, 30, 13),
+    stat(1.0, 12.0, 9.0, 3, 1),
+    stat(1.0, 16.0, 11.0, 5, 1),
+    stat(2.0, 18.0, 14.0, 9, 2),
+    stat(3.0, 18.0, 17.0, 14, 4),
+    stat(3.0, 24.0, 22.0, 20, 5),
+    stat(5.0, 22.0, 28.0, 28, 9),
+    stat(3.0, 5.0, 13.0, 3, 1),
+    stat(4.0, 7.0, 15.0, 6, 1),
+    stat(6.0, 8.0, 17.0, 11, 2),
+    stat(8.0, 12.0, 21.0, 18, 4),
+    stat(11.0, 14.0, 26.0, 28, 7),
+    stat(12.0, 16.0, 32.0, 40, 10),
+    stat(2.0, 5.0, 14.0, 3, 1),
+    stat(4.0, 8.0, 16.0, 5, 2),
+    stat(5.0, 9.0, 19.0, 9, 3),
+    stat(8.0, 10.0, 23.0, 16, 5),
+    stat(10.0, 13.0, 28.0, 28, 8),
+    stat(12.0, 15.0, 34.0, 40, 12),
+    stat(2.0, 6.0, 11.0, 3, 1),
+    stat(3.0, 10.0, 14.0, 6, 2),
+    stat(5.0, 11.0, 17.0, 10, 3),
+    stat(6.0, 12.0, 21.0, 16, 5),
+    stat(8.0, 14.0, 26.0, 24, 7),
+    stat(10.0, 16.0, 32.0, 34, 10),
+];
+
+const POWDER_ELEMENTS: [char; 5] = ['e', 't', 'w', 'f', 'a'];
+
+/// powder id used by hppeng, 0:e1 1:e2 ... 6:t1 ... 29:a6
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Powder {
+    pub id: u8,
+}
+
+impl Powder {
+    pub fn new(id: u8) -> Result<Self, String> {
+        if (id as usize) < POWDER_STATS.len() {
+            Ok(Self { id })
+        } else {
+            Err(format!("Invalid powder id: {}", id))
+        }
+    }
+    /// 0:e 1:t 2:w 3:f 4:a
+    pub fn element(&self) -> usize {
+        self.id as usize / 6
+    }
+    /// 1..=6
+    pub fn tier(&self) -> u8 {
+        self.id % 6 + 1
+    }
+    pub fn stat(&self) -> &'static PowderStat {
+        &POWDER_STATS[self.id as usize]
+    }
+}
+
+impl FromStr for Powder {
+    type Err = String;
+
+    /// "e6", "T3", ...
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut chars = s.chars();
+        let (Some(element), Some(tier), None) = (chars.next(), chars.next(), chars.next()) else {
+            return Err(format!(
+                "Invalid value for powder: expected format like 'e6', found '{}'",
+                s
+            ));
+        };
+        let element = POWDER_ELEMENTS
+            .iter()
+            .position(|&v| v == element.to_ascii_lowercase())
+            .ok_or_else(|| format!("Invalid powder element, found '{}'", s))?;
+        let tier = match tier.to_digit(10) {
+            Some(tier @ 1..=6) => tier as usize,
+            _ => return Err(format!("Invalid powder tier, found '{}'", s)),
+        };
+        Powder::new((element * 6 + tier - 1) as u8)
+    }
+}
+
+impl Display for Powder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", POWDER_ELEMENTS[self.element()], self.tier())
+    }
+}
+
+impl<'de> Deserialize<'de> for Powder {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        let s: String = Deserialize::deserialize(deserializer)?;
+        Powder::from_str(&s).map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_str_works() {
+        assert_eq!(Powder::from_str("e1"), Ok(Powder { id: 0 }));
+        assert_eq!(Powder::from_str("W6"), Ok(Powder { id: 17 }));
+        assert_eq!(Powder::from_str("a6"), Ok(Powder { id: 29 }));
+        assert!(Powder::from_str("x1").is_err());
+        assert!(Powder::from_str("e7").is_err());
+        assert!(Powder::from_str("e").is_err());
+    }
+    #[test]
+    fn display_works() {
+        for id in 0..30 {
+            let powder = Powder::new(id).unwrap();
+            assert_eq!(Powder::from_str(&powder.to_string()), Ok(powder));
+        }
+    }
+}
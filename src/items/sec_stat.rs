@@ -8,13 +8,13 @@ pub struct SecStat {
 }
 
 impl SecStat {
-    pub fn new(exp_bonus: i16, loot_bonus: i16) -> Self {
+    pub fn new(exp_bonus: i16, loot_bonus: i16, heal_pct: i16) -> Self {
         Self {
-            inner: i16x8::from([exp_bonus, loot_bonus, 0, 0, 0, 0, 0, 0]),
+            inner: i16x8::from([exp_bonus, loot_bonus, heal_pct, 0, 0, 0, 0, 0]),
         }
     }
     pub fn sum_max_stats(apparels: &[&Apparel], weapon: &Weapon) -> Self {
-        let mut total: i16x8 = weapon.common_stat_max.inner;
+        let mut total: i16x8 = weapon.sec_stat_max.inner;
 
         for item in apparels {
             total += item.sec_stat_max.inner;
@@ -27,18 +27,26 @@ impl SecStat {
     pub fn loot_bonus(&self) -> i16 {
         self.inner[1]
     }
+    /// healing efficiency
+    pub fn heal_pct(&self) -> i16 {
+        self.inner[2]
+    }
     pub fn any_lt(&self, other: &Self) -> bool {
         self.inner.simd_lt(other.inner).any()
     }
+    pub fn any_gt(&self, other: &Self) -> bool {
+        self.inner.simd_gt(other.inner).any()
+    }
 }
 
 impl std::fmt::Display for SecStat {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "(exp_bonus:{}, loot_bonus:{})",
+            "(exp_bonus:{}, loot_bonus:{}, heal_pct:{})",
             self.exp_bonus(),
             self.loot_bonus(),
+            self.heal_pct(),
         )
     }
 }
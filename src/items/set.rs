@@ -0,0 +1,234 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::*;
+
+/// key is the set name, e.g. "Adventurer's"
+pub type SetsMap = HashMap<String, ItemSet>;
+
+/// https://github.com/hppeng-wynn/hppeng-wynn.github.io/blob/HEAD/js/build_utils.js
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ItemSet {
+    pub items: Vec<String>,
+    /// `bonuses[n - 1]` is the bonus of wearing n pieces, keys are hppeng ids (e.g. "sdPct")
+    pub bonuses: Vec<HashMap<String, serde_json::Value>>,
+}
+
+/// set bonuses of a build, every bonus is an item without requirements so it can be summed with the
+/// other items
+#[derive(Debug, Clone, Default)]
+pub struct Sets {
+    /// bonuses of each set, `bonuses[set][n - 1]` is the bonus of wearing n pieces
+    bonuses: Vec<Vec<Apparel>>,
+    /// item name to the index of its set
+    item_sets: HashMap<String, usize>,
+}
+
+impl Sets {
+    pub fn new(sets: &SetsMap) -> Self {
+        let mut bonuses = Vec::with_capacity(sets.len());
+        let mut item_sets = HashMap::new();
+        for (index, (name, set)) in sets.iter().enumerate() {
+            bonuses.push(
+                set.bonuses
+                    .iter()
+                    .map(|bonus| bonus_apparel(name, bonus))
+                    .collect(),
+            );
+            for item in &set.items {
+                item_sets.insert(item.clone(), index);
+            }
+        }
+        Self { bonuses, item_sets }
+    }
+    /// bonuses of the sets that have pieces in `items`
+    pub fn bonuses<'a>(&'a self, items: &[&Apparel]) -> Vec<&'a Apparel> {
+        let mut counts: Vec<(usize, usize)> = Vec::new();
+        for item in items {
+            let Some(&set) = self.item_sets.get(&item.name) else {
+                continue;
+            };
+            match counts.iter_mut().find(|(index, _)| *index == set) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((set, 1)),
+            }
+        }
+        counts
+            .into_iter()
+            .filter_map(|(set, count)| {
+                let bonuses = &self.bonuses[set];
+                bonuses.get(count - 1).or(bonuses.last())
+            })
+            .collect()
+    }
+    /// index of the set of the apparel, `None` if it is not a piece of a set
+    pub fn set_of(&self, name: &str) -> Option<usize> {
+        self.item_sets.get(name).copied()
+    }
+    /// skill points of the bonus of wearing `count` pieces of the set, the pieces beyond the last
+    /// bonus keep it
+    pub fn bonus_add(&self, set: usize, count: usize) -> Point {
+        match count {
+            0 => Point::default(),
+            _ => {
+                let bonuses = &self.bonuses[set];
+                bonuses
+                    .get(count - 1)
+                    .or(bonuses.last())
+                    .map(|bonus| bonus.add.clone())
+                    .unwrap_or_default()
+            }
+        }
+    }
+    /// whether the apparel is a piece of a set
+    pub fn contains(&self, name: &str) -> bool {
+        self.item_sets.contains_key(name)
+    }
+    /// all the bonuses of each set that has pieces in `items`, to bound the bonuses of the builds
+    /// made of them
+    pub fn possible_bonuses<'a>(&'a self, items: &[&Apparel]) -> Vec<&'a [Apparel]> {
+        let mut sets: Vec<usize> = items
+            .iter()
+            .filter_map(|item| self.item_sets.get(&item.name).copied())
+            .collect();
+        sets.sort_unstable();
+        sets.dedup();
+        sets.into_iter()
+            .map(|set| self.bonuses[set].as_slice())
+            .collect()
+    }
+}
+
+/// the stats of a set bonus as an item, ids that are not calculated are ignored
+fn bonus_apparel(set_name: &str, bonus: &HashMap<String, serde_json::Value>) -> Apparel {
+    let get = |key: &str| {
+        bonus
+            .get(key)
+            .and_then(|value| value.as_f64())
+            .unwrap_or(0.0) as i16
+    };
+    let dam = |suffix: &str| {
+        Dam::new(
+            get(&format!("n{}", suffix)),
+            get(&format!("e{}", suffix)),
+            get(&format!("t{}", suffix)),
+            get(&format!("w{}", suffix)),
+            get(&format!("f{}", suffix)),
+            get(&format!("a{}", suffix)),
+        )
+    };
+    Apparel {
+        name: format!("{} set bonus", set_name),
+        tier: Rarity::Set.to_string(),
+        hp_bonus_max: get("hpBonus") as i32,
+        add: Point::new(get("str"), get("dex"), get("int"), get("def"), get("agi")),
+        def: Point::new(
+            get("eDef"),
+            get("tDef"),
+            get("wDef"),
+            get("fDef"),
+            get("aDef"),
+        ),
+        def_pct_max: Point::new(
+            get("eDefPct"),
+            get("tDefPct"),
+            get("wDefPct"),
+            get("fDefPct"),
+            get("aDefPct"),
+        ),
+        dam_pct_max: dam("DamPct"),
+        common_stat_max: CommonStat::new(
+            get("hprRaw"),
+            get("hprPct"),
+            get("mr"),
+            get("ls"),
+            get("ms"),
+            get("spd"),
+            get("sdRaw"),
+            get("sdPct"),
+        ),
+        sec_stat_max: SecStat::new(get("xpb"), get("lb"), get("healPct")),
+        melee_stat_max: MeleeStat {
+            md_pct: get("mdPct"),
+            md_raw: get("mdRaw"),
+            md_pct_s: dam("MdPct"),
+            md_raw_s: dam("MdRaw"),
+            r_md_pct: get("rMdPct"),
+            r_md_raw: get("rMdRaw"),
+            atk_tier: get("atkTier"),
+        },
+        damage_stat_max: DamageStat {
+            sd_pct_s: dam("SdPct"),
+            sd_raw_s: dam("SdRaw"),
+            r_sd_pct: get("rSdPct"),
+            r_sd_raw: get("rSdRaw"),
+            dam_pct: get("damPct"),
+            dam_raw: get("damRaw"),
+            dam_raw_s: dam("DamRaw"),
+            r_dam_pct: get("rDamPct"),
+            r_dam_raw: get("rDamRaw"),
+            crit_dam_pct: get("critDamPct"),
+        },
+        spell_cost_max: SpellCost::new(
+            [get("spPct1"), get("spPct2"), get("spPct3"), get("spPct4")],
+            [get("spRaw1"), get("spRaw2"), get("spRaw3"), get("spRaw4")],
+        ),
+        fix_id: true,
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bonuses_works() {
+        let sets: SetsMap = serde_json::from_str(
+            r#"{
+                "Adventurer's": {
+                    "items": ["Adventurer's Cap", "Adventurer's Boots", "Adventurer's Pants"],
+                    "bonuses": [{}, {"sdPct": 4, "hpBonus": 15}, {"sdPct": 8, "str": 3, "eDamPct": 5, "fDef": -20}]
+                }
+            }"#,
+        )
+        .unwrap();
+        let sets = Sets::new(&sets);
+        let item = |name: &str| Apparel {
+            name: name.to_string(),
+            ..Default::default()
+        };
+        let cap = item("Adventurer's Cap");
+        let boots = item("Adventurer's Boots");
+        let pants = item("Adventurer's Pants");
+        let other = item("Other");
+
+        assert!(sets
+            .bonuses(&[&cap, &other])
+            .iter()
+            .all(|v| v.add.sum() == 0));
+
+        let bonuses = sets.bonuses(&[&cap, &boots, &other]);
+        assert_eq!(bonuses.len(), 1);
+        assert_eq!(bonuses[0].common_stat_max.sd_pct(), 4);
+        assert_eq!(bonuses[0].hp_bonus_max, 15);
+
+        let bonuses = sets.bonuses(&[&cap, &boots, &pants]);
+        assert_eq!(bonuses[0].common_stat_max.sd_pct(), 8);
+
+        assert_eq!(sets.possible_bonuses(&[&cap, &pants])[0].len(), 3);
+        assert!(sets.possible_bonuses(&[&other]).is_empty());
+        assert!(sets.contains("Adventurer's Cap"));
+        assert!(!sets.contains("Other"));
+        assert_eq!(bonuses[0].add.e(), 3);
+        assert_eq!(bonuses[0].dam_pct_max.e(), 5);
+        assert_eq!(bonuses[0].def.f(), -20);
+
+        let set = sets.set_of("Adventurer's Cap").unwrap();
+        assert_eq!(sets.set_of("Other"), None);
+        assert_eq!(sets.bonus_add(set, 0), Point::default());
+        assert_eq!(sets.bonus_add(set, 2), Point::default());
+        assert_eq!(sets.bonus_add(set, 3), Point::new(3, 0, 0, 0, 0));
+        assert_eq!(sets.bonus_add(set, 4), Point::new(3, 0, 0, 0, 0));
+    }
+}
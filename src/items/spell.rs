@@ -1,3 +1,5 @@
+use std::collections::{HashMap, HashSet};
+
 use super::*;
 
 #[derive(Debug, Clone, Default, PartialEq)]
@@ -6,6 +8,13 @@ pub struct Spell {
     pub id: i32,
     pub cost: i32,
     pub parts: Vec<DamagePart>,
+    pub heals: Vec<HealPart>,
+    /// totals are calculated in order, a total can use the totals before it
+    pub totals: Vec<TotalPart>,
+    /// "scaling": "melee", the spell uses main attack stats instead of spell damage stats
+    pub melee: bool,
+    /// "use_atkspd", the weapon attack speed multiplies the damage
+    pub use_atk_spd: bool,
 }
 
 impl Spell {
@@ -15,7 +24,83 @@ impl Spell {
             id,
             cost,
             parts,
+            heals: Vec::new(),
+            totals: Vec::new(),
+            melee: false,
+            use_atk_spd: true,
+        }
+    }
+    /// one of the 4 spells cast with clicks, they have a mana cost and spell cost identifications
+    pub fn is_base_spell(&self) -> bool {
+        (1..=4).contains(&self.id)
+    }
+    /// add a part to the spell, a part with the same name is merged (or overwritten)
+    /// https://github.com/hppeng-wynn/hppeng-wynn.github.io/blob/a30a0aa1a8b9f857ae66d31e67bdc5df0a4d0cfc/js/builder/atree.js#L941
+    pub fn merge_part(&mut self, behavior: Behavior, part: &Part) {
+        match part {
+            Part::Damage { name, multipliers } => {
+                let dam_convert = DamagesConvert::from_slice_i32(multipliers);
+                match self.parts.iter_mut().find(|v| &v.name == name) {
+                    Some(v) if behavior == Behavior::Overwrite => v.dam_convert = dam_convert,
+                    Some(v) => v.dam_convert += &dam_convert,
+                    None => self.parts.push(DamagePart::new(name.clone(), dam_convert)),
+                }
+            }
+            Part::Heal { name, power } => match self.heals.iter_mut().find(|v| &v.name == name) {
+                Some(v) if behavior == Behavior::Overwrite => v.power = *power,
+                Some(v) => v.power += power,
+                None => self.heals.push(HealPart::new(name.clone(), *power)),
+            },
+            Part::Total { name, hits } => match self.totals.iter_mut().find(|v| &v.name == name) {
+                Some(v) if behavior == Behavior::Overwrite => v.hits = hits.clone(),
+                Some(v) => {
+                    for (part_name, count) in hits {
+                        match (v.hits.get_mut(part_name), count) {
+                            (
+                                Some(NumberOrString::Number(value)),
+                                NumberOrString::Number(count),
+                            ) => *value += count,
+                            _ => {
+                                v.hits.insert(part_name.clone(), count.clone());
+                            }
+                        }
+                    }
+                }
+                None => self.totals.push(TotalPart::new(name.clone(), hits.clone())),
+            },
+        }
+    }
+    /// replace the property references ("<ability id>.<property name>") in the hits of totals
+    pub fn resolve_hits(&mut self, props: &HashMap<String, f64>) {
+        for total in &mut self.totals {
+            for count in total.hits.values_mut() {
+                if let NumberOrString::Text(prop) = count {
+                    *count =
+                        NumberOrString::Number(props.get(prop.as_str()).copied().unwrap_or(0.0));
+                }
+            }
+        }
+    }
+    pub fn part_names(&self) -> impl Iterator<Item = &String> {
+        self.parts
+            .iter()
+            .map(|v| &v.name)
+            .chain(self.heals.iter().map(|v| &v.name))
+            .chain(self.totals.iter().map(|v| &v.name))
+    }
+    /// keep the named parts and the parts used by the named totals, return false if nothing is kept
+    pub fn retain_parts(&mut self, names: &HashSet<&str>) -> bool {
+        let mut keep: HashSet<String> = names.iter().map(|v| v.to_string()).collect();
+        // totals only use the parts and totals before them
+        for total in self.totals.iter().rev() {
+            if keep.contains(&total.name) {
+                keep.extend(total.hits.keys().cloned());
+            }
         }
+        self.parts.retain(|v| keep.contains(&v.name));
+        self.heals.retain(|v| keep.contains(&v.name));
+        self.totals.retain(|v| keep.contains(&v.name));
+        !(self.parts.is_empty() && self.heals.is_empty() && self.totals.is_empty())
     }
 }
 
@@ -29,3 +114,92 @@ impl DamagePart {
         Self { name, dam_convert }
     }
 }
+
+/// heal of the spell, `power` is a ratio of the max hp
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HealPart {
+    pub name: String,
+    pub power: f64,
+}
+impl HealPart {
+    pub fn new(name: String, power: f64) -> Self {
+        Self { name, power }
+    }
+}
+
+/// sum of other parts of the spell, e.g. "Total Damage": {"Single Hit": 2}
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TotalPart {
+    pub name: String,
+    /// part name to the number of hits, see `Spell::resolve_hits`
+    pub hits: HashMap<String, NumberOrString>,
+}
+impl TotalPart {
+    pub fn new(name: String, hits: HashMap<String, NumberOrString>) -> Self {
+        Self { name, hits }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merge_part_works() {
+        let mut spell = Spell::new("Bash".to_string(), 1, 0, Vec::new());
+        let total = |count: f64| Part::Total {
+            name: "Total Damage".to_string(),
+            hits: HashMap::from([("Single Hit".to_string(), NumberOrString::Number(count))]),
+        };
+        let heal = |power: f64| Part::Heal {
+            name: "Heal".to_string(),
+            power,
+        };
+        spell.merge_part(Behavior::Merge, &total(1.0));
+        spell.merge_part(Behavior::Merge, &total(1.0));
+        spell.merge_part(Behavior::Merge, &heal(0.25));
+        spell.merge_part(Behavior::Merge, &heal(0.5));
+        assert_eq!(
+            spell.totals,
+            vec![TotalPart::new(
+                "Total Damage".to_string(),
+                HashMap::from([("Single Hit".to_string(), NumberOrString::Number(2.0))])
+            )]
+        );
+        assert_eq!(spell.heals, vec![HealPart::new("Heal".to_string(), 0.75)]);
+
+        spell.merge_part(Behavior::Overwrite, &heal(0.3));
+        assert_eq!(spell.heals, vec![HealPart::new("Heal".to_string(), 0.3)]);
+    }
+    #[test]
+    fn retain_parts_works() {
+        let mut spell = Spell::new(
+            "Bash".to_string(),
+            1,
+            0,
+            vec![
+                DamagePart::new("Single Hit".to_string(), Default::default()),
+                DamagePart::new("Explosion".to_string(), Default::default()),
+            ],
+        );
+        spell.totals.push(TotalPart::new(
+            "Total Damage".to_string(),
+            HashMap::from([(
+                "Single Hit".to_string(),
+                NumberOrString::Text("0.hits".to_string()),
+            )]),
+        ));
+        spell.resolve_hits(&HashMap::from([("0.hits".to_string(), 2.0)]));
+        assert_eq!(
+            spell.totals[0].hits["Single Hit"],
+            NumberOrString::Number(2.0)
+        );
+
+        assert!(spell.retain_parts(&HashSet::from(["Total Damage"])));
+        assert_eq!(
+            spell.part_names().collect::<Vec<_>>(),
+            ["Single Hit", "Total Damage"]
+        );
+        assert!(!spell.retain_parts(&HashSet::from(["Heal"])));
+    }
+}
@@ -0,0 +1,74 @@
+use std::simd::i16x8;
+
+use super::*;
+
+/// spell cost identifications, negative values reduce the cost
+/// 0:spPct1 1:spPct2 2:spPct3 3:spPct4 4:spRaw1 5:spRaw2 6:spRaw3 7:spRaw4
+#[derive(Clone, Debug, Default)]
+pub struct SpellCost {
+    pub inner: i16x8,
+}
+
+impl SpellCost {
+    pub fn new(sp_pct: [i16; 4], sp_raw: [i16; 4]) -> Self {
+        let [p1, p2, p3, p4] = sp_pct;
+        let [r1, r2, r3, r4] = sp_raw;
+        Self {
+            inner: i16x8::from([p1, p2, p3, p4, r1, r2, r3, r4]),
+        }
+    }
+    pub fn sum_max_stats(apparels: &[&Apparel], weapon: &Weapon) -> Self {
+        let mut total: i16x8 = weapon.spell_cost_max.inner;
+
+        for item in apparels {
+            total += item.spell_cost_max.inner;
+        }
+        Self { inner: total }
+    }
+    /// spPct of the spell, 0 if the spell is not one of the 4 base spells
+    pub fn sp_pct(&self, spell_id: i32) -> i16 {
+        match spell_id {
+            1..=4 => self.inner[spell_id as usize - 1],
+            _ => 0,
+        }
+    }
+    /// spRaw of the spell, 0 if the spell is not one of the 4 base spells
+    pub fn sp_raw(&self, spell_id: i32) -> i16 {
+        match spell_id {
+            1..=4 => self.inner[spell_id as usize + 3],
+            _ => 0,
+        }
+    }
+}
+
+impl std::fmt::Display for SpellCost {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "(sp_pct:[{}, {}, {}, {}], sp_raw:[{}, {}, {}, {}])",
+            self.inner[0],
+            self.inner[1],
+            self.inner[2],
+            self.inner[3],
+            self.inner[4],
+            self.inner[5],
+            self.inner[6],
+            self.inner[7],
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sp_pct_and_sp_raw_works() {
+        let cost = SpellCost::new([-10, 0, 0, 5], [0, -4, 0, 0]);
+        assert_eq!(cost.sp_pct(1), -10);
+        assert_eq!(cost.sp_pct(4), 5);
+        assert_eq!(cost.sp_raw(2), -4);
+        assert_eq!(cost.sp_pct(0), 0);
+        assert_eq!(cost.sp_raw(5), 0);
+    }
+}
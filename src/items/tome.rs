@@ -0,0 +1,137 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+
+use super::*;
+
+#[derive(Clone, Debug, Default)]
+pub struct Tome {
+    pub name: String,
+    pub tier: String,
+    pub r#type: TomeType,
+    pub lvl: i32,
+    pub hp_bonus_max: i32,
+    pub add: Point,
+    pub def: Point,
+
+    pub def_pct_max: Point,
+    pub dam_pct_max: Dam,
+
+    pub common_stat_max: CommonStat,
+    pub sec_stat_max: SecStat,
+    pub melee_stat_max: MeleeStat,
+    pub damage_stat_max: DamageStat,
+    pub spell_cost_max: SpellCost,
+}
+
+impl TryFrom<&WApiItem> for Tome {
+    type Error = String;
+
+    fn try_from(value: &WApiItem) -> Result<Self, Self::Error> {
+        Ok(Tome {
+            name: value.internal_name.clone(),
+            tier: value
+                .rarity
+                .clone()
+                .map(|v| v.to_string())
+                .unwrap_or_default(),
+            r#type: value.tome_type.clone().unwrap_or(TomeType::Other),
+            lvl: value.requirements.level,
+            hp_bonus_max: value.hp_bonus_max(),
+            add: value.add()?,
+            def: value.def(),
+            def_pct_max: value.def_pct_max(),
+            dam_pct_max: value.dam_pct_max(),
+            common_stat_max: value.common_stat_max(),
+            sec_stat_max: value.sec_stat_max(),
+            melee_stat_max: value.melee_stat_max(),
+            damage_stat_max: value.damage_stat_max(),
+            spell_cost_max: value.spell_cost_max(),
+        })
+    }
+}
+
+/// sum of the stats of all equipped tomes, tomes don't roll so only the max value is used
+#[derive(Clone, Debug, Default)]
+pub struct TomeStat {
+    pub hp: i32,
+    pub add: Point,
+    pub def: Point,
+    pub def_pct: Point,
+    pub dam_pct: Dam,
+    pub common_stat: CommonStat,
+    pub sec_stat: SecStat,
+    pub melee_stat: MeleeStat,
+    pub damage_stat: DamageStat,
+    pub spell_cost: SpellCost,
+}
+
+impl TomeStat {
+    pub fn sum(tomes: &[&Tome]) -> Self {
+        let mut total = TomeStat::default();
+        for tome in tomes {
+            total.hp += tome.hp_bonus_max;
+            total.add += &tome.add;
+            total.def += &tome.def;
+            total.def_pct += &tome.def_pct_max;
+            total.dam_pct += &tome.dam_pct_max;
+            total.common_stat += &tome.common_stat_max;
+            total.sec_stat.inner += tome.sec_stat_max.inner;
+            total.melee_stat += &tome.melee_stat_max;
+            total.damage_stat += &tome.damage_stat_max;
+            total.spell_cost.inner += tome.spell_cost_max.inner;
+        }
+        total
+    }
+}
+
+/// the entries of hppeng's tomes.json that are needed for the tome id map
+/// https://github.com/hppeng-wynn/hppeng-wynn.github.io/blob/HEAD/data/tomes.json
+#[derive(Deserialize)]
+struct HppengTomes {
+    tomes: Vec<HppengTome>,
+}
+#[derive(Deserialize)]
+struct HppengTome {
+    name: String,
+    id: i32,
+}
+
+/// tome name to hppeng tome id, read from the contents of hppeng's tomes.json
+pub fn hppeng_tome_ids(contents: &str) -> Result<HashMap<String, i32>, String> {
+    let tomes: HppengTomes = serde_json::from_str(contents)
+        .map_err(|e| format!("Could not parse hppeng tomes file: {}", e))?;
+    Ok(tomes
+        .tomes
+        .into_iter()
+        .map(|tome| (tome.name, tome.id))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hppeng_tome_ids_works() {
+        let ids = hppeng_tome_ids(
+            r#"{"tomes": [{"name": "Guild Tome", "id": 43, "type": "guildTome", "lvl": 60}]}"#,
+        )
+        .unwrap();
+        assert_eq!(ids, HashMap::from([("Guild Tome".to_string(), 43)]));
+        assert!(hppeng_tome_ids(r#"[{"name": "Guild Tome", "id": 43}]"#).is_err());
+    }
+
+    #[test]
+    fn sum_works() {
+        let tome = Tome {
+            name: String::from("Tome"),
+            r#type: TomeType::ArmourTome,
+            hp_bonus_max: 120,
+            add: Point::new(1, 0, 0, 0, 0),
+            ..Default::default()
+        };
+        let stat = TomeStat::sum(&[&tome, &tome]);
+        assert_eq!(stat.hp, 240);
+        assert_eq!(stat.add, Point::new(2, 0, 0, 0, 0));
+    }
+}
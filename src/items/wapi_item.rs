@@ -199,6 +199,7 @@ pub struct Identifications {
     pub air_defence: Option<StatOrInt>,
     pub air_main_attack_damage: Option<StatOrInt>,
     pub air_spell_damage: Option<StatOrInt>,
+    pub critical_damage_bonus: Option<StatOrInt>,
     pub damage: Option<StatOrInt>,
     pub earth_damage: Option<StatOrInt>,
     pub earth_defence: Option<StatOrInt>,
@@ -229,6 +230,7 @@ pub struct Identifications {
     pub mana_steal: Option<StatOrInt>,
     pub neutral_damage: Option<StatOrInt>,
     pub neutral_main_attack_damage: Option<StatOrInt>,
+    pub neutral_spell_damage: Option<StatOrInt>,
     pub poison: Option<StatOrInt>,
     #[serde(rename = "raw1stSpellCost")]
     pub raw_1st_spell_cost: Option<StatOrInt>,
@@ -267,6 +269,7 @@ pub struct Identifications {
     pub raw_thunder_main_attack_damage: Option<StatOrInt>,
     pub raw_thunder_spell_damage: Option<StatOrInt>,
     pub raw_water_damage: Option<StatOrInt>,
+    pub raw_water_main_attack_damage: Option<StatOrInt>,
     pub raw_water_spell_damage: Option<StatOrInt>,
     pub reflection: Option<StatOrInt>,
     #[serde(rename = "2ndSpellCost")]
@@ -286,6 +289,7 @@ pub struct Identifications {
     pub walk_speed: Option<StatOrInt>,
     pub water_damage: Option<StatOrInt>,
     pub water_defence: Option<StatOrInt>,
+    pub water_main_attack_damage: Option<StatOrInt>,
     pub water_spell_damage: Option<StatOrInt>,
     pub weaken_enemy: Option<StatOrInt>,
     pub xp_bonus: Option<StatOrInt>,
@@ -390,6 +394,30 @@ pub enum ItemType {
     Tome,
 }
 
+#[derive(Clone, PartialEq, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TomeType {
+    WeaponTome,
+    ArmourTome,
+    GuildTome,
+    LootrunTome,
+    #[default]
+    #[serde(other)]
+    Other,
+}
+
+impl Display for TomeType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TomeType::WeaponTome => write!(f, "weapon_tome"),
+            TomeType::ArmourTome => write!(f, "armour_tome"),
+            TomeType::GuildTome => write!(f, "guild_tome"),
+            TomeType::LootrunTome => write!(f, "lootrun_tome"),
+            TomeType::Other => write!(f, "other"),
+        }
+    }
+}
+
 #[derive(Clone, PartialEq, Debug, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct WApiItem {
@@ -413,6 +441,7 @@ pub struct WApiItem {
     pub rarity: Option<Rarity>,
     pub requirements: Requirements,
     pub restrictions: Option<Restrictions>,
+    pub tome_type: Option<TomeType>,
     pub r#type: Option<ItemType>,
     pub weapon_type: Option<WeaponType>,
 }
@@ -429,6 +458,12 @@ impl WApiItem {
         };
         Ok(item_type)
     }
+    pub fn major_ids(&self) -> Vec<String> {
+        self.major_ids
+            .as_ref()
+            .map(|major_ids| major_ids.keys().cloned().collect())
+            .unwrap_or_default()
+    }
     pub fn hp_bonus_max(&self) -> i32 {
         self.identifications
             .as_ref()
@@ -569,6 +604,7 @@ impl WApiItem {
         SecStat::new(
             ids.xp_bonus.map(|v| v.max()).unwrap_or(0) as i16,
             ids.loot_bonus.map(|v| v.max()).unwrap_or(0) as i16,
+            ids.healing_efficiency.map(|v| v.max()).unwrap_or(0) as i16,
         )
     }
     pub fn sec_stat_min(&self) -> SecStat {
@@ -578,8 +614,215 @@ impl WApiItem {
         SecStat::new(
             ids.xp_bonus.map(|v| v.min()).unwrap_or(0) as i16,
             ids.loot_bonus.map(|v| v.min()).unwrap_or(0) as i16,
+            ids.healing_efficiency.map(|v| v.min()).unwrap_or(0) as i16,
+        )
+    }
+    pub fn damage_stat_max(&self) -> DamageStat {
+        let Some(ids) = self.identifications.as_ref() else {
+            return DamageStat::default();
+        };
+        let max = |v: Option<StatOrInt>| v.map(|v| v.max()).unwrap_or(0) as i16;
+        DamageStat {
+            sd_pct_s: Dam::new(
+                max(ids.neutral_spell_damage),
+                max(ids.earth_spell_damage),
+                max(ids.thunder_spell_damage),
+                max(ids.water_spell_damage),
+                max(ids.fire_spell_damage),
+                max(ids.air_spell_damage),
+            ),
+            sd_raw_s: Dam::new(
+                max(ids.raw_neutral_spell_damage),
+                max(ids.raw_earth_spell_damage),
+                max(ids.raw_thunder_spell_damage),
+                max(ids.raw_water_spell_damage),
+                max(ids.raw_fire_spell_damage),
+                max(ids.raw_air_spell_damage),
+            ),
+            r_sd_pct: max(ids.elemental_spell_damage),
+            r_sd_raw: max(ids.raw_elemental_spell_damage),
+            dam_pct: max(ids.damage),
+            dam_raw: max(ids.raw_damage),
+            dam_raw_s: Dam::new(
+                max(ids.raw_neutral_damage),
+                max(ids.raw_earth_damage),
+                max(ids.raw_thunder_damage),
+                max(ids.raw_water_damage),
+                max(ids.raw_fire_damage),
+                max(ids.raw_air_damage),
+            ),
+            r_dam_pct: max(ids.elemental_damage),
+            r_dam_raw: max(ids.raw_elemental_damage),
+            crit_dam_pct: max(ids.critical_damage_bonus),
+        }
+    }
+    pub fn damage_stat_min(&self) -> DamageStat {
+        let Some(ids) = self.identifications.as_ref() else {
+            return DamageStat::default();
+        };
+        let min = |v: Option<StatOrInt>| v.map(|v| v.min()).unwrap_or(0) as i16;
+        DamageStat {
+            sd_pct_s: Dam::new(
+                min(ids.neutral_spell_damage),
+                min(ids.earth_spell_damage),
+                min(ids.thunder_spell_damage),
+                min(ids.water_spell_damage),
+                min(ids.fire_spell_damage),
+                min(ids.air_spell_damage),
+            ),
+            sd_raw_s: Dam::new(
+                min(ids.raw_neutral_spell_damage),
+                min(ids.raw_earth_spell_damage),
+                min(ids.raw_thunder_spell_damage),
+                min(ids.raw_water_spell_damage),
+                min(ids.raw_fire_spell_damage),
+                min(ids.raw_air_spell_damage),
+            ),
+            r_sd_pct: min(ids.elemental_spell_damage),
+            r_sd_raw: min(ids.raw_elemental_spell_damage),
+            dam_pct: min(ids.damage),
+            dam_raw: min(ids.raw_damage),
+            dam_raw_s: Dam::new(
+                min(ids.raw_neutral_damage),
+                min(ids.raw_earth_damage),
+                min(ids.raw_thunder_damage),
+                min(ids.raw_water_damage),
+                min(ids.raw_fire_damage),
+                min(ids.raw_air_damage),
+            ),
+            r_dam_pct: min(ids.elemental_damage),
+            r_dam_raw: min(ids.raw_elemental_damage),
+            crit_dam_pct: min(ids.critical_damage_bonus),
+        }
+    }
+    pub fn spell_cost_max(&self) -> SpellCost {
+        let Some(ids) = self.identifications.as_ref() else {
+            return SpellCost::default();
+        };
+        let max = |v: Option<StatOrInt>| v.map(|v| v.max()).unwrap_or(0) as i16;
+        SpellCost::new(
+            [
+                max(ids.first_spell_cost),
+                max(ids.second_spell_cost),
+                max(ids.third_spell_cost),
+                max(ids.fourth_spell_cost),
+            ],
+            [
+                max(ids.raw_1st_spell_cost),
+                max(ids.raw_2nd_spell_cost),
+                max(ids.raw_3rd_spell_cost),
+                max(ids.raw_4th_spell_cost),
+            ],
+        )
+    }
+    pub fn spell_cost_min(&self) -> SpellCost {
+        let Some(ids) = self.identifications.as_ref() else {
+            return SpellCost::default();
+        };
+        let min = |v: Option<StatOrInt>| v.map(|v| v.min()).unwrap_or(0) as i16;
+        SpellCost::new(
+            [
+                min(ids.first_spell_cost),
+                min(ids.second_spell_cost),
+                min(ids.third_spell_cost),
+                min(ids.fourth_spell_cost),
+            ],
+            [
+                min(ids.raw_1st_spell_cost),
+                min(ids.raw_2nd_spell_cost),
+                min(ids.raw_3rd_spell_cost),
+                min(ids.raw_4th_spell_cost),
+            ],
         )
     }
+    pub fn melee_stat_max(&self) -> MeleeStat {
+        let Some(ids) = self.identifications.as_ref() else {
+            return MeleeStat::default();
+        };
+        MeleeStat {
+            md_pct: ids.main_attack_damage.map(|v| v.max()).unwrap_or(0) as i16,
+            md_raw: ids.raw_main_attack_damage.map(|v| v.max()).unwrap_or(0) as i16,
+            md_pct_s: Dam::new(
+                ids.neutral_main_attack_damage.map(|v| v.max()).unwrap_or(0) as i16,
+                ids.earth_main_attack_damage.map(|v| v.max()).unwrap_or(0) as i16,
+                ids.thunder_main_attack_damage.map(|v| v.max()).unwrap_or(0) as i16,
+                ids.water_main_attack_damage.map(|v| v.max()).unwrap_or(0) as i16,
+                ids.fire_main_attack_damage.map(|v| v.max()).unwrap_or(0) as i16,
+                ids.air_main_attack_damage.map(|v| v.max()).unwrap_or(0) as i16,
+            ),
+            md_raw_s: Dam::new(
+                ids.raw_neutral_main_attack_damage
+                    .map(|v| v.max())
+                    .unwrap_or(0) as i16,
+                ids.raw_earth_main_attack_damage
+                    .map(|v| v.max())
+                    .unwrap_or(0) as i16,
+                ids.raw_thunder_main_attack_damage
+                    .map(|v| v.max())
+                    .unwrap_or(0) as i16,
+                ids.raw_water_main_attack_damage
+                    .map(|v| v.max())
+                    .unwrap_or(0) as i16,
+                ids.raw_fire_main_attack_damage
+                    .map(|v| v.max())
+                    .unwrap_or(0) as i16,
+                ids.raw_air_main_attack_damage.map(|v| v.max()).unwrap_or(0) as i16,
+            ),
+            r_md_pct: ids
+                .elemental_main_attack_damage
+                .map(|v| v.max())
+                .unwrap_or(0) as i16,
+            r_md_raw: ids
+                .raw_elemental_main_attack_damage
+                .map(|v| v.max())
+                .unwrap_or(0) as i16,
+            atk_tier: ids.raw_attack_speed.map(|v| v.max()).unwrap_or(0) as i16,
+        }
+    }
+    pub fn melee_stat_min(&self) -> MeleeStat {
+        let Some(ids) = self.identifications.as_ref() else {
+            return MeleeStat::default();
+        };
+        MeleeStat {
+            md_pct: ids.main_attack_damage.map(|v| v.min()).unwrap_or(0) as i16,
+            md_raw: ids.raw_main_attack_damage.map(|v| v.min()).unwrap_or(0) as i16,
+            md_pct_s: Dam::new(
+                ids.neutral_main_attack_damage.map(|v| v.min()).unwrap_or(0) as i16,
+                ids.earth_main_attack_damage.map(|v| v.min()).unwrap_or(0) as i16,
+                ids.thunder_main_attack_damage.map(|v| v.min()).unwrap_or(0) as i16,
+                ids.water_main_attack_damage.map(|v| v.min()).unwrap_or(0) as i16,
+                ids.fire_main_attack_damage.map(|v| v.min()).unwrap_or(0) as i16,
+                ids.air_main_attack_damage.map(|v| v.min()).unwrap_or(0) as i16,
+            ),
+            md_raw_s: Dam::new(
+                ids.raw_neutral_main_attack_damage
+                    .map(|v| v.min())
+                    .unwrap_or(0) as i16,
+                ids.raw_earth_main_attack_damage
+                    .map(|v| v.min())
+                    .unwrap_or(0) as i16,
+                ids.raw_thunder_main_attack_damage
+                    .map(|v| v.min())
+                    .unwrap_or(0) as i16,
+                ids.raw_water_main_attack_damage
+                    .map(|v| v.min())
+                    .unwrap_or(0) as i16,
+                ids.raw_fire_main_attack_damage
+                    .map(|v| v.min())
+                    .unwrap_or(0) as i16,
+                ids.raw_air_main_attack_damage.map(|v| v.min()).unwrap_or(0) as i16,
+            ),
+            r_md_pct: ids
+                .elemental_main_attack_damage
+                .map(|v| v.min())
+                .unwrap_or(0) as i16,
+            r_md_raw: ids
+                .raw_elemental_main_attack_damage
+                .map(|v| v.min())
+                .unwrap_or(0) as i16,
+            atk_tier: ids.raw_attack_speed.map(|v| v.min()).unwrap_or(0) as i16,
+        }
+    }
     pub fn damages(&self) -> Damages {
         let Some(base) = self.base.as_ref() else {
             return Damages::default();
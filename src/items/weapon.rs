@@ -17,6 +17,7 @@ pub struct Weapon {
     // "aDam_"
     pub damage: Damages,
     pub atk_spd: AtkSpd,
+    pub slots: i32,
 
     pub req: Point,
     pub add: Point,
@@ -28,9 +29,17 @@ pub struct Weapon {
     pub def_pct_min: Point,
     pub dam_pct_max: Dam,
     pub dam_pct_min: Dam,
+    pub melee_stat_max: MeleeStat,
+    pub melee_stat_min: MeleeStat,
+    pub damage_stat_max: DamageStat,
+    pub damage_stat_min: DamageStat,
+    pub spell_cost_max: SpellCost,
+    pub spell_cost_min: SpellCost,
 
     pub damage_present: Mask,
 
+    pub major_ids: Vec<String>,
+
     pub fix_id: bool,
 }
 
@@ -45,6 +54,7 @@ impl TryFrom<&WApiItem> for Weapon {
             hp_bonus_min: value.hp_bonus_min(),
             damage: value.damages(),
             atk_spd: value.attack_speed().unwrap(),
+            slots: value.powder_slots.unwrap_or(0),
             req: value.req(),
             add: value.add()?,
             common_stat_max: value.common_stat_max(),
@@ -55,8 +65,25 @@ impl TryFrom<&WApiItem> for Weapon {
             def_pct_min: value.def_pct_min(),
             dam_pct_max: value.dam_pct_max(),
             dam_pct_min: value.dam_pct_min(),
+            melee_stat_max: value.melee_stat_max(),
+            melee_stat_min: value.melee_stat_min(),
+            damage_stat_max: value.damage_stat_max(),
+            damage_stat_min: value.damage_stat_min(),
+            spell_cost_max: value.spell_cost_max(),
+            spell_cost_min: value.spell_cost_min(),
             damage_present: value.damage_present(),
+            major_ids: value.major_ids(),
             fix_id: value.identified.unwrap_or(false),
         })
     }
 }
+impl Weapon {
+    /// apply powders to the weapon base damage, elements gained from powders become present
+    ///
+    /// the powders beyond the powder slots of the weapon are dropped
+    pub fn apply_powders(&mut self, powders: &[Powder]) {
+        let slots = (self.slots.max(0) as usize).min(powders.len());
+        self.damage.apply_powders(&powders[..slots]);
+        self.damage_present = self.damage_present.clone() | self.damage.present();
+    }
+}
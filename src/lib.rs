@@ -1,5 +1,4 @@
 #![feature(portable_simd)]
-#![feature(maybe_uninit_uninit_array)]
 #![feature(maybe_uninit_array_assume_init)]
 pub mod calculate;
 pub mod config;
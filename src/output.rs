@@ -0,0 +1,439 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+use itertools::Itertools;
+use serde_json::{json, Value};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+use crate::*;
+
+/// the slots of the items of a build, in the order of `Build::items` and the weapon
+pub const SLOTS: [&str; 9] = [
+    "ring_1",
+    "ring_2",
+    "helmet",
+    "chest_plate",
+    "leggings",
+    "boots",
+    "bracelet",
+    "necklace",
+    "weapon",
+];
+
+/// the columns of the skill points assigned, the original skill points and the defenses
+const POINT_COLUMNS: [[&str; 5]; 3] = [
+    [
+        "earth_assign",
+        "thunder_assign",
+        "water_assign",
+        "fire_assign",
+        "air_assign",
+    ],
+    [
+        "earth_original",
+        "thunder_original",
+        "water_original",
+        "fire_original",
+        "air_original",
+    ],
+    [
+        "earth_def",
+        "thunder_def",
+        "water_def",
+        "fire_def",
+        "air_def",
+    ],
+];
+
+/// builds sent to the `Writer` are written in batches of at most this many rows, one transaction
+/// per batch for the database
+const BATCH_SIZE: usize = 1000;
+/// the search threads wait when this many rows are not written yet
+const CHANNEL_SIZE: usize = 10000;
+
+/// a valid build, owned so it can be sent to the `Writer`
+#[derive(Default)]
+pub struct Build {
+    pub url: String,
+    pub status: Status,
+    /// names of the items of the combination
+    pub items: [String; 8],
+    pub weapon: String,
+}
+impl Build {
+    pub fn new(url: String, status: Status, combination: &[&Apparel; 8], weapon: &Weapon) -> Self {
+        Self {
+            url,
+            status,
+            items: combination.map(|item| item.name.clone()),
+            weapon: weapon.name.clone(),
+        }
+    }
+    /// (name, value) of the url, the items and the stats, named as the columns of the `build` table
+    fn columns(&self) -> Vec<(&'static str, Value)> {
+        let stat = &self.status;
+        let mut columns = vec![("url", json!(self.url))];
+        let items = self.items.iter().chain([&self.weapon]);
+        columns.extend(
+            SLOTS
+                .iter()
+                .zip(items)
+                .map(|(slot, name)| (*slot, json!(name))),
+        );
+        let points = [
+            &stat.skill_point.assign,
+            &stat.skill_point.original,
+            &stat.max_def,
+        ];
+        for (names, point) in POINT_COLUMNS.iter().zip(points) {
+            let values = [point.e(), point.t(), point.w(), point.f(), point.a()];
+            columns.extend(names.iter().zip(values).map(|(name, v)| (*name, json!(v))));
+        }
+        columns.extend([
+            ("max_mr", json!(stat.max_common_stat.mr())),
+            ("max_ms", json!(stat.max_common_stat.ms())),
+            ("max_spd", json!(stat.max_common_stat.spd())),
+            ("max_ls", json!(stat.max_common_stat.ls())),
+            ("max_hpr_raw", json!(stat.max_common_stat.hpr_raw())),
+            ("max_hpr_pct", json!(stat.max_common_stat.hpr_pct())),
+            ("max_sd_raw", json!(stat.max_common_stat.sd_raw())),
+            ("max_sd_pct", json!(stat.max_common_stat.sd_pct())),
+            ("max_ehp", json!(stat.max_ehp)),
+            ("max_hp", json!(stat.max_hp)),
+            ("max_hpr", json!(stat.max_hpr)),
+            ("max_neutral_dam_pct", json!(stat.max_dam_pct.n())),
+            ("max_earth_dam_pct", json!(stat.max_dam_pct.e())),
+            ("max_thunder_dam_pct", json!(stat.max_dam_pct.t())),
+            ("max_water_dam_pct", json!(stat.max_dam_pct.w())),
+            ("max_fire_dam_pct", json!(stat.max_dam_pct.f())),
+            ("max_air_dam_pct", json!(stat.max_dam_pct.a())),
+            ("max_exp_bonus", json!(stat.max_sec_stat.exp_bonus())),
+            ("max_loot_bonus", json!(stat.max_sec_stat.loot_bonus())),
+            ("melee_dps", json!(stat.melee_dps)),
+            ("mana_sustain", json!(stat.mana_sustain)),
+        ]);
+        columns
+    }
+}
+
+pub enum Row {
+    Build(Box<Build>),
+    /// (run, segments), the segments of the run whose builds are all written
+    Checkpoint(String, Vec<usize>),
+}
+
+/// where the `Writer` writes the builds, see `Output`
+pub enum Sink {
+    /// the `build`, `damage` and `spell_cost` tables, and the checkpoints
+    Sqlite {
+        pool: sqlx::SqlitePool,
+        config: Box<Config>,
+        run: db::Run,
+    },
+    Csv {
+        file: BufWriter<File>,
+        /// (spell damages, spell costs) of the spell columns of the file, see `SpellColumns`
+        spells: SpellColumns,
+    },
+    Jsonl(BufWriter<File>),
+}
+impl Sink {
+    /// a csv or json lines sink writing a new file at `path`, the csv header is written at once
+    pub fn file(output: Output, path: &Path, spells: SpellColumns) -> io::Result<Self> {
+        let mut file = BufWriter::new(File::create(path)?);
+        Ok(match output {
+            Output::Sqlite => unreachable!("the sqlite output is not a file"),
+            Output::Csv => {
+                write_csv_header(&mut file, &spells)?;
+                Sink::Csv { file, spells }
+            }
+            Output::Jsonl => Sink::Jsonl(file),
+        })
+    }
+    async fn write(&mut self, rows: &[Row]) {
+        let mut builds = rows.iter().filter_map(|row| match row {
+            Row::Build(build) => Some(build.as_ref()),
+            // the files are written from the start again
+            Row::Checkpoint(..) => None,
+        });
+        let result = match self {
+            Sink::Sqlite { pool, config, run } => {
+                db::write(pool, config, run, rows).await;
+                Ok(())
+            }
+            Sink::Csv { file, spells } => builds
+                .try_for_each(|build| write_csv(file, spells, build))
+                .and_then(|_| file.flush()),
+            Sink::Jsonl(file) => builds
+                .try_for_each(|build| write_jsonl(file, build))
+                .and_then(|_| file.flush()),
+        };
+        if let Err(err) = result {
+            eprintln!("can not write the builds: {}", err);
+        }
+    }
+}
+
+/// (spell damages, spell costs) named as in `Status`, the spell parts of every merged atree of the
+/// run, so the columns do not depend on the spells of the first build
+pub type SpellColumns = (Vec<String>, Vec<String>);
+
+/// write the csv header, the columns of the `build` table then the spell columns
+fn write_csv_header(file: &mut impl Write, (damages, costs): &SpellColumns) -> io::Result<()> {
+    // the names of the columns do not depend on the build
+    let header = Build::default()
+        .columns()
+        .iter()
+        .map(|(name, _)| name.to_string())
+        .chain(damages.iter().flat_map(|name| {
+            ["normal", "crit", "avg"].map(|part| csv_quote(&format!("{} {}", name, part)))
+        }))
+        .chain(
+            costs
+                .iter()
+                .map(|name| csv_quote(&format!("{} cost", name))),
+        )
+        .join(",");
+    writeln!(file, "{}", header)
+}
+
+/// write a csv line of the build, a spell the build does not have is left empty
+fn write_csv(
+    file: &mut impl Write,
+    (damages, costs): &SpellColumns,
+    build: &Build,
+) -> io::Result<()> {
+    let values = build
+        .columns()
+        .iter()
+        .map(|(_, value)| csv_field(value))
+        .chain(damages.iter().flat_map(|name| {
+            let damage = build.status.spell_damages.iter().find(|v| &v.name == name);
+            [
+                damage.map(|v| v.normal),
+                damage.map(|v| v.crit),
+                damage.map(|v| v.avg),
+            ]
+            .map(|v| v.map(|v| v.to_string()).unwrap_or_default())
+        }))
+        .chain(costs.iter().map(|name| {
+            build
+                .status
+                .spell_costs
+                .iter()
+                .find(|(cost_name, _)| cost_name == name)
+                .map(|(_, cost)| cost.to_string())
+                .unwrap_or_default()
+        }))
+        .join(",");
+    writeln!(file, "{}", values)
+}
+
+fn csv_field(value: &Value) -> String {
+    match value {
+        Value::String(s) => csv_quote(s),
+        value => value.to_string(),
+    }
+}
+
+/// quote the field if it has a comma, a quote or a new line
+fn csv_quote(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_owned()
+    }
+}
+
+/// write the build as a json object on one line, the spells are in `damages` and `spell_costs`
+/// like their tables
+fn write_jsonl(file: &mut impl Write, build: &Build) -> io::Result<()> {
+    let damages: Vec<Value> = build
+        .status
+        .spell_damages
+        .iter()
+        .map(|v| json!({"name": v.name, "normal": v.normal, "crit": v.crit, "avg": v.avg}))
+        .collect();
+    let costs: Vec<Value> = build
+        .status
+        .spell_costs
+        .iter()
+        .map(|(name, cost)| json!({"name": name, "cost": cost}))
+        .collect();
+    // the fields are written in the order of the columns
+    let fields = build
+        .columns()
+        .into_iter()
+        .chain([("damages", json!(damages)), ("spell_costs", json!(costs))])
+        .map(|(name, value)| format!("{}:{}", json!(name), value))
+        .join(",");
+    writeln!(file, "{{{}}}", fields)
+}
+
+/// writes the builds and checkpoints of the search threads to the sink from a task, many rows at
+/// once, the rows are written in the order they are sent
+pub struct Writer {
+    sender: mpsc::Sender<Row>,
+    task: JoinHandle<()>,
+}
+impl Writer {
+    pub fn spawn(mut sink: Sink) -> Self {
+        let (sender, mut receiver) = mpsc::channel(CHANNEL_SIZE);
+        let task = spawn(async move {
+            let mut rows = Vec::with_capacity(BATCH_SIZE);
+            while receiver.recv_many(&mut rows, BATCH_SIZE).await > 0 {
+                sink.write(&rows).await;
+                rows.clear();
+            }
+        });
+        Self { sender, task }
+    }
+    /// waits while the channel is full, so it must not be called from an async context
+    pub fn save_build(&self, build: Build) {
+        self.sender
+            .blocking_send(Row::Build(Box::new(build)))
+            .unwrap();
+    }
+    pub async fn save_build_async(&self, build: Build) {
+        self.sender.send(Row::Build(Box::new(build))).await.unwrap();
+    }
+    /// record the segments of the run, the builds sent before are written first, see
+    /// `save_build` for the context
+    pub fn save_checkpoint(&self, run: String, segments: Vec<usize>) {
+        self.sender
+            .blocking_send(Row::Checkpoint(run, segments))
+            .unwrap();
+    }
+    /// write the rows left and stop the task
+    pub async fn close(self) {
+        drop(self.sender);
+        self.task.await.unwrap();
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    pub(crate) fn build(spells: &[&str]) -> Build {
+        let spell_damages = spells
+            .iter()
+            .map(|name| SpellDamage {
+                name: name.to_string(),
+                normal: 1.0,
+                crit: 2.0,
+                avg: 1.5,
+            })
+            .collect();
+        Build {
+            url: "https://hppeng-wynn.github.io/builder/#a,b".to_string(),
+            status: Status {
+                spell_damages,
+                ..Default::default()
+            },
+            items: Default::default(),
+            weapon: "Weapon".to_string(),
+        }
+    }
+
+    #[test]
+    fn csv_works() {
+        let mut file = Vec::new();
+        // the spells of every atree, not only of the first build
+        let spells = (
+            vec!["Bash".to_string(), "Uppercut".to_string()],
+            vec!["Bash".to_string()],
+        );
+        write_csv_header(&mut file, &spells).unwrap();
+        write_csv(&mut file, &spells, &build(&["Uppercut"])).unwrap();
+        write_csv(&mut file, &spells, &build(&["Bash", "Uppercut"])).unwrap();
+        let file = String::from_utf8(file).unwrap();
+        let lines: Vec<&str> = file.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("url,ring_1,ring_2,helmet,"));
+        assert!(lines[0].ends_with(
+            "Bash normal,Bash crit,Bash avg,Uppercut normal,Uppercut crit,Uppercut avg,Bash cost"
+        ));
+        assert_eq!(
+            lines[0].split(',').count(),
+            Build::default().columns().len() + 7
+        );
+        // the spells the build does not have are empty
+        assert!(lines[1].starts_with("\"https://hppeng-wynn.github.io/builder/#a,b\","));
+        assert!(lines[1].ends_with(",,,,1,2,1.5,"));
+        assert!(lines[2].ends_with(",1,2,1.5,1,2,1.5,"));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn writer_works() {
+        let pool = db::tests::memory_pool().await;
+        // the number of builds written when the checkpoint is
+        sqlx::raw_sql(
+            "CREATE TABLE checkpoint_builds(builds INTEGER NOT NULL);
+            CREATE TRIGGER checkpoint_builds AFTER INSERT ON checkpoint BEGIN
+                INSERT INTO checkpoint_builds SELECT COUNT(*) FROM build;
+            END;",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        let run = db::tests::save_run(&pool, "run").await;
+        let writer = Writer::spawn(Sink::Sqlite {
+            pool: pool.clone(),
+            config: Box::new(db::tests::config()),
+            run,
+        });
+
+        let build = |index: usize| db::tests::build(&format!("url {}", index));
+        // more than a batch before the checkpoint, the last builds are sent twice
+        let before = BATCH_SIZE + 500;
+        let writer = tokio::task::spawn_blocking(move || {
+            (0..before).for_each(|index| writer.save_build(build(index)));
+            (before - 10..before).for_each(|index| writer.save_build(build(index)));
+            writer.save_checkpoint("run".to_string(), vec![0, 1]);
+            writer
+        })
+        .await
+        .unwrap();
+        for index in before..before + 100 {
+            writer.save_build_async(build(index)).await;
+        }
+        writer.close().await;
+
+        let count = |sql: &str| {
+            let pool = pool.clone();
+            let sql = sql.to_string();
+            async move {
+                let (count,): (i64,) = sqlx::query_as(&sql).fetch_one(&pool).await.unwrap();
+                count as usize
+            }
+        };
+        assert_eq!(count("SELECT COUNT(*) FROM build").await, before + 100);
+        assert_eq!(count("SELECT COUNT(*) FROM damage").await, before + 100);
+        assert_eq!(
+            count("SELECT COUNT(*) FROM build_item").await,
+            (before + 100) * SLOTS.len()
+        );
+        assert_eq!(
+            db::load_checkpoint(&pool, "run").await,
+            [0, 1].into_iter().collect()
+        );
+        // the builds sent before the checkpoint are written before it
+        assert_eq!(
+            count("SELECT MIN(builds) FROM checkpoint_builds").await,
+            before
+        );
+    }
+
+    #[test]
+    fn jsonl_works() {
+        let mut file = Vec::new();
+        write_jsonl(&mut file, &build(&["Bash"])).unwrap();
+        let value: Value = serde_json::from_slice(&file).unwrap();
+        assert_eq!(value["url"], "https://hppeng-wynn.github.io/builder/#a,b");
+        assert_eq!(value["weapon"], "Weapon");
+        assert_eq!(value["air_original"], 0);
+        assert_eq!(value["damages"][0]["avg"], 1.5);
+    }
+}
@@ -0,0 +1,219 @@
+use wynn_build_tools::calculate::*;
+use wynn_build_tools::config::*;
+use wynn_build_tools::items::*;
+use wynn_build_tools::util::Shard;
+
+/// a candidate of a slot of the branch and bound search, the two rings are one slot
+pub struct Candidate<'a> {
+    /// (index in the combination, item)
+    pub items: Vec<(usize, &'a Apparel)>,
+    pub sum: StatSum,
+}
+
+/// the slots with fewer candidates are fixed first so there are fewer partial combinations to
+/// bound, the candidates with a higher objective are visited first to raise the score to beat early,
+/// a shard only keeps its part of the candidates of the first slot
+pub fn slots<'a>(
+    no_ring_apparels: &[&[&'a Apparel]; 6],
+    rings: &[&'a Apparel],
+    ring_combinations: &[[usize; 2]],
+    objective: Option<&ObjectiveBound>,
+    shard: Option<Shard>,
+) -> Vec<Vec<Candidate<'a>>> {
+    let mut slots: Vec<Vec<Candidate>> = no_ring_apparels
+        .iter()
+        .enumerate()
+        .map(|(slot, apparels)| {
+            apparels
+                .iter()
+                .map(|apparel| Candidate {
+                    // the rings are the first 2 items of a combination
+                    items: vec![(slot + 2, *apparel)],
+                    sum: StatSum::from(*apparel),
+                })
+                .collect()
+        })
+        .collect();
+    slots.push(
+        ring_combinations
+            .iter()
+            .map(|[a, b]| Candidate {
+                items: vec![(0, rings[*a]), (1, rings[*b])],
+                sum: &StatSum::from(rings[*a]) + &StatSum::from(rings[*b]),
+            })
+            .collect(),
+    );
+    slots.sort_by_key(|candidates| candidates.len());
+    if let Some(objective) = objective {
+        for candidates in &mut slots {
+            candidates.sort_by(|a, b| {
+                objective
+                    .max(&b.sum, &b.sum)
+                    .total_cmp(&objective.max(&a.sum, &a.sum))
+            });
+        }
+    }
+    if let (Some(shard), Some(first)) = (shard, slots.first_mut()) {
+        let mut index = 0;
+        first.retain(|_| {
+            index += 1;
+            shard.contains(index - 1)
+        });
+    }
+    slots
+}
+
+/// upper bound of an objective that is a weighted sum of stats summed over the items
+pub struct ObjectiveBound {
+    constant: f64,
+    terms: Vec<(f64, BuildStat)>,
+}
+impl ObjectiveBound {
+    /// `None` if the objective uses other values (e.g. ehp or spell damages)
+    pub fn new(objective: &Expr) -> Option<Self> {
+        let (constant, vars) = objective.linear()?;
+        let mut terms = Vec::with_capacity(vars.len());
+        for (weight, var) in vars {
+            match var {
+                Var::Stat(stat)
+                    if stat_value(stat, &StatSum::default(), &Point::default()).is_some() =>
+                {
+                    terms.push((weight, *stat))
+                }
+                _ => return None,
+            }
+        }
+        Some(Self { constant, terms })
+    }
+    /// the max objective of the builds whose sums are between `lower` and `upper`
+    pub fn max(&self, lower: &StatSum, upper: &StatSum) -> f64 {
+        let (def_lower, def_upper) = StatSum::def_range(lower, upper);
+        self.constant
+            + self
+                .terms
+                .iter()
+                .map(|(weight, stat)| {
+                    let value = if *weight >= 0.0 {
+                        stat_value(stat, upper, &def_upper)
+                    } else {
+                        stat_value(stat, lower, &def_lower)
+                    };
+                    weight * value.unwrap()
+                })
+                .sum::<f64>()
+    }
+}
+/// the stats that are increasing with the sums, `None` for the other stats
+fn stat_value(stat: &BuildStat, sum: &StatSum, def: &Point) -> Option<f64> {
+    let value = match stat {
+        BuildStat::MaxHp => sum.hp as f64,
+        BuildStat::MaxHprRaw => sum.common_stat.hpr_raw() as f64,
+        BuildStat::MaxHprPct => sum.common_stat.hpr_pct() as f64,
+        BuildStat::MaxMr => sum.common_stat.mr() as f64,
+        BuildStat::MaxLs => sum.common_stat.ls() as f64,
+        BuildStat::MaxMs => sum.common_stat.ms() as f64,
+        BuildStat::MaxSpd => sum.common_stat.spd() as f64,
+        BuildStat::MaxSdRaw => sum.common_stat.sd_raw() as f64,
+        BuildStat::MaxSdPct => sum.common_stat.sd_pct() as f64,
+        BuildStat::MaxExpBonus => sum.sec_stat.exp_bonus() as f64,
+        BuildStat::MaxLootBonus => sum.sec_stat.loot_bonus() as f64,
+        BuildStat::MaxHealPct => sum.sec_stat.heal_pct() as f64,
+        BuildStat::ManaSustain => mana_sustain(&sum.common_stat),
+        BuildStat::MaxDefense(index) => def.inner[*index] as f64,
+        BuildStat::MaxDamPct(index) => sum.dam_pct.inner[*index] as f64,
+        BuildStat::MaxEhp | BuildStat::MaxHpr | BuildStat::MeleeDps | BuildStat::Point(_) => {
+            return None
+        }
+    };
+    Some(value)
+}
+
+/// bounds the builds starting with the candidates fixed so far, the thresholds are the ones of
+/// `calculate_stats` that only depend on the summed stats
+pub struct SearchBound<'a> {
+    /// always active stats (weapon, tomes, base hp) with the bounds of the atree and set bonuses
+    lower: StatSum,
+    upper: StatSum,
+    /// sum of the min/max of the slots after each depth
+    rest_lower: Vec<StatSum>,
+    rest_upper: Vec<StatSum>,
+    objective: Option<&'a ObjectiveBound>,
+    hp: Option<i32>,
+    common_stat: Option<CommonStat>,
+    dam: Option<Dam>,
+    sec_stat: Option<SecStat>,
+    sec_stat_max: Option<SecStat>,
+    def: Option<Point>,
+    def_max: Option<Point>,
+}
+impl<'a> SearchBound<'a> {
+    /// `scaling_bounded` is false if an atree scaling effect of the builds has no max, the hp, the
+    /// common stats, the dam pct and the objective are not bounded then
+    pub fn new(
+        config: &Config,
+        slots: &[Vec<Candidate>],
+        lower: StatSum,
+        upper: StatSum,
+        scaling_bounded: bool,
+        objective: Option<&'a ObjectiveBound>,
+    ) -> Self {
+        let mut rest_lower = vec![StatSum::default(); slots.len() + 1];
+        let mut rest_upper = vec![StatSum::default(); slots.len() + 1];
+        for (depth, candidates) in slots.iter().enumerate().rev() {
+            let (min, max) = StatSum::range(candidates.iter().map(|candidate| &candidate.sum))
+                .unwrap_or_default();
+            rest_lower[depth] = &rest_lower[depth + 1] + &min;
+            rest_upper[depth] = &rest_upper[depth + 1] + &max;
+        }
+        Self {
+            lower,
+            upper,
+            rest_lower,
+            rest_upper,
+            objective: objective.filter(|_| scaling_bounded),
+            hp: config.hp_threshold().filter(|_| scaling_bounded),
+            common_stat: config.common_stat_threshold().filter(|_| scaling_bounded),
+            dam: config.dam_threshold().filter(|_| scaling_bounded),
+            sec_stat: config.sec_stat_threshold(),
+            sec_stat_max: config.sec_stat_max_threshold(),
+            def: config.def_threshold(),
+            def_max: config.def_max_threshold(),
+        }
+    }
+    /// whether no build starting with `fixed` can pass the thresholds or beat `score_to_beat`
+    pub fn prune(&self, fixed: &[&Candidate], score_to_beat: f64) -> bool {
+        let mut lower = &self.lower + &self.rest_lower[fixed.len()];
+        let mut upper = &self.upper + &self.rest_upper[fixed.len()];
+        for candidate in fixed {
+            lower += &candidate.sum;
+            upper += &candidate.sum;
+        }
+        if self.hp.is_some_and(|v| upper.hp < v)
+            || self
+                .common_stat
+                .as_ref()
+                .is_some_and(|v| upper.common_stat.any_lt(v))
+            || self.dam.as_ref().is_some_and(|v| upper.dam_pct.any_lt(v))
+            || self
+                .sec_stat
+                .as_ref()
+                .is_some_and(|v| upper.sec_stat.any_lt(v))
+            || self
+                .sec_stat_max
+                .as_ref()
+                .is_some_and(|v| lower.sec_stat.any_gt(v))
+        {
+            return true;
+        }
+        if self.def.is_some() || self.def_max.is_some() {
+            let (def_lower, def_upper) = StatSum::def_range(&lower, &upper);
+            if self.def.as_ref().is_some_and(|v| def_upper.any_lt(v))
+                || self.def_max.as_ref().is_some_and(|v| def_lower.any_gt(v))
+            {
+                return true;
+            }
+        }
+        self.objective
+            .is_some_and(|objective| objective.max(&lower, &upper) <= score_to_beat)
+    }
+}
@@ -0,0 +1,340 @@
+mod search_build_args;
+
+use clap::Parser;
+use itertools::Itertools;
+use sqlx::{sqlite::SqlitePoolOptions, Row, SqlitePool};
+
+use search_build_args::*;
+use wynn_build_tools::config::*;
+
+/// separates the spell and the part of the damage names, as written by the builder
+const SPLIT_STR: &str = ".";
+/// the columns of the stat table, the sorted and filtered ones are added after them
+const COLUMNS: [SortAndFilterBy; 8] = [
+    SortAndFilterBy::Ehp,
+    SortAndFilterBy::Hp,
+    SortAndFilterBy::Hpr,
+    SortAndFilterBy::Mr,
+    SortAndFilterBy::Ms,
+    SortAndFilterBy::Spd,
+    SortAndFilterBy::Ls,
+    SortAndFilterBy::MeleeDps,
+];
+/// the item columns of `build`, the builds saved before the `build_item` table have them too
+const ITEM_COLUMNS: &str =
+    "build.helmet, build.chest_plate, build.leggings, build.boots, build.ring_1, build.ring_2, build.bracelet, build.necklace, build.weapon";
+
+#[tokio::main]
+async fn main() {
+    let args = BuildSearchArgs::parse();
+    let db_path = match &args.db_path {
+        Some(db_path) => db_path.clone(),
+        None => {
+            let config = load_config(&args.config).await.unwrap_or_else(|err| {
+                eprintln!("can not load the config {}: {}", args.config, err);
+                std::process::exit(1);
+            });
+            config.hppeng.db_path
+        }
+    };
+
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect(&format!("sqlite:{}?mode=ro", db_path))
+        .await
+        .unwrap_or_else(|err| {
+            eprintln!("can not open the database {}: {}", db_path, err);
+            std::process::exit(1);
+        });
+    let columns = columns(&args);
+    let builds = search(&pool, &args, &columns).await.unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    });
+    if builds.is_empty() {
+        println!("no build found");
+        return;
+    }
+
+    let cells: Vec<Vec<String>> = builds
+        .iter()
+        .map(|(_, _, values)| values.iter().map(|v| format_value(*v)).collect())
+        .collect();
+    let names: Vec<String> = columns.iter().map(|v| v.to_string()).collect();
+    let widths: Vec<usize> = names
+        .iter()
+        .enumerate()
+        .map(|(i, name)| {
+            cells
+                .iter()
+                .map(|row| row[i].len())
+                .chain([name.len()])
+                .max()
+                .unwrap()
+        })
+        .collect();
+    let rank_width = builds.len().to_string().len() + 1;
+
+    println!(
+        "{:<rank_width$} {}",
+        "#",
+        names
+            .iter()
+            .zip(&widths)
+            .map(|(name, width)| format!("{:>width$}", name))
+            .join(" ")
+    );
+    for (rank, row) in cells.iter().enumerate() {
+        println!(
+            "{:<rank_width$} {}",
+            rank + 1,
+            row.iter()
+                .zip(&widths)
+                .map(|(cell, width)| format!("{:>width$}", cell))
+                .join(" ")
+        );
+    }
+    println!();
+    for (rank, (url, weapon, _)) in builds.iter().enumerate() {
+        println!("#{} weapon:{} {}", rank + 1, weapon, url);
+    }
+}
+
+/// the columns of the table, `COLUMNS`, then the sorted, filtered and spell ones
+fn columns(args: &BuildSearchArgs) -> Vec<SortAndFilterBy> {
+    let spell = args.spell.as_ref().map(|_| SortAndFilterBy::SpellAvg);
+    COLUMNS
+        .iter()
+        .chain(&args.sort_by)
+        .chain(args.min_values.iter().map(|(by, _)| by))
+        .chain(args.max_values.iter().map(|(by, _)| by))
+        .chain(&spell)
+        .copied()
+        .unique()
+        .collect()
+}
+
+/// the builds of `args` as (url, weapon, values of the columns)
+async fn search(
+    pool: &SqlitePool,
+    args: &BuildSearchArgs,
+    columns: &[SortAndFilterBy],
+) -> Result<Vec<(String, String, Vec<f64>)>, String> {
+    let (sql, binds) = query(args, columns)?;
+    if let Some(spell) = &args.spell {
+        check_spell(pool, spell).await?;
+    }
+    let mut query = sqlx::query(&sql);
+    for bind in binds {
+        query = match bind {
+            Bind::Text(v) => query.bind(v),
+            Bind::Number(v) => query.bind(v),
+        };
+    }
+    let rows = query
+        .fetch_all(pool)
+        .await
+        .map_err(|err| format!("can not search the builds: {}", err))?;
+    Ok(rows
+        .iter()
+        .map(|row| {
+            let values = (0..columns.len()).map(|i| row.get(i + 2)).collect();
+            (row.get(0), row.get(1), values)
+        })
+        .collect())
+}
+
+/// the damages are saved by spell part, `Spell.Part`, a spell or part without damages is an error
+/// listing the parts of the spell
+async fn check_spell(pool: &SqlitePool, spell: &str) -> Result<(), String> {
+    let name = spell.split_once(SPLIT_STR).map_or(spell, |(name, _)| name);
+    let parts: Vec<(String,)> = sqlx::query_as(
+        "SELECT DISTINCT name FROM damage WHERE substr(name, 1, length(?) + 1) = ? || ? ORDER BY name",
+    )
+    .bind(name)
+    .bind(name)
+    .bind(SPLIT_STR)
+    .fetch_all(pool)
+    .await
+    .map_err(|err| format!("can not search the spells: {}", err))?;
+    if parts.iter().any(|(part,)| part == spell) {
+        return Ok(());
+    }
+    if parts.is_empty() {
+        return Err(format!("no build has damages of the spell {}", name));
+    }
+    Err(format!(
+        "`--spell` is a spell part, the parts of {} are: {}",
+        name,
+        parts.iter().map(|(part,)| part).join(", ")
+    ))
+}
+
+enum Bind {
+    Text(String),
+    Number(f64),
+}
+
+/// the query of the builds of `args` selecting (url, weapon, columns...), with the values of its
+/// `?` in order
+fn query(
+    args: &BuildSearchArgs,
+    columns: &[SortAndFilterBy],
+) -> Result<(String, Vec<Bind>), String> {
+    let mut binds = Vec::new();
+    let mut sql = format!(
+        "SELECT build.url, build.weapon, {} FROM build",
+        columns
+            .iter()
+            .map(|by| format!("CAST({} AS REAL)", by.column()))
+            .join(", ")
+    );
+    if columns.iter().any(|by| by.is_spell()) {
+        let spell = args
+            .spell
+            .as_ref()
+            .ok_or("the spell of the spell damages is missing, see `--spell`")?;
+        sql.push_str(" JOIN damage ON damage.build_id = build.row_id AND damage.name = ?");
+        binds.push(Bind::Text(spell.clone()));
+    }
+
+    let mut conditions = Vec::new();
+    for (by, value) in &args.min_values {
+        conditions.push(format!("{} >= ?", by.column()));
+        binds.push(Bind::Number(*value));
+    }
+    for (by, value) in &args.max_values {
+        conditions.push(format!("{} <= ?", by.column()));
+        binds.push(Bind::Number(*value));
+    }
+    for item in &args.include {
+        conditions.push(format!("? IN ({})", ITEM_COLUMNS));
+        binds.push(Bind::Text(item.clone()));
+    }
+    for item in &args.exclude {
+        conditions.push(format!("? NOT IN ({})", ITEM_COLUMNS));
+        binds.push(Bind::Text(item.clone()));
+    }
+    if !conditions.is_empty() {
+        sql.push_str(&format!(" WHERE {}", conditions.join(" AND ")));
+    }
+
+    let order = match args.order_by {
+        OrderBy::Asc => "ASC",
+        OrderBy::Desc => "DESC",
+    };
+    if !args.sort_by.is_empty() {
+        let sort_by = args
+            .sort_by
+            .iter()
+            .map(|by| format!("{} {}", by.column(), order))
+            .join(", ");
+        sql.push_str(&format!(" ORDER BY {}", sort_by));
+    }
+    sql.push_str(&format!(" LIMIT {}", args.limit));
+    Ok((sql, binds))
+}
+
+fn format_value(value: f64) -> String {
+    if value.fract() == 0.0 {
+        format!("{:.0}", value)
+    } else {
+        format!("{:.2}", value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn query_works() {
+        let args = BuildSearchArgs::parse_from([
+            "search_build",
+            "-s",
+            "spell-avg",
+            "ehp",
+            "--spell",
+            "Arrow Storm.Total Damage",
+            "--min",
+            "mr=5",
+            "-e",
+            "Cancer",
+        ]);
+        let selected = columns(&args);
+        assert_eq!(selected.len(), COLUMNS.len() + 1);
+        let (sql, binds) = query(&args, &selected).unwrap();
+        assert!(sql.contains("JOIN damage ON damage.build_id = build.row_id AND damage.name = ?"));
+        assert!(sql.contains("WHERE build.max_mr >= ? AND ? NOT IN ("));
+        assert!(sql.ends_with("ORDER BY damage.avg DESC, build.max_ehp DESC LIMIT 10"));
+        assert_eq!(binds.len(), 3);
+
+        let args = BuildSearchArgs::parse_from(["search_build", "-s", "spell-avg"]);
+        assert!(query(&args, &columns(&args)).is_err());
+    }
+
+    #[tokio::test]
+    async fn search_works() {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        sqlx::migrate::Migrator::new(std::path::Path::new("migrations"))
+            .await
+            .unwrap()
+            .run(&pool)
+            .await
+            .unwrap();
+        // the other columns of the builds are 0
+        let not_null: Vec<(String,)> =
+            sqlx::query_as("SELECT name FROM pragma_table_info('build') WHERE \"notnull\"")
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        let not_null = not_null.into_iter().map(|(name,)| name).collect::<Vec<_>>();
+        for (row_id, url, avg) in [(1, "a", 100.0), (2, "b", 200.0)] {
+            let values = not_null.iter().map(|name| match name.as_str() {
+                "url" => format!("'{}'", url),
+                _ => "0".to_string(),
+            });
+            sqlx::raw_sql(&format!(
+                "INSERT INTO build(row_id, {}) VALUES ({}, {});
+                INSERT INTO damage(build_id, name, normal, crit, avg)
+                VALUES ({}, 'Arrow Storm.Total Damage', 0, 0, {}), ({}, 'Arrow Storm.Single Arrow', 0, 0, 1);",
+                not_null.join(", "),
+                row_id,
+                values.collect::<Vec<_>>().join(", "),
+                row_id,
+                avg,
+                row_id
+            ))
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+
+        let search = |spell: &str| {
+            let args =
+                BuildSearchArgs::parse_from(["search_build", "-s", "spell-avg", "--spell", spell]);
+            let pool = pool.clone();
+            async move { search(&pool, &args, &columns(&args)).await }
+        };
+        let builds = search("Arrow Storm.Total Damage").await.unwrap();
+        assert_eq!(
+            builds
+                .iter()
+                .map(|(url, _, values)| (url.as_str(), *values.last().unwrap()))
+                .collect::<Vec<_>>(),
+            [("b", 200.0), ("a", 100.0)]
+        );
+        assert_eq!(
+            search("Arrow Storm").await.unwrap_err(),
+            "`--spell` is a spell part, the parts of Arrow Storm are: Arrow Storm.Single Arrow, Arrow Storm.Total Damage"
+        );
+        assert_eq!(
+            search("Arrow Bomb.Total Damage").await.unwrap_err(),
+            "no build has damages of the spell Arrow Bomb"
+        );
+    }
+}
@@ -0,0 +1,174 @@
+use clap::{Parser, ValueEnum};
+use std::fmt::Display;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about)]
+pub struct BuildSearchArgs {
+    /// Path of the config file, its `db_path` is searched
+    #[arg(short, long, default_value = "config/config.toml")]
+    pub config: String,
+
+    /// Path of the database, instead of `db_path` of the config
+    #[arg(long)]
+    pub db_path: Option<String>,
+
+    /// A limit on the number of results
+    #[arg(short, long, default_value_t = 10, value_parser = clap::value_parser!(u32).range(1..))]
+    pub limit: u32,
+
+    /// Order the results in ascending or descending order
+    #[arg(short, long, default_value_t = OrderBy::Desc)]
+    pub order_by: OrderBy,
+
+    /// Sort the results by specific fields, the next field breaks the ties of the previous one
+    #[arg(short, long, num_args = 1..)]
+    pub sort_by: Vec<SortAndFilterBy>,
+
+    /// Minimum values for various attributes (format: attribute=value)
+    ///
+    /// The attributes are the values of `--sort-by`.
+    #[arg(long = "min", value_parser = parse_key_val_sort_by, num_args = 0..)]
+    pub min_values: Vec<(SortAndFilterBy, f64)>,
+
+    /// Maximum values for various attributes (format: attribute=value)
+    ///
+    /// The attributes are the values of `--sort-by`.
+    #[arg(long = "max", value_parser = parse_key_val_sort_by, num_args = 0..)]
+    pub max_values: Vec<(SortAndFilterBy, f64)>,
+
+    /// The spell part of `spell-normal`, `spell-crit` and `spell-avg` (format: spell.part), e.g.
+    /// `--spell "Arrow Storm.Total Damage"`
+    ///
+    /// The damages are saved by part, a spell without its part is an error listing its parts.
+    #[arg(long)]
+    pub spell: Option<String>,
+
+    /// Only the builds with all these items, weapons included
+    #[arg(short, long, num_args = 1..)]
+    pub include: Vec<String>,
+
+    /// Only the builds without any of these items, weapons included
+    #[arg(short, long, num_args = 1..)]
+    pub exclude: Vec<String>,
+}
+
+fn parse_key_val_sort_by(s: &str) -> Result<(SortAndFilterBy, f64), String> {
+    let (key, value) = s
+        .split_once('=')
+        .ok_or_else(|| format!("invalid KEY=value: no `=` found in `{}`", s))?;
+    let key = SortAndFilterBy::from_str(key.trim(), true)?;
+    let value = value
+        .trim()
+        .parse()
+        .map_err(|e| format!("invalid value: {}; a number is required", e))?;
+    Ok((key, value))
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
+pub enum OrderBy {
+    /// Sort the results in ascending order, arrange them from smallest to largest
+    Asc,
+    /// Sort the results in descending order, arrange them from largest to smallest
+    Desc,
+}
+
+impl Display for OrderBy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OrderBy::Asc => write!(f, "asc"),
+            OrderBy::Desc => write!(f, "desc"),
+        }
+    }
+}
+
+/// Sort and filter by
+#[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, ValueEnum, Debug)]
+pub enum SortAndFilterBy {
+    /// Effective hp
+    Ehp,
+    Hp,
+    /// Hp regain
+    Hpr,
+    HprRaw,
+    HprPct,
+    /// Mana regain
+    Mr,
+    /// Mana steal
+    Ms,
+    /// Walk speed bonus
+    Spd,
+    /// Life steal
+    Ls,
+    SdRaw,
+    SdPct,
+    Ndmg,
+    Edmg,
+    Tdmg,
+    Wdmg,
+    Fdmg,
+    Admg,
+    Edef,
+    Tdef,
+    Wdef,
+    Fdef,
+    Adef,
+    ExpB,
+    LootBonus,
+    MeleeDps,
+    ManaSustain,
+    /// Normal damage of `--spell`
+    SpellNormal,
+    /// Crit damage of `--spell`
+    SpellCrit,
+    /// Average damage of `--spell`
+    SpellAvg,
+}
+
+impl Display for SortAndFilterBy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_possible_value().unwrap().get_name())
+    }
+}
+
+impl SortAndFilterBy {
+    /// the column of the value, the spell columns are of the joined `damage` table
+    pub fn column(&self) -> &'static str {
+        match self {
+            SortAndFilterBy::Ehp => "build.max_ehp",
+            SortAndFilterBy::Hp => "build.max_hp",
+            SortAndFilterBy::Hpr => "build.max_hpr",
+            SortAndFilterBy::HprRaw => "build.max_hpr_raw",
+            SortAndFilterBy::HprPct => "build.max_hpr_pct",
+            SortAndFilterBy::Mr => "build.max_mr",
+            SortAndFilterBy::Ms => "build.max_ms",
+            SortAndFilterBy::Spd => "build.max_spd",
+            SortAndFilterBy::Ls => "build.max_ls",
+            SortAndFilterBy::SdRaw => "build.max_sd_raw",
+            SortAndFilterBy::SdPct => "build.max_sd_pct",
+            SortAndFilterBy::Ndmg => "build.max_neutral_dam_pct",
+            SortAndFilterBy::Edmg => "build.max_earth_dam_pct",
+            SortAndFilterBy::Tdmg => "build.max_thunder_dam_pct",
+            SortAndFilterBy::Wdmg => "build.max_water_dam_pct",
+            SortAndFilterBy::Fdmg => "build.max_fire_dam_pct",
+            SortAndFilterBy::Admg => "build.max_air_dam_pct",
+            SortAndFilterBy::Edef => "build.earth_def",
+            SortAndFilterBy::Tdef => "build.thunder_def",
+            SortAndFilterBy::Wdef => "build.water_def",
+            SortAndFilterBy::Fdef => "build.fire_def",
+            SortAndFilterBy::Adef => "build.air_def",
+            SortAndFilterBy::ExpB => "build.max_exp_bonus",
+            SortAndFilterBy::LootBonus => "build.max_loot_bonus",
+            SortAndFilterBy::MeleeDps => "build.melee_dps",
+            SortAndFilterBy::ManaSustain => "build.mana_sustain",
+            SortAndFilterBy::SpellNormal => "damage.normal",
+            SortAndFilterBy::SpellCrit => "damage.crit",
+            SortAndFilterBy::SpellAvg => "damage.avg",
+        }
+    }
+    pub fn is_spell(&self) -> bool {
+        matches!(
+            self,
+            SortAndFilterBy::SpellNormal | SortAndFilterBy::SpellCrit | SortAndFilterBy::SpellAvg
+        )
+    }
+}
@@ -11,7 +11,7 @@ async fn main() {
     let config = load_config("config/config.toml").await.unwrap();
 
     let args = ItemSearchArgs::parse();
-    let (mut apparels, _) = match load_from_wapi(&config.hppeng.items_file) {
+    let (mut apparels, _, _) = match load_from_wapi(&config.hppeng.items_file) {
         Ok(v) => v,
         Err(_) => {
             let api_fetch_attempt =
@@ -6,7 +6,9 @@ use std::{
     },
 };
 
-use rayon::prelude::{ParallelBridge, ParallelIterator};
+use rayon::prelude::{
+    IntoParallelIterator, IntoParallelRefIterator, ParallelBridge, ParallelIterator,
+};
 
 /// Generates all possible combinations of elements from multiple arrays and applies a function to each combination.
 ///
@@ -69,6 +71,188 @@ pub fn generate_full_combinations_with_random<T, TR, F, const LEN: usize>(
         })
 }
 
+/// Generates the combinations like `generate_full_combinations_with_random`, but the order of the
+/// segments comes from `rng`, the segments rejected by `skip` are not visited and the finished
+/// ones are reported, so a run can be reproduced, split or resumed.
+///
+/// The segment `i` covers the combinations `i * segment_size..(i + 1) * segment_size`. The
+/// segments are visited in parallel in the shuffled order, once all the combinations of a
+/// segment are applied `on_finished` is called with its index.
+///
+/// # Type Parameters
+///
+/// - `T`: Type of the elements in the arrays.
+/// - `TR`: Type that can be referenced as `T`.
+/// - `S`: Type of the function selecting the segments to skip.
+/// - `F`: Type of the function to be applied to each combination.
+/// - `D`: Type of the function called with each finished segment.
+/// - `LEN`: Length of the `arrays` array.
+///
+/// # Parameters
+///
+/// - `segment_size`: The number of combinations of each segment.
+/// - `count`: An `Arc<AtomicUsize>` used to keep track of the number of combinations processed.
+/// - `arrays`: A reference to an array of slices, where each slice contains elements to be combined.
+/// - `rng`: The random number generator shuffling the segments, seed it for a reproducible order.
+/// - `skip`: A function taking a segment index and returning whether the segment is skipped,
+///   e.g. because it is finished or in another shard.
+/// - `func`: A function to be applied to each combination of elements.
+/// - `on_finished`: A function called with the index of each segment once all its combinations
+///   are applied.
+///
+/// # Example
+///
+/// ```rust
+/// use std::sync::{Arc, Mutex, atomic::{AtomicUsize, Ordering}};
+/// use wynn_build_tools::util::generate_full_combinations_by_segment;
+///
+/// struct Item(i32);
+/// impl AsRef<Item> for Item {
+///     fn as_ref(&self) -> &Item {
+///         self
+///     }
+/// }
+///
+/// let arrays: [&[Item]; 2] = [&[Item(1), Item(2)], &[Item(3), Item(4), Item(5)]];
+/// let count = Arc::new(AtomicUsize::new(0));
+/// let finished = Mutex::new(Vec::new());
+///
+/// // the segment 0 covers the first 4 combinations and is skipped
+/// generate_full_combinations_by_segment(
+///     4,
+///     count.clone(),
+///     &arrays,
+///     fastrand::Rng::with_seed(42),
+///     |segment| segment == 0,
+///     |[a, b]: [&Item; 2]| println!("{}", a.0 + b.0),
+///     |segment| finished.lock().unwrap().push(segment),
+/// );
+///
+/// assert_eq!(count.load(Ordering::Relaxed), 2);
+/// assert_eq!(*finished.lock().unwrap(), [1]);
+/// ```
+pub fn generate_full_combinations_by_segment<T, TR, S, F, D, const LEN: usize>(
+    segment_size: usize,
+    count: Arc<AtomicUsize>,
+    arrays: &[&[TR]; LEN],
+    rng: fastrand::Rng,
+    skip: S,
+    func: F,
+    on_finished: D,
+) where
+    TR: Sync + AsRef<T>,
+    S: Fn(usize) -> bool,
+    F: Fn([&T; LEN]) + Sync,
+    D: Fn(usize) + Sync,
+{
+    let max_indexes: [usize; LEN] = arrays.map(|f| f.len());
+    let total_combinations = max_indexes.iter().product::<usize>();
+
+    let mut segments: Vec<usize> = (0..total_combinations.div_ceil(segment_size))
+        .filter(|segment| !skip(*segment))
+        .collect();
+    rng.shuffle(&mut segments);
+    segments.into_par_iter().for_each(|segment| {
+        let start = segment * segment_size;
+        (start..total_combinations.min(start + segment_size))
+            .into_par_iter()
+            .for_each(|i| {
+                count.fetch_add(1, Ordering::AcqRel);
+                let index_combinations = map_to_index_space(&max_indexes, i);
+                func(unsafe { select_from_arrays(&index_combinations, arrays) });
+            });
+        on_finished(segment);
+    });
+}
+
+/// Enumerates one candidate per slot depth-first and applies a function to each combination,
+/// skipping the partial combinations rejected by `prune`.
+///
+/// The candidates of the first slot are explored in parallel using the Rayon library. `prune` is
+/// checked for every partial combination, so it should bound all the combinations starting with
+/// the fixed candidates cheaply.
+///
+/// # Type Parameters
+///
+/// - `T`: Type of the candidates.
+/// - `P`: Type of the pruning function.
+/// - `F`: Type of the function to be applied to each combination.
+///
+/// # Parameters
+///
+/// - `count`: An `Arc<AtomicUsize>` incremented by the number of combinations covered, pruned ones included.
+/// - `slots`: The candidates of each slot, in the order they are fixed.
+/// - `prune`: A function taking the candidates of the first slots and returning whether none of
+///   the combinations starting with them should be visited.
+/// - `func`: A function to be applied to each combination that is not pruned.
+///
+/// # Example
+///
+/// ```rust
+/// use std::sync::{Arc, Mutex, atomic::{AtomicUsize, Ordering}};
+/// use wynn_build_tools::util::branch_and_bound;
+///
+/// let slots = vec![vec![1, 2, 3], vec![10, 20]];
+/// let count = Arc::new(AtomicUsize::new(0));
+/// let visited = Mutex::new(Vec::new());
+///
+/// // the combinations starting with 1 are skipped
+/// branch_and_bound(count.clone(), &slots, |fixed| *fixed[0] == 1, |combination| {
+///     visited.lock().unwrap().push(combination[0] + combination[1]);
+/// });
+///
+/// let mut visited = visited.into_inner().unwrap();
+/// visited.sort();
+/// assert_eq!(visited, [12, 13, 22, 23]);
+/// assert_eq!(count.load(Ordering::Relaxed), 6);
+/// ```
+pub fn branch_and_bound<T, P, F>(count: Arc<AtomicUsize>, slots: &[Vec<T>], prune: P, func: F)
+where
+    T: Sync,
+    P: Fn(&[&T]) -> bool + Sync,
+    F: Fn(&[&T]) + Sync,
+{
+    fn visit<'a, T, P, F>(
+        slots: &'a [Vec<T>],
+        sizes: &[usize],
+        fixed: &mut Vec<&'a T>,
+        count: &AtomicUsize,
+        prune: &P,
+        func: &F,
+    ) where
+        P: Fn(&[&T]) -> bool,
+        F: Fn(&[&T]),
+    {
+        let depth = fixed.len();
+        if prune(fixed) {
+            count.fetch_add(sizes[depth], Ordering::Relaxed);
+        } else if depth == slots.len() {
+            func(fixed);
+            count.fetch_add(1, Ordering::Relaxed);
+        } else {
+            for candidate in &slots[depth] {
+                fixed.push(candidate);
+                visit(slots, sizes, fixed, count, prune, func);
+                fixed.pop();
+            }
+        }
+    }
+
+    // number of combinations below each depth
+    let mut sizes = vec![1; slots.len() + 1];
+    for i in (0..slots.len()).rev() {
+        sizes[i] = sizes[i + 1] * slots[i].len();
+    }
+    let Some(first) = slots.first() else {
+        return;
+    };
+    first.par_iter().for_each(|candidate| {
+        let mut fixed = Vec::with_capacity(slots.len());
+        fixed.push(candidate);
+        visit(slots, &sizes, &mut fixed, &count, &prune, &func);
+    });
+}
+
 /// Selects elements from multiple arrays based on provided indexes.
 ///
 /// # Safety
@@ -125,7 +309,7 @@ where
     TR: 'a + AsRef<T>,
     ATR: 'a + AsRef<[TR]>,
 {
-    let mut result: [MaybeUninit<&'a T>; LEN] = MaybeUninit::uninit_array();
+    let mut result: [MaybeUninit<&'a T>; LEN] = [const { MaybeUninit::uninit() }; LEN];
     for i in 0..LEN {
         result[i].write(arrays[i].as_ref()[*indexes.get_unchecked(i)].as_ref());
     }
@@ -496,6 +680,81 @@ pub fn generate_no_order_combinations<const LEN: usize>(space: usize) -> Vec<[us
 mod tests {
     use super::*;
     use std::{collections::HashSet, hash::Hash};
+    #[test]
+    fn branch_and_bound_works() {
+        use std::sync::Mutex;
+
+        let slots = vec![vec![1, 2], vec![10, 20, 30], vec![100, 200]];
+        let count = Arc::new(AtomicUsize::new(0));
+        let visited = Mutex::new(Vec::new());
+        // no combination starting with 2 or summing over 220
+        branch_and_bound(
+            count.clone(),
+            &slots,
+            |fixed| *fixed[0] == 2 || fixed.iter().copied().sum::<i32>() > 220,
+            |combination| {
+                visited
+                    .lock()
+                    .unwrap()
+                    .push(combination.iter().copied().sum::<i32>())
+            },
+        );
+        let mut visited = visited.into_inner().unwrap();
+        visited.sort();
+        assert_eq!(visited, [111, 121, 131, 211]);
+        assert_eq!(count.load(Ordering::Relaxed), 12);
+    }
+
+    #[test]
+    fn generate_full_combinations_by_segment_works() {
+        use std::sync::Mutex;
+
+        struct Item(i32);
+        impl AsRef<Item> for Item {
+            fn as_ref(&self) -> &Item {
+                self
+            }
+        }
+
+        let arrays: [&[Item]; 3] = [
+            &[Item(1), Item(2)],
+            &[Item(10), Item(20), Item(30)],
+            &[Item(100), Item(200)],
+        ];
+        let count = Arc::new(AtomicUsize::new(0));
+        let visited = Mutex::new(Vec::new());
+        let finished = Mutex::new(Vec::new());
+        // 12 combinations in segments of 5, the last one is partial
+        generate_full_combinations_by_segment(
+            5,
+            count.clone(),
+            &arrays,
+            fastrand::Rng::new(),
+            |segment| segment == 1,
+            |combination: [&Item; 3]| {
+                visited
+                    .lock()
+                    .unwrap()
+                    .push(combination.iter().map(|item| item.0).sum::<i32>())
+            },
+            |segment| finished.lock().unwrap().push(segment),
+        );
+        let mut visited = visited.into_inner().unwrap();
+        visited.sort();
+        // the combinations 5..10 are skipped
+        let mut expected = Vec::new();
+        for i in (0..5).chain(10..12) {
+            let [a, b, c] = map_to_index_space(&[2, 3, 2], i);
+            expected.push(arrays[0][a].0 + arrays[1][b].0 + arrays[2][c].0);
+        }
+        expected.sort();
+        assert_eq!(visited, expected);
+        assert_eq!(count.load(Ordering::Relaxed), 7);
+        let mut finished = finished.into_inner().unwrap();
+        finished.sort();
+        assert_eq!(finished, [0, 2]);
+    }
+
     #[test]
     fn generate_index_works() {
         assert_eq!(map_to_index_space(&[2, 1], 1), [1, 0]);
@@ -0,0 +1,66 @@
+/// 64-bit FNV-1a hash, unlike `DefaultHasher` its output is fixed, so it can identify data saved
+/// across runs and Rust releases
+///
+/// Only `write` is defined by FNV, hash bytes with it instead of `Hash::hash`, whose encoding of
+/// the values is not fixed.
+///
+/// # Example
+///
+/// ```rust
+/// use wynn_build_tools::util::Fnv;
+///
+/// let mut hasher = Fnv::default();
+/// hasher.write(b"a");
+/// assert_eq!(hasher.finish(), 0xaf63dc4c8601ec8c);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Fnv(u64);
+impl Fnv {
+    const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+
+    pub fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(Self::PRIME);
+        }
+    }
+    /// writes a field followed by a separator, so the fields `ab, c` and `a, bc` differ
+    pub fn write_field(&mut self, field: &str) {
+        self.write(field.as_bytes());
+        self.write(&[0]);
+    }
+    pub fn finish(&self) -> u64 {
+        self.0
+    }
+}
+impl Default for Fnv {
+    fn default() -> Self {
+        Self(Self::OFFSET_BASIS)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fnv_works() {
+        let hash = |bytes: &[u8]| {
+            let mut hasher = Fnv::default();
+            hasher.write(bytes);
+            hasher.finish()
+        };
+        // test vectors of the reference implementation
+        assert_eq!(hash(b""), 0xcbf29ce484222325);
+        assert_eq!(hash(b"a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(hash(b"foobar"), 0x85944171f73967e8);
+
+        let fields = |fields: &[&str]| {
+            let mut hasher = Fnv::default();
+            fields.iter().for_each(|v| hasher.write_field(v));
+            hasher.finish()
+        };
+        assert_ne!(fields(&["ab", "c"]), fields(&["a", "bc"]));
+    }
+}
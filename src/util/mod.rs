@@ -1,8 +1,14 @@
 mod combinations;
+mod fnv;
 mod permutations;
+mod shard;
+mod top_k;
 
 pub use combinations::*;
+pub use fnv::*;
 pub use permutations::*;
+pub use shard::*;
+pub use top_k::*;
 
 use std::cmp::{Ordering, Reverse};
 
@@ -0,0 +1,103 @@
+use std::{fmt, str::FromStr};
+
+/// One of `count` disjoint parts of an index space, the indexes are dealt round-robin so every
+/// part covers the whole space evenly
+///
+/// It is written `i/n` with `1 <= i <= n`, e.g. `--shard 2/4` is the second of 4 parts.
+///
+/// # Example
+///
+/// ```rust
+/// use wynn_build_tools::util::Shard;
+///
+/// let shard: Shard = "2/3".parse().unwrap();
+/// assert!(shard.contains(1));
+/// assert!(!shard.contains(2));
+/// assert!(shard.contains(4));
+/// assert_eq!(shard.len_of(5), 2);
+/// assert_eq!(shard.to_string(), "2/3");
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Shard {
+    /// from 0
+    index: usize,
+    count: usize,
+}
+impl Shard {
+    /// `index` is from 0
+    pub fn new(index: usize, count: usize) -> Result<Self, String> {
+        if index >= count {
+            return Err(format!(
+                "shard {} is not one of {} shards",
+                index + 1,
+                count
+            ));
+        }
+        Ok(Self { index, count })
+    }
+    pub fn contains(&self, index: usize) -> bool {
+        index % self.count == self.index
+    }
+    /// the number of indexes of `0..len` in the shard
+    pub fn len_of(&self, len: usize) -> usize {
+        len / self.count + usize::from(len % self.count > self.index)
+    }
+}
+impl FromStr for Shard {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (index, count) = s
+            .split_once('/')
+            .ok_or_else(|| format!("invalid shard `{}`, expected i/n", s))?;
+        let parse = |v: &str| {
+            v.trim()
+                .parse::<usize>()
+                .map_err(|e| format!("invalid shard `{}`: {}", s, e))
+        };
+        let (index, count) = (parse(index)?, parse(count)?);
+        if index == 0 {
+            return Err(format!("invalid shard `{}`, shards are counted from 1", s));
+        }
+        Self::new(index - 1, count)
+    }
+}
+impl fmt::Display for Shard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.index + 1, self.count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shards_are_disjoint() {
+        let shards: Vec<Shard> = (0..3).map(|i| Shard::new(i, 3).unwrap()).collect();
+        for index in 0..10 {
+            assert_eq!(
+                shards.iter().filter(|shard| shard.contains(index)).count(),
+                1
+            );
+        }
+        for len in 0..10 {
+            let lens: Vec<usize> = shards.iter().map(|shard| shard.len_of(len)).collect();
+            assert_eq!(lens.iter().sum::<usize>(), len);
+            for (shard, shard_len) in shards.iter().zip(&lens) {
+                assert_eq!((0..len).filter(|i| shard.contains(*i)).count(), *shard_len);
+            }
+        }
+    }
+
+    #[test]
+    fn parse_works() {
+        assert_eq!("1/1".parse(), Ok(Shard::new(0, 1).unwrap()));
+        assert_eq!("3/4".parse(), Ok(Shard::new(2, 4).unwrap()));
+        assert!("0/4".parse::<Shard>().is_err());
+        assert!("5/4".parse::<Shard>().is_err());
+        assert!("1/0".parse::<Shard>().is_err());
+        assert!("1".parse::<Shard>().is_err());
+        assert!("a/2".parse::<Shard>().is_err());
+    }
+}
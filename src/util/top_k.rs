@@ -0,0 +1,125 @@
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::BinaryHeap,
+};
+
+/// Keeps the `k` items with the highest scores seen so far
+///
+/// The lowest kept score is on top of a min-heap, so a push is `O(log k)` and the check of
+/// [`TopK::accepts`] is `O(1)`.
+///
+/// # Example
+///
+/// ```rust
+/// use wynn_build_tools::util::TopK;
+///
+/// let mut top = TopK::new(2);
+/// top.push(1.0, "a");
+/// top.push(3.0, "b");
+/// top.push(2.0, "c");
+/// assert!(!top.accepts(1.5));
+/// assert_eq!(top.into_sorted_vec(), vec![(3.0, "b"), (2.0, "c")]);
+/// ```
+pub struct TopK<T> {
+    k: usize,
+    heap: BinaryHeap<Reverse<Scored<T>>>,
+}
+impl<T> TopK<T> {
+    pub fn new(k: usize) -> Self {
+        Self {
+            k,
+            heap: BinaryHeap::with_capacity(k + 1),
+        }
+    }
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+    /// the score to beat, `None` until `k` items are kept
+    pub fn min_score(&self) -> Option<f64> {
+        if self.heap.len() < self.k {
+            None
+        } else {
+            self.heap.peek().map(|Reverse(v)| v.score)
+        }
+    }
+    /// whether an item with `score` would be kept, check it before building an expensive item
+    pub fn accepts(&self, score: f64) -> bool {
+        self.k > 0 && self.min_score().is_none_or(|min| score > min)
+    }
+    /// return whether the item is kept
+    pub fn push(&mut self, score: f64, item: T) -> bool {
+        if !self.accepts(score) {
+            return false;
+        }
+        self.heap.push(Reverse(Scored { score, item }));
+        if self.heap.len() > self.k {
+            self.heap.pop();
+        }
+        true
+    }
+    /// (score, item) from the highest score
+    pub fn sorted(&self) -> Vec<(f64, &T)> {
+        let mut items: Vec<(f64, &T)> = self
+            .heap
+            .iter()
+            .map(|Reverse(v)| (v.score, &v.item))
+            .collect();
+        items.sort_by(|a, b| b.0.total_cmp(&a.0));
+        items
+    }
+    /// (score, item) from the highest score
+    pub fn into_sorted_vec(self) -> Vec<(f64, T)> {
+        self.heap
+            .into_sorted_vec()
+            .into_iter()
+            .map(|Reverse(v)| (v.score, v.item))
+            .collect()
+    }
+}
+
+struct Scored<T> {
+    score: f64,
+    item: T,
+}
+impl<T> PartialEq for Scored<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+impl<T> Eq for Scored<T> {}
+impl<T> PartialOrd for Scored<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl<T> Ord for Scored<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.score.total_cmp(&other.score)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn top_k_works() {
+        let mut top = TopK::new(3);
+        assert_eq!(top.min_score(), None);
+        for (score, item) in [(5.0, 'a'), (1.0, 'b'), (7.0, 'c'), (3.0, 'd'), (2.0, 'e')] {
+            top.push(score, item);
+        }
+        assert_eq!(top.len(), 3);
+        assert_eq!(top.min_score(), Some(3.0));
+        assert!(!top.push(3.0, 'f'));
+        assert!(top.push(4.0, 'g'));
+        assert_eq!(top.sorted(), vec![(7.0, &'c'), (5.0, &'a'), (4.0, &'g')]);
+        assert_eq!(
+            top.into_sorted_vec(),
+            vec![(7.0, 'c'), (5.0, 'a'), (4.0, 'g')]
+        );
+    }
+}